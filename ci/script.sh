@@ -9,8 +9,14 @@ main() {
 
     local examples=(
         _llseek
+        affinity
         args
+        auxv
+        backtrace
+        barrier
+        catch_unwind
         chdir
+        condvar
         create
         dup
         env
@@ -18,14 +24,32 @@ main() {
         hashmap
         hello
         instant
+        local_key
         ls
+        mpsc
+        mutex_stress
+        once
         open
+        park
+        pi_mutex
+        poison
         preadwrite
+        relocations
+        runtime
+        rwlock
+        scoped_threads
+        shared_sync
+        stack_guard
+        stack_overflow
         stat
         stderr
+        stdio
         system-time
         tcp_listen_connect
         thread
+        thread_local
+        thread_stacks
+        vdso
         vec
         zero
     )
@@ -0,0 +1,54 @@
+#![feature(linux_thread)]
+
+use std::os::linux::thread::{self, CpuSet, JoinHandleExt, SchedPolicy};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+fn main() {
+    let cpus = thread::affinity().unwrap();
+    assert!(cpus.count() > 0);
+    assert_eq!(thread::available_parallelism().unwrap(), cpus.count());
+
+    // Pin the current thread to one of its CPUs and back
+    let first = (0..thread::CPU_SETSIZE).find(|&cpu| cpus.is_set(cpu)).unwrap();
+    let mut one = CpuSet::new();
+    one.set(first);
+    thread::set_affinity(&one).unwrap();
+    assert_eq!(thread::affinity().unwrap(), one);
+    thread::set_affinity(&cpus).unwrap();
+
+    assert_eq!(thread::sched_policy().unwrap(), SchedPolicy::Other);
+
+    // Unprivileged processes may always lower their priority
+    let nice = thread::nice().unwrap();
+    if nice < 19 {
+        thread::set_nice(nice + 1).unwrap();
+        assert_eq!(thread::nice().unwrap(), nice + 1);
+    }
+
+    let main_tid = thread::tid();
+    assert!(main_tid > 0);
+
+    let done = Arc::new(AtomicBool::new(false));
+    let done2 = done.clone();
+    let handle = std::thread::spawn(move || {
+        while !done2.load(Ordering::SeqCst) {
+            std::thread::park();
+        }
+        thread::tid()
+    });
+
+    // Spawned threads are separate kernel tasks that can be configured from
+    // the outside
+    assert!(handle.tid() > 0);
+    assert!(handle.tid() != main_tid);
+    handle.set_affinity(&one).unwrap();
+    assert_eq!(handle.affinity().unwrap(), one);
+    handle.set_sched_policy(SchedPolicy::Batch).unwrap();
+    assert_eq!(handle.sched_policy().unwrap(), SchedPolicy::Batch);
+
+    let tid = handle.tid();
+    done.store(true, Ordering::SeqCst);
+    handle.thread().unpark();
+    assert_eq!(handle.join().unwrap(), tid);
+}
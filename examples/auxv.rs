@@ -0,0 +1,22 @@
+#![feature(auxv)]
+
+use std::env;
+use std::os::linux::auxv::{self, AT_PAGESZ, AT_RANDOM};
+
+fn main() {
+    let page_size = auxv::getauxval(AT_PAGESZ).unwrap();
+    assert!(page_size.is_power_of_two());
+    assert!(auxv::entries().any(|(type_, value)| type_ == AT_PAGESZ && value == page_size));
+    assert_eq!(auxv::getauxval(!0), None);
+
+    assert!(auxv::getauxval(AT_RANDOM).is_some());
+    assert!(auxv::random().is_some());
+
+    let execfn = auxv::execfn().unwrap();
+    assert!(!execfn.to_bytes().is_empty());
+    assert!(env::current_exe().unwrap().is_absolute());
+
+    for (type_, value) in auxv::entries() {
+        println!("{:2}: {:#x}", type_, value);
+    }
+}
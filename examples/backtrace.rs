@@ -0,0 +1,23 @@
+#![feature(backtrace)]
+
+#[cfg(not(any(target_arch = "aarch64",
+              target_arch = "arm",
+              target_arch = "x86",
+              target_arch = "x86_64")))]
+fn main() {
+}
+
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+fn main() {
+    use std::backtrace::Backtrace;
+
+    let backtrace = Backtrace::new();
+    println!("{:?}", backtrace);
+
+    assert!(backtrace.frames().iter().any(|frame| {
+        frame.name().map(|name| name.starts_with("backtrace::main")).unwrap_or(false)
+    }));
+}
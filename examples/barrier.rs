@@ -0,0 +1,38 @@
+use std::sync::{Arc, Barrier, Mutex};
+use std::thread;
+
+const THREADS: usize = 8;
+const STEPS: usize = 100;
+
+fn main() {
+    // Every thread advances its cell in lockstep with the others: no thread
+    // starts step `n + 1` before all of them are done with step `n`
+    let barrier = Arc::new(Barrier::new(THREADS));
+    let steps = Arc::new(Mutex::new(vec![0; THREADS]));
+
+    let threads: Vec<_> = (0..THREADS).map(|i| {
+        let barrier = barrier.clone();
+        let steps = steps.clone();
+        thread::spawn(move || {
+            let mut leaders = 0;
+            for step in 0..STEPS {
+                steps.lock().unwrap()[i] += 1;
+                if barrier.wait().is_leader() {
+                    leaders += 1;
+                }
+                assert!(steps.lock().unwrap().iter().all(|&s| s == step + 1));
+                barrier.wait();
+            }
+            leaders
+        })
+    }).collect();
+
+    // Exactly one thread is the leader at the end of every step
+    let leaders: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    assert_eq!(leaders, STEPS);
+
+    // A barrier for a single thread never blocks
+    let barrier = Barrier::new(1);
+    assert!(barrier.wait().is_leader());
+    assert!(barrier.wait().is_leader());
+}
@@ -0,0 +1,57 @@
+#[cfg(not(any(target_arch = "aarch64",
+              target_arch = "arm",
+              target_arch = "x86",
+              target_arch = "x86_64")))]
+fn main() {
+}
+
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+fn main() {
+    use std::panic;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::thread;
+
+    static DROPPED: AtomicUsize = AtomicUsize::new(0);
+
+    struct Droppable;
+
+    impl Drop for Droppable {
+        fn drop(&mut self) {
+            DROPPED.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let result = panic::catch_unwind(|| {
+        let _d = Droppable;
+        panic!("oh no!");
+    });
+    // A literal message is the payload as is, a formatted one is a `String`
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<&'static str>(), Some(&"oh no!"));
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 1);
+
+    let result = panic::catch_unwind(|| {
+        panic!("oh no! {}", 42);
+    });
+    let payload = result.unwrap_err();
+    assert_eq!(payload.downcast_ref::<String>().map(|s| &s[..]), Some("oh no! 42"));
+
+    let result = panic::catch_unwind(|| {
+        let _d = Droppable;
+        panic::resume_unwind(Box::new(42));
+    });
+    assert_eq!(result.unwrap_err().downcast_ref::<i32>(), Some(&42));
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 2);
+
+    assert_eq!(panic::catch_unwind(|| 1 + 1).ok(), Some(2));
+
+    let result = thread::spawn(|| {
+        let _d = Droppable;
+        panic!("in a thread");
+    }).join();
+    assert!(result.is_err());
+    assert_eq!(DROPPED.load(Ordering::SeqCst), 3);
+}
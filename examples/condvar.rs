@@ -0,0 +1,58 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn main() {
+    const ITEMS: u32 = 10000;
+    const CONSUMERS: usize = 4;
+
+    // A queue with one producer and several consumers; `None` means the
+    // producer is done
+    let queue = Arc::new((Mutex::new(VecDeque::new()), Condvar::new()));
+
+    let consumers: Vec<_> = (0..CONSUMERS).map(|_| {
+        let queue = queue.clone();
+        thread::spawn(move || {
+            let &(ref lock, ref cvar) = &*queue;
+            let mut sum = 0u64;
+            loop {
+                let mut items = lock.lock().unwrap();
+                while items.is_empty() {
+                    items = cvar.wait(items).unwrap();
+                }
+                match items.pop_front().unwrap() {
+                    Some(item) => sum += item as u64,
+                    None => {
+                        // Leave the marker for the other consumers
+                        items.push_front(None);
+                        cvar.notify_all();
+                        return sum;
+                    }
+                }
+            }
+        })
+    }).collect();
+
+    {
+        let &(ref lock, ref cvar) = &*queue;
+        for item in 1..ITEMS + 1 {
+            lock.lock().unwrap().push_back(Some(item));
+            cvar.notify_one();
+        }
+        lock.lock().unwrap().push_back(None);
+        cvar.notify_all();
+    }
+
+    let total: u64 = consumers.into_iter().map(|c| c.join().unwrap()).sum();
+    assert_eq!(total, (ITEMS as u64) * (ITEMS as u64 + 1) / 2);
+
+    // Nobody notifies, so the wait times out
+    let lock = Mutex::new(());
+    let cvar = Condvar::new();
+    let start = Instant::now();
+    let (_guard, result) = cvar.wait_timeout(lock.lock().unwrap(),
+                                             Duration::from_millis(50)).unwrap();
+    assert!(result.timed_out());
+    assert!(start.elapsed() >= Duration::from_millis(50));
+}
@@ -0,0 +1,33 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+struct Foo(&'static str);
+
+impl Drop for Foo {
+    fn drop(&mut self) {
+        io::stdout().write_all(self.0.as_bytes()).unwrap();
+    }
+}
+
+thread_local!(static COUNTER: RefCell<u32> = RefCell::new(0));
+thread_local!(static FOO: RefCell<Option<Foo>> = RefCell::new(None));
+
+fn spawn() {
+    use std::thread;
+
+    thread::spawn(|| {
+        COUNTER.with(|c| assert_eq!(*c.borrow(), 0));
+        COUNTER.with(|c| *c.borrow_mut() += 2);
+        COUNTER.with(|c| assert_eq!(*c.borrow(), 2));
+        FOO.with(|f| *f.borrow_mut() = Some(Foo("Dropped in the spawned thread\n")));
+    }).join().unwrap();
+}
+
+fn main() {
+    COUNTER.with(|c| *c.borrow_mut() += 1);
+    FOO.with(|f| *f.borrow_mut() = Some(Foo("Dropped in the main thread\n")));
+
+    spawn();
+
+    COUNTER.with(|c| assert_eq!(*c.borrow(), 1));
+}
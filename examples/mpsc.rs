@@ -0,0 +1,80 @@
+use std::sync::mpsc::{self, RecvTimeoutError, TryRecvError, TrySendError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+fn main() {
+    // Many producers, one consumer
+    let (tx, rx) = mpsc::channel();
+    for i in 0..10 {
+        let tx = tx.clone();
+        thread::spawn(move || {
+            for j in 0..100 {
+                tx.send(i * 100 + j).unwrap();
+            }
+        });
+    }
+    drop(tx);
+    let mut received: Vec<u32> = rx.iter().collect();
+    received.sort();
+    assert_eq!(received, (0..1000).collect::<Vec<_>>());
+
+    // Messages of a single sender arrive in order
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for i in 0..1000 {
+            tx.send(i).unwrap();
+        }
+    });
+    assert!(rx.into_iter().eq(0..1000));
+
+    // Nothing to receive yet
+    let (tx, rx) = mpsc::channel::<()>();
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    let start = Instant::now();
+    let timeout = Duration::from_millis(50);
+    assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Timeout));
+    assert!(start.elapsed() >= timeout);
+    drop(tx);
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(rx.recv_timeout(timeout), Err(RecvTimeoutError::Disconnected));
+
+    // Pending messages are still received after the senders are gone
+    let (tx, rx) = mpsc::channel();
+    tx.send(1).unwrap();
+    tx.send(2).unwrap();
+    drop(tx);
+    assert_eq!(rx.try_iter().collect::<Vec<_>>(), [1, 2]);
+    assert!(rx.recv().is_err());
+
+    // Sending fails once the receiver is gone
+    let (tx, rx) = mpsc::channel();
+    drop(rx);
+    assert_eq!(tx.send(1).unwrap_err().0, 1);
+
+    // Bounded channels block senders while the buffer is full
+    let (tx, rx) = mpsc::sync_channel(2);
+    tx.send(1).unwrap();
+    tx.try_send(2).unwrap();
+    assert_eq!(tx.try_send(3), Err(TrySendError::Full(3)));
+    let sender = thread::spawn(move || tx.send(3).unwrap());
+    assert_eq!(rx.iter().take(3).collect::<Vec<_>>(), [1, 2, 3]);
+    sender.join().unwrap();
+
+    // A rendezvous channel hands off each message
+    let (tx, rx) = mpsc::sync_channel(0);
+    assert_eq!(tx.try_send(1), Err(TrySendError::Full(1)));
+    let sender = thread::spawn(move || {
+        for i in 0..100 {
+            tx.send(i).unwrap();
+        }
+    });
+    assert!(rx.iter().eq(0..100));
+    sender.join().unwrap();
+
+    // ... and gives it back if the receiver goes away
+    let (tx, rx) = mpsc::sync_channel(0);
+    let sender = thread::spawn(move || tx.send(1));
+    thread::sleep(Duration::from_millis(50));
+    drop(rx);
+    assert_eq!(sender.join().unwrap().unwrap_err().0, 1);
+}
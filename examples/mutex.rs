@@ -1,10 +1,3 @@
-#[cfg(not(any(target_arch = "aarch64",
-              target_arch = "arm",
-              target_arch = "powerpc",
-              target_arch = "x86",
-              target_arch = "x86_64")))]
-fn main() {}
-
 fn test_mutex() {
   use std::{time, thread};
   use std::sync::{Arc, Mutex};
@@ -78,11 +71,6 @@ fn test_reentrant_mutex() {
   assert_eq!(*data.lock().unwrap(), 5 + 2 * NTHREADS * ITERATIONS);
 }
 
-#[cfg(any(target_arch = "aarch64",
-          target_arch = "arm",
-          target_arch = "powerpc",
-          target_arch = "x86",
-          target_arch = "x86_64"))]
 fn main() {
   test_mutex();
   test_reentrant_mutex();
@@ -0,0 +1,57 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+const THREADS: usize = 32;
+const ITERATIONS: usize = 20_000;
+
+fn main() {
+    // Many threads hammering the same lock; the non-atomic read-modify-write
+    // of the counter only adds up if the lock excludes everyone else
+    let counter = Arc::new(Mutex::new(0));
+    let threads: Vec<_> = (0..THREADS).map(|_| {
+        let counter = counter.clone();
+        thread::spawn(move || {
+            for _ in 0..ITERATIONS {
+                let mut guard = counter.lock().unwrap();
+                assert!(counter.try_lock().is_err());
+                let value = *guard;
+                if value % 1000 == 0 {
+                    // Give the other threads time to pile up on the lock
+                    thread::yield_now();
+                }
+                *guard = value + 1;
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(*counter.lock().unwrap(), THREADS * ITERATIONS);
+
+    // Long critical sections make the waiters go to sleep in the kernel; a
+    // lost wakeup would hang here
+    let mutex = Arc::new(Mutex::new(Vec::new()));
+    let threads: Vec<_> = (0..THREADS).map(|i| {
+        let mutex = mutex.clone();
+        thread::spawn(move || {
+            for _ in 0..10 {
+                let mut guard = mutex.lock().unwrap();
+                guard.push(i);
+                thread::sleep(Duration::from_millis(1));
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(mutex.lock().unwrap().len(), THREADS * 10);
+
+    // `try_lock` fails while the lock is held, and succeeds afterwards
+    let mutex = Mutex::new(());
+    {
+        let _guard = mutex.lock().unwrap();
+        assert!(mutex.try_lock().is_err());
+    }
+    assert!(mutex.try_lock().is_ok());
+}
@@ -0,0 +1,42 @@
+#![feature(once_poison)]
+
+use std::panic;
+use std::sync::{Once, ONCE_INIT};
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+use std::thread;
+
+static CALLS: AtomicUsize = ATOMIC_USIZE_INIT;
+
+fn main() {
+    static INIT: Once = ONCE_INIT;
+
+    // Many threads race to initialize, but only one runs the closure and all
+    // of them see its effect
+    let threads: Vec<_> = (0..10).map(|_| {
+        thread::spawn(|| {
+            INIT.call_once(|| {
+                thread::yield_now();
+                CALLS.fetch_add(1, Ordering::SeqCst);
+            });
+            assert_eq!(CALLS.load(Ordering::SeqCst), 1);
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    INIT.call_once(|| panic!("called twice"));
+
+    // A panic poisons the `Once`
+    static POISONED: Once = ONCE_INIT;
+    assert!(panic::catch_unwind(|| POISONED.call_once(|| panic!("init failed"))).is_err());
+    assert!(panic::catch_unwind(|| POISONED.call_once(|| {})).is_err());
+
+    // ... unless the poisoning is ignored
+    let mut forced = false;
+    POISONED.call_once_force(|state| {
+        assert!(state.poisoned());
+        forced = true;
+    });
+    assert!(forced);
+    POISONED.call_once(|| panic!("called after completion"));
+}
@@ -0,0 +1,32 @@
+fn main() {
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    assert_eq!(thread::current().name(), Some("main"));
+
+    // A token left by `unpark` makes the next `park` return immediately
+    thread::current().unpark();
+    thread::park();
+
+    // `park_timeout` returns once the timeout has elapsed
+    let start = Instant::now();
+    thread::park_timeout(Duration::from_millis(50));
+    assert!(start.elapsed() >= Duration::from_millis(50));
+
+    let flag = Arc::new(AtomicBool::new(false));
+    let flag2 = flag.clone();
+
+    let handle = thread::Builder::new().name("parked".to_owned()).spawn(move || {
+        assert_eq!(thread::current().name(), Some("parked"));
+        while !flag2.load(Ordering::SeqCst) {
+            thread::park();
+        }
+    }).unwrap();
+
+    thread::sleep(Duration::from_millis(10));
+    flag.store(true, Ordering::SeqCst);
+    handle.thread().unpark();
+    handle.join().unwrap();
+}
@@ -0,0 +1,52 @@
+#![feature(linux_sync)]
+
+use std::mem;
+use std::os::linux::sync::PiMutex;
+use std::sync::Arc;
+use std::thread;
+
+fn main() {
+    // Contended locking goes through the kernel, which hands the lock over
+    let counter = Arc::new(PiMutex::new(0));
+    let threads: Vec<_> = (0..8).map(|_| {
+        let counter = counter.clone();
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                let mut guard = counter.lock().unwrap();
+                let value = *guard;
+                if value % 100 == 0 {
+                    thread::yield_now();
+                }
+                *guard = value + 1;
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+    assert_eq!(*counter.lock().unwrap(), 80_000);
+
+    let mutex = PiMutex::new(());
+    {
+        let _guard = mutex.lock().unwrap();
+        assert!(mutex.try_lock().is_err());
+    }
+    assert!(mutex.try_lock().is_ok());
+
+    // A thread that exits while holding the lock poisons it, instead of
+    // leaving it locked forever
+    let mutex = Arc::new(PiMutex::new(1));
+    let m = mutex.clone();
+    let owner = thread::spawn(move || {
+        let mut guard = m.lock().unwrap();
+        *guard = 2;
+        mem::forget(guard);
+    });
+    owner.join().unwrap();
+    match mutex.lock() {
+        Err(poisoned) => assert_eq!(*poisoned.into_inner(), 2),
+        Ok(_) => panic!("the previous owner died, the mutex should be poisoned"),
+    }
+    assert!(mutex.is_poisoned());
+    assert!(mutex.try_lock().is_err());
+}
@@ -0,0 +1,75 @@
+use std::panic;
+use std::sync::{Arc, Mutex, PoisonError, RwLock, TryLockError};
+use std::sync::atomic::{AtomicBool, Ordering, ATOMIC_BOOL_INIT};
+use std::thread;
+
+static DROPPED_WHILE_PANICKING: AtomicBool = ATOMIC_BOOL_INIT;
+
+struct Probe;
+
+impl Drop for Probe {
+    fn drop(&mut self) {
+        DROPPED_WHILE_PANICKING.store(thread::panicking(), Ordering::SeqCst);
+    }
+}
+
+fn main() {
+    assert!(!thread::panicking());
+
+    // Destructors run during unwinding see `panicking()`
+    assert!(panic::catch_unwind(|| {
+        let _probe = Probe;
+        panic!("unwinding");
+    }).is_err());
+    assert!(DROPPED_WHILE_PANICKING.load(Ordering::SeqCst));
+    // ... and the count is reset once the panic has been caught
+    assert!(!thread::panicking());
+    drop(Probe);
+    assert!(!DROPPED_WHILE_PANICKING.load(Ordering::SeqCst));
+
+    // A thread that panics while holding a lock poisons it
+    let mutex = Arc::new(Mutex::new(0));
+    let m = mutex.clone();
+    assert!(thread::spawn(move || {
+        let mut guard = m.lock().unwrap();
+        *guard += 1;
+        panic!("poisoning the mutex");
+    }).join().is_err());
+    assert!(mutex.is_poisoned());
+    match mutex.lock() {
+        Err(poisoned) => assert_eq!(*poisoned.into_inner(), 1),
+        Ok(_) => panic!("the mutex should be poisoned"),
+    }
+    match mutex.try_lock() {
+        Err(TryLockError::Poisoned(poisoned)) => assert_eq!(**poisoned.get_ref(), 1),
+        _ => panic!("the mutex should be poisoned"),
+    }
+    assert_eq!(*mutex.lock().unwrap_or_else(PoisonError::into_inner), 1);
+
+    // Panicking without holding the lock doesn't
+    let mutex = Arc::new(Mutex::new(0));
+    let m = mutex.clone();
+    assert!(thread::spawn(move || {
+        *m.lock().unwrap() += 1;
+        panic!("after unlocking");
+    }).join().is_err());
+    assert!(!mutex.is_poisoned());
+    assert_eq!(*mutex.lock().unwrap(), 1);
+
+    // Only writers poison a `RwLock`
+    let rwlock = Arc::new(RwLock::new(0));
+    let r = rwlock.clone();
+    assert!(thread::spawn(move || {
+        let _guard = r.read().unwrap();
+        panic!("while reading");
+    }).join().is_err());
+    assert!(!rwlock.is_poisoned());
+    let r = rwlock.clone();
+    assert!(thread::spawn(move || {
+        let _guard = r.write().unwrap();
+        panic!("while writing");
+    }).join().is_err());
+    assert!(rwlock.is_poisoned());
+    assert!(rwlock.read().is_err());
+    assert!(rwlock.write().is_err());
+}
@@ -0,0 +1,33 @@
+// Statics that hold addresses, which need to be relocated when the executable
+// is position independent (see the `-pie` targets)
+
+trait Greet {
+    fn greet(&self) -> &'static str;
+}
+
+struct World;
+
+impl Greet for World {
+    fn greet(&self) -> &'static str {
+        "Hello, world!"
+    }
+}
+
+fn greeting() -> &'static str {
+    GREETING
+}
+
+static GREETING: &'static str = "Hello, world!";
+static GREET: fn() -> &'static str = greeting;
+static GREETER: &'static (Greet + Sync) = &World;
+static NUMBERS: &'static [&'static u32] = &[&1, &2, &3];
+
+fn main() {
+    assert_eq!(GREETING, "Hello, world!");
+    assert_eq!(GREET(), GREETING);
+    assert_eq!(GREETER.greet(), GREETING);
+    assert_eq!(NUMBERS.iter().map(|&&n| n).sum::<u32>(), 6);
+
+    println!("{}", GREETING);
+    println!("main is at {:p}", main as fn() as *const u8);
+}
@@ -0,0 +1,82 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Read;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::process::{self, Command};
+use std::thread;
+
+const ENOEXEC: i32 = 8;
+
+struct PrintOnDrop;
+
+impl Drop for PrintOnDrop {
+    fn drop(&mut self) {
+        print!(", by a TLS destructor");
+    }
+}
+
+thread_local!(static PRINT_ON_DROP: PrintOnDrop = PrintOnDrop);
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() == 3 {
+        return child(&args[1], &args[2]);
+    }
+
+    // `lang_start` names the main thread
+    assert_eq!(thread::current().name(), Some("main"));
+
+    // Output still sitting in the stdout buffer when `main` returns or the
+    // process exits is flushed
+    check("return", Some(0), "printed by main, by a TLS destructor");
+    check("exit", Some(3), "printed before exit");
+    // A panic in `main` is caught and reported with exit status 101
+    check("panic", Some(101), "thread 'main' panicked at 'boom'");
+}
+
+// Runs this program again with `mode` and checks its exit status and what it
+// printed
+fn check(mode: &str, code: Option<i32>, output: &str) {
+    let path = env::temp_dir().join(format!("steed-runtime-{}", mode));
+    File::create(&path).unwrap();
+
+    let status = match Command::new(env::current_exe().unwrap()).arg(mode).arg(&path).status() {
+        Ok(status) => status,
+        // A foreign binary can't be executed under user mode emulation
+        Err(ref e) if e.raw_os_error() == Some(ENOEXEC) => {
+            println!("skipped: can't run a child process here");
+            return;
+        }
+        Err(e) => panic!("failed to run the child: {}", e),
+    };
+
+    let mut printed = String::new();
+    File::open(&path).unwrap().read_to_string(&mut printed).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(status.code(), code);
+    assert!(printed.contains(output), "unexpected output: {:?}", printed);
+}
+
+fn child(mode: &str, path: &str) {
+    // Replace stdout and stderr with the file: each one takes the lowest
+    // free descriptor
+    for &fd in &[1, 2] {
+        drop(unsafe { File::from_raw_fd(fd) });
+        let file = OpenOptions::new().append(true).open(path).unwrap();
+        assert_eq!(file.into_raw_fd(), fd);
+    }
+
+    match mode {
+        "return" => {
+            PRINT_ON_DROP.with(|_| {});
+            print!("printed by main");
+        }
+        "exit" => {
+            print!("printed before exit");
+            process::exit(3);
+        }
+        "panic" => panic!("boom"),
+        _ => unreachable!(),
+    }
+}
@@ -1,57 +1,69 @@
-#[cfg(not(any(target_arch = "aarch64",
-              target_arch = "arm",
-              target_arch = "powerpc",
-              target_arch = "x86",
-              target_arch = "x86_64")))]
-fn main() {}
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
 
-#[cfg(any(target_arch = "aarch64",
-          target_arch = "arm",
-          target_arch = "powerpc",
-          target_arch = "x86",
-          target_arch = "x86_64"))]
-fn main() {
-    use std::{time, thread};
-    use std::sync::{Arc, RwLock};
-
-    let mut threads = vec![];
-    const NTHREADS: i32 = 100;
-    const ITERATIONS: i32 = 100;
-
-    let lock = Arc::new(RwLock::new(5));
-    for _ in 0..NTHREADS {
-      let lock = lock.clone();
-      let t = thread::spawn(move ||
-        for _ in 0..ITERATIONS {
-          // sleep for 1 ms
-          thread::sleep(time::Duration::from_millis(1));
-          {
-            let r1 = lock.read().unwrap();
-            assert!(*r1 >= 5);
+const READERS: usize = 8;
 
-            match lock.try_read() {
-              Ok(r2) => assert!(*r2 >= 5),
-              Err(_) => {},
+fn main() {
+    // Readers overlap so that the lock is always read locked by someone;
+    // a waiting writer must still get through
+    let lock = Arc::new(RwLock::new(0));
+    let stop = Arc::new(AtomicBool::new(false));
+    let readers: Vec<_> = (0..READERS).map(|_| {
+        let lock = lock.clone();
+        let stop = stop.clone();
+        thread::spawn(move || {
+            let mut last = 0;
+            while !stop.load(Ordering::SeqCst) {
+                let value = *lock.read().unwrap();
+                assert!(value >= last);
+                last = value;
+                thread::sleep(Duration::from_millis(1));
+                let _guard = lock.read().unwrap();
+                thread::sleep(Duration::from_millis(1));
             }
-          }
-
-          {
-            //let mut w = lock.try_write().unwrap();
-            //*w += 1;
-          }
+        })
+    }).collect();
 
-          {
-            let mut w = lock.write().unwrap();
-            *w += 1;
-          }
-      });
-
-      threads.push(t);
+    thread::sleep(Duration::from_millis(20));
+    for i in 1..101 {
+        *lock.write().unwrap() = i;
+    }
+    stop.store(true, Ordering::SeqCst);
+    for reader in readers {
+        reader.join().unwrap();
     }
+    assert_eq!(*lock.read().unwrap(), 100);
 
-    for t in threads {
-        t.join().unwrap();
+    // Writers exclude each other
+    let lock = Arc::new(RwLock::new(0));
+    let writers: Vec<_> = (0..READERS).map(|_| {
+        let lock = lock.clone();
+        thread::spawn(move || {
+            for _ in 0..10_000 {
+                let mut guard = lock.write().unwrap();
+                let value = *guard;
+                *guard = value + 1;
+            }
+        })
+    }).collect();
+    for writer in writers {
+        writer.join().unwrap();
     }
+    assert_eq!(*lock.read().unwrap(), READERS * 10_000);
 
-    assert_eq!(*lock.read().unwrap(), 5 + NTHREADS * ITERATIONS);
+    // `try_read` and `try_write` never block
+    let lock = RwLock::new(());
+    {
+        let _read = lock.read().unwrap();
+        assert!(lock.try_read().is_ok());
+        assert!(lock.try_write().is_err());
+    }
+    {
+        let _write = lock.write().unwrap();
+        assert!(lock.try_read().is_err());
+        assert!(lock.try_write().is_err());
+    }
+    assert!(lock.try_write().is_ok());
 }
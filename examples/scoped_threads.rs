@@ -0,0 +1,109 @@
+#![feature(thread_scope)]
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Read;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::panic;
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+const ENOEXEC: i32 = 8;
+const SIGABRT: i32 = 6;
+
+struct PanicOnDrop;
+
+impl Drop for PanicOnDrop {
+    fn drop(&mut self) {
+        panic!("dropped");
+    }
+}
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() == 2 {
+        return child(&args[1]);
+    }
+
+    let data: Vec<u64> = (1..1001).collect();
+    let mut sums = vec![0; 4];
+    let finished = AtomicUsize::new(0);
+
+    // The threads borrow `data` and `finished`, and each one gets a mutable
+    // chunk of `sums`
+    thread::scope(|s| {
+        for (chunk, sum) in data.chunks(250).zip(sums.iter_mut()) {
+            let finished = &finished;
+            s.spawn(move || {
+                *sum = chunk.iter().sum();
+                finished.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+    });
+    // All the threads have been joined
+    assert_eq!(finished.load(Ordering::SeqCst), 4);
+    assert_eq!(sums.iter().sum::<u64>(), 500500);
+
+    // Results can be returned through the handles
+    let len = thread::scope(|s| {
+        let handle = s.spawn(|| data.len());
+        handle.join().unwrap()
+    });
+    assert_eq!(len, data.len());
+
+    // A panic of a joined thread is returned by `join`
+    thread::scope(|s| {
+        assert!(s.spawn(|| panic!("joined")).join().is_err());
+    });
+
+    // A panic of a thread that wasn't joined is propagated by `scope`
+    let result = panic::catch_unwind(|| {
+        thread::scope(|s| {
+            s.spawn(|| panic!("not joined"));
+        });
+    });
+    assert!(result.is_err());
+
+    // A panic while dropping the payload of a thread that wasn't joined
+    // can't be propagated: the process aborts instead of `scope` waiting
+    // forever for the thread
+    check_drop_panic();
+}
+
+fn check_drop_panic() {
+    let path = env::temp_dir().join("steed-scoped-threads");
+    File::create(&path).unwrap();
+
+    let status = match Command::new(env::current_exe().unwrap()).arg(&path).status() {
+        Ok(status) => status,
+        // A foreign binary can't be executed under user mode emulation
+        Err(ref e) if e.raw_os_error() == Some(ENOEXEC) => {
+            println!("skipped: can't run a child process here");
+            return;
+        }
+        Err(e) => panic!("failed to run the child: {}", e),
+    };
+
+    let mut output = String::new();
+    File::open(&path).unwrap().read_to_string(&mut output).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.contains("thread result panicked on drop"),
+            "unexpected output: {:?}",
+            output);
+    assert_eq!(status.signal(), Some(SIGABRT));
+}
+
+fn child(path: &str) {
+    // Replace stderr with the file: it takes the lowest free descriptor
+    drop(unsafe { File::from_raw_fd(2) });
+    let stderr = OpenOptions::new().write(true).open(path).unwrap();
+    assert_eq!(stderr.into_raw_fd(), 2);
+
+    thread::scope(|s| {
+        s.spawn(|| panic::resume_unwind(Box::new(PanicOnDrop)));
+    });
+    unreachable!();
+}
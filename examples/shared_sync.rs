@@ -0,0 +1,100 @@
+#![feature(linux_sync)]
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::mem;
+use std::os::linux::sync::{Semaphore, SharedCondvar, SharedMemory, SharedMutex};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+#[repr(C)]
+struct Shared {
+    counter: SharedMutex<u64>,
+    done: SharedCondvar,
+    jobs: Semaphore,
+}
+
+fn main() {
+    const THREADS: u64 = 4;
+    const ITERATIONS: u64 = 10_000;
+
+    // Two separate mappings of the same file, like two processes would have.
+    // They are at different addresses, so the threads using one mapping can
+    // only wake up the threads using the other through the shared futex
+    // operations, which identify a futex by its file and offset.
+    let path = env::temp_dir().join("steed-shared-sync");
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&path)
+        .unwrap();
+    file.set_len(mem::size_of::<Shared>() as u64).unwrap();
+    let ours = Arc::new(unsafe { SharedMemory::<Shared>::from_file(&file).unwrap() });
+    let theirs = Arc::new(unsafe { SharedMemory::<Shared>::from_file(&file).unwrap() });
+    drop(file);
+    fs::remove_file(&path).unwrap();
+    assert!(&**ours as *const Shared != &**theirs as *const Shared);
+
+    // The file is zero-filled: unlocked, no notifications, no jobs
+    assert_eq!(*ours.counter.lock(), 0);
+    assert_eq!(ours.jobs.value(), 0);
+    assert!(!ours.jobs.try_wait());
+    assert!(!ours.jobs.wait_timeout(Duration::from_millis(10)));
+
+    // Workers block on the semaphore until jobs are posted through the other
+    // mapping, then increment the counter under the mutex
+    let workers: Vec<_> = (0..THREADS).map(|_| {
+        let theirs = theirs.clone();
+        thread::spawn(move || {
+            theirs.jobs.wait();
+            for _ in 0..ITERATIONS {
+                let mut counter = theirs.counter.lock();
+                let value = *counter;
+                if value % 100 == 0 {
+                    thread::yield_now();
+                }
+                *counter = value + 1;
+            }
+            theirs.done.notify_all();
+        })
+    }).collect();
+
+    thread::sleep(Duration::from_millis(10));
+    for _ in 0..THREADS {
+        ours.jobs.post();
+    }
+
+    // Wait for the workers through our mapping
+    {
+        let mut counter = ours.counter.lock();
+        while *counter != THREADS * ITERATIONS {
+            counter = ours.done.wait(counter);
+        }
+    }
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    assert_eq!(ours.jobs.value(), 0);
+
+    let (guard, result) = ours.done.wait_timeout(ours.counter.lock(),
+                                                 Duration::from_millis(10));
+    assert!(result.timed_out());
+    assert!(theirs.counter.try_lock().is_none());
+    drop(guard);
+    assert!(theirs.counter.try_lock().is_some());
+
+    // An anonymous mapping, as it would be shared with forked children
+    let semaphore = SharedMemory::new(Semaphore::new(2)).unwrap();
+    assert!(semaphore.try_wait());
+    semaphore.wait();
+    assert!(!semaphore.try_wait());
+    semaphore.post();
+    assert_eq!(semaphore.value(), 1);
+
+    // Zero-sized values still get a mapping
+    let unit = SharedMemory::new(()).unwrap();
+    assert_eq!(*unit, ());
+}
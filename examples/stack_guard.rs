@@ -0,0 +1,34 @@
+fn main() {
+    use std::fs::File;
+    use std::io::Read;
+    use std::thread;
+
+    // Returns the (start, end, perms) of every mapping of the process
+    fn mappings() -> Vec<(usize, usize, String)> {
+        let mut maps = String::new();
+        File::open("/proc/self/maps").unwrap().read_to_string(&mut maps).unwrap();
+        maps.lines()
+            .map(|line| {
+                let mut fields = line.split(' ');
+                let mut range = fields.next().unwrap().split('-');
+                let start = usize::from_str_radix(range.next().unwrap(), 16).unwrap();
+                let end = usize::from_str_radix(range.next().unwrap(), 16).unwrap();
+                (start, end, fields.next().unwrap().to_owned())
+            })
+            .collect()
+    }
+
+    thread::spawn(|| {
+        let local = 0u8;
+        let sp = &local as *const u8 as usize;
+
+        let maps = mappings();
+        let stack = maps.iter().position(|&(start, end, _)| start <= sp && sp < end).unwrap();
+        assert!(stack > 0);
+
+        // The stack is right above an inaccessible guard page
+        let (_, guard_end, ref perms) = maps[stack - 1];
+        assert_eq!(guard_end, maps[stack].0);
+        assert_eq!(perms, "---p");
+    }).join().unwrap();
+}
@@ -0,0 +1,77 @@
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::Read;
+use std::os::unix::io::{FromRawFd, IntoRawFd};
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+use std::ptr;
+use std::thread;
+
+const ENOEXEC: i32 = 8;
+const SIGABRT: i32 = 6;
+
+fn main() {
+    let args: Vec<_> = env::args().collect();
+    if args.len() == 3 {
+        return child(&args[1], &args[2]);
+    }
+
+    check("main", "thread 'main' has overflowed its stack");
+    check("thread", "thread 'overflow' has overflowed its stack");
+}
+
+// Runs this program again to overflow the stack of the thread given by `mode`
+// and checks what the child printed to stderr and that it aborted
+fn check(mode: &str, message: &str) {
+    let path = env::temp_dir().join(format!("steed-stack-overflow-{}", mode));
+    File::create(&path).unwrap();
+
+    let status = match Command::new(env::current_exe().unwrap()).arg(mode).arg(&path).status() {
+        Ok(status) => status,
+        // A foreign binary can't be executed under user mode emulation
+        Err(ref e) if e.raw_os_error() == Some(ENOEXEC) => {
+            println!("skipped: can't run a child process here");
+            return;
+        }
+        Err(e) => panic!("failed to run the child: {}", e),
+    };
+
+    let mut output = String::new();
+    File::open(&path).unwrap().read_to_string(&mut output).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert!(output.contains(message), "unexpected output: {:?}", output);
+    assert_eq!(status.signal(), Some(SIGABRT));
+}
+
+fn child(mode: &str, path: &str) {
+    // Replace stderr with the file: it takes the lowest free descriptor
+    drop(unsafe { File::from_raw_fd(2) });
+    let stderr = OpenOptions::new().write(true).open(path).unwrap();
+    assert_eq!(stderr.into_raw_fd(), 2);
+
+    match mode {
+        "main" => {
+            recurse(0);
+        }
+        "thread" => {
+            thread::Builder::new()
+                .name("overflow".to_owned())
+                .spawn(|| recurse(0))
+                .unwrap()
+                .join()
+                .unwrap();
+        }
+        _ => unreachable!(),
+    }
+}
+
+fn recurse(depth: usize) -> usize {
+    // Less than a page per frame, so the first access past the end of the
+    // stack lands in the guard page
+    let buf = [depth as u8; 1024];
+    if depth == usize::max_value() {
+        return 0;
+    }
+    unsafe { ptr::read_volatile(&buf[0]) as usize + recurse(depth + 1) }
+}
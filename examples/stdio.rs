@@ -0,0 +1,35 @@
+use std::io::{self, Write};
+use std::thread;
+
+fn main() {
+    // Each thread holds the lock for a whole block of lines, so the blocks
+    // come out in one piece instead of interleaved with the other threads'
+    let threads: Vec<_> = (0..4).map(|i| {
+        thread::spawn(move || {
+            let stdout = io::stdout();
+            let mut handle = stdout.lock();
+            for j in 0..3 {
+                writeln!(handle, "thread {}: line {}", i, j).unwrap();
+            }
+        })
+    }).collect();
+    for thread in threads {
+        thread.join().unwrap();
+    }
+
+    // The lock is reentrant: `print!` works while the current thread holds it
+    {
+        let stdout = io::stdout();
+        let mut handle = stdout.lock();
+        write!(handle, "locked, ").unwrap();
+        println!("and printed");
+    }
+
+    let stderr = io::stderr();
+    let mut handle = stderr.lock();
+    writeln!(io::stderr(), "stderr is not buffered").unwrap();
+    handle.flush().unwrap();
+
+    // A partial line is buffered until the process exits
+    print!("flushed at exit");
+}
@@ -0,0 +1,48 @@
+#![feature(attr_literals)]
+#![feature(repr_align)]
+#![feature(thread_local)]
+
+// In `.tdata`
+#[thread_local]
+static mut COUNTER: u32 = 42;
+
+// In `.tbss`
+#[thread_local]
+static mut ZEROED: u64 = 0;
+
+// Over-aligned, to check the layout of the TLS block
+#[repr(align(64))]
+struct Aligned(u8);
+
+#[thread_local]
+static mut ALIGNED: Aligned = Aligned(7);
+
+fn check() {
+    unsafe {
+        assert_eq!(COUNTER, 42);
+        assert_eq!(ZEROED, 0);
+        assert_eq!(ALIGNED.0, 7);
+        assert_eq!(&ALIGNED as *const Aligned as usize % 64, 0);
+
+        COUNTER += 1;
+        ZEROED += 1;
+        ALIGNED.0 += 1;
+
+        assert_eq!(COUNTER, 43);
+        assert_eq!(ZEROED, 1);
+        assert_eq!(ALIGNED.0, 8);
+    }
+}
+
+fn main() {
+    use std::thread;
+
+    check();
+
+    // Every thread starts with a fresh copy
+    thread::spawn(check).join().unwrap();
+    thread::spawn(check).join().unwrap();
+
+    // The main thread's copy is untouched
+    assert_eq!(unsafe { COUNTER }, 43);
+}
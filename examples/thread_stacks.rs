@@ -0,0 +1,54 @@
+fn main() {
+    use std::fs::File;
+    use std::io::Read;
+    use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    const THREADS: usize = 100;
+    // Every thread stack is at least 2 MB
+    const SLACK: usize = 16 * 1024 * 1024;
+
+    // Returns the total size of the mappings of the process
+    fn mapped() -> usize {
+        let mut maps = String::new();
+        File::open("/proc/self/maps").unwrap().read_to_string(&mut maps).unwrap();
+        maps.lines()
+            .map(|line| {
+                let mut range = line.split(' ').next().unwrap().split('-');
+                let start = usize::from_str_radix(range.next().unwrap(), 16).unwrap();
+                let end = usize::from_str_radix(range.next().unwrap(), 16).unwrap();
+                end - start
+            })
+            .sum()
+    }
+
+    static DONE: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let before = mapped();
+
+    // `join` frees the stack
+    for _ in 0..THREADS {
+        thread::spawn(|| {}).join().unwrap();
+    }
+    assert!(mapped() < before + SLACK);
+
+    // Dropping the `JoinHandle` detaches the thread, which frees its own stack
+    for _ in 0..THREADS {
+        thread::spawn(|| {
+            DONE.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    while DONE.load(Ordering::SeqCst) < THREADS {
+        thread::yield_now();
+    }
+
+    // The threads may not have exited yet
+    for _ in 0..100 {
+        if mapped() < before + SLACK {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("the stacks of detached threads weren't freed");
+}
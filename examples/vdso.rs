@@ -0,0 +1,18 @@
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // Exercise the fast path: on architectures with a vDSO `clock_gettime`
+    // these don't enter the kernel
+    let start = Instant::now();
+    let mut last = start;
+    for _ in 0..1_000_000 {
+        let now = Instant::now();
+        assert!(now >= last);
+        last = now;
+    }
+    println!("1M Instant::now() calls: {:?}", start.elapsed());
+
+    let since_epoch = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    // Some time after 2017-01-01
+    assert!(since_epoch.as_secs() > 1_483_228_800);
+}
@@ -0,0 +1,158 @@
+//! Backtraces of the current thread
+//!
+//! A `Backtrace` is a snapshot of the call stack, with the same information
+//! that is printed when a thread panics while the `RUST_BACKTRACE` environment
+//! variable is set.
+//!
+//! ```
+//! #![feature(backtrace)]
+//!
+//! use std::backtrace::Backtrace;
+//!
+//! let backtrace = Backtrace::new();
+//! println!("{:?}", backtrace);
+//! ```
+
+#![unstable(feature = "backtrace", issue = "0")]
+
+use ffi::OsString;
+use fmt;
+use path::{Path, PathBuf};
+use str;
+use sys::backtrace::{self, Object};
+use sys::ext::ffi::OsStringExt;
+use sys_common::backtrace::{demangle, HEX_WIDTH};
+
+/// A captured backtrace of the current thread
+pub struct Backtrace {
+    frames: Vec<BacktraceFrame>,
+}
+
+/// A frame of a `Backtrace`
+///
+/// Symbol information is only available if the executable has a symbol table
+/// and file and line information only if it was compiled with debug info.
+pub struct BacktraceFrame {
+    ip: usize,
+    symbol_address: Option<usize>,
+    name: Option<String>,
+    filename: Option<PathBuf>,
+    lineno: Option<u32>,
+}
+
+impl Backtrace {
+    /// Captures a backtrace of the current thread and symbolizes it.
+    ///
+    /// The innermost frames belong to the implementation of this function.
+    ///
+    /// The backtrace is empty on architectures whose stacks we can't walk yet.
+    #[inline(never)]
+    pub fn new() -> Backtrace {
+        let mut ips = vec![];
+        backtrace::trace(&mut |ip| {
+            ips.push(ip);
+            true
+        });
+
+        let object = Object::open().ok();
+        let object = object.as_ref();
+        Backtrace {
+            frames: ips.into_iter()
+                .map(|ip| {
+                    let symbol = object.and_then(|o| o.symbol(ip));
+                    let location = object.and_then(|o| o.location(ip));
+                    BacktraceFrame {
+                        ip: ip,
+                        symbol_address: symbol.map(|(address, _)| address),
+                        name: symbol.and_then(|(_, name)| demangled(name)),
+                        filename: location.as_ref().map(|&(ref file, _)| {
+                            PathBuf::from(OsString::from_vec(file.clone()))
+                        }),
+                        lineno: location.map(|(_, line)| line),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns the frames of this backtrace, innermost first.
+    pub fn frames(&self) -> &[BacktraceFrame] {
+        &self.frames
+    }
+}
+
+impl BacktraceFrame {
+    /// Returns the instruction pointer of this frame.
+    ///
+    /// This is the address of the instruction the frame will resume at, i.e.
+    /// the return address of the call the frame is in.
+    pub fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// Returns the address of the function this frame is in.
+    pub fn symbol_address(&self) -> Option<usize> {
+        self.symbol_address
+    }
+
+    /// Returns the (demangled) name of the function this frame is in.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_ref().map(|name| &name[..])
+    }
+
+    /// Returns the source file of the call this frame is in.
+    pub fn filename(&self) -> Option<&Path> {
+        self.filename.as_ref().map(|filename| filename.as_path())
+    }
+
+    /// Returns the line of the call this frame is in.
+    pub fn lineno(&self) -> Option<u32> {
+        self.lineno
+    }
+}
+
+fn demangled(name: &[u8]) -> Option<String> {
+    let name = match str::from_utf8(name) {
+        Ok(name) => name,
+        Err(_) => return None,
+    };
+    let mut demangled = vec![];
+    if demangle(&mut demangled, name).is_err() {
+        return None;
+    }
+    String::from_utf8(demangled).ok()
+}
+
+impl fmt::Debug for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "stack backtrace:")?;
+        for (i, frame) in self.frames.iter().enumerate() {
+            writeln!(f,
+                     "  {:2}: {:3$?} - {}",
+                     i,
+                     frame.ip as *const u8,
+                     frame.name().unwrap_or("<unknown>"),
+                     HEX_WIDTH)?;
+            if let (Some(filename), Some(lineno)) = (frame.filename(), frame.lineno()) {
+                writeln!(f,
+                         "{:3$}      at {}:{}",
+                         "",
+                         filename.display(),
+                         lineno,
+                         HEX_WIDTH)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for BacktraceFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BacktraceFrame")
+            .field("ip", &(self.ip as *const u8))
+            .field("name", &self.name())
+            .field("filename", &self.filename())
+            .field("lineno", &self.lineno())
+            .finish()
+    }
+}
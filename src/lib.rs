@@ -13,11 +13,14 @@
 #![feature(dropck_parametricity)]
 #![feature(generic_param_attrs)]
 #![feature(exact_size_is_empty)]
+#![feature(fn_traits)]
 #![feature(fnbox)]
 #![feature(fused)]
 #![feature(global_asm)]
 #![feature(heap_api)]
+#![feature(hint_core_should_pause)]
 #![feature(int_error_internals)]
+#![feature(integer_atomics)]
 #![feature(lang_items)]
 #![feature(macro_reexport)]
 #![feature(naked_functions)]
@@ -32,7 +35,9 @@
 #![feature(slice_patterns)]
 #![feature(staged_api)]
 #![feature(str_internals)]
+#![feature(thread_local)]
 #![feature(try_from)]
+#![feature(unboxed_closures)]
 #![feature(unicode)]
 #![feature(unique)]
 #![no_std]
@@ -49,8 +54,8 @@ use prelude::v1::*;
 // imported by the compiler (via our #[no_std] attribute) In this case we just
 // add a new crate name so we can attach the reexports to it.
 #[macro_reexport(assert, assert_eq, assert_ne, debug_assert, debug_assert_eq,
-                 debug_assert_ne, panic, unreachable, unimplemented, write,
-                 writeln, try)]
+                 debug_assert_ne, unreachable, unimplemented, write, writeln,
+                 try)]
 extern crate core as __core;
 
 extern crate alloc;
@@ -152,9 +157,12 @@ pub use std_unicode::char;
 mod macros;
 
 pub mod sync;
+#[macro_use]
+pub mod thread;
 
 // Rust 1.16.0
 pub mod ascii;
+pub mod backtrace;
 // Rust 1.16.0 (incomplete)
 pub mod collections;
 // Rust 1.16.0 (incomplete)
@@ -173,13 +181,14 @@ pub mod memchr;
 pub mod net;
 // Rust 1.16.0
 pub mod num;
+// Rust 1.16.0
+pub mod panic;
 // Rust 1.16.0 (no tests)
 pub mod os;
 // Rust 1.16.0
 pub mod path;
 // Rust 1.16.0 (incomplete)
 pub mod process;
-pub mod thread;
 // Rust 1.16.0
 pub mod time;
 
@@ -190,27 +199,10 @@ pub mod rt;
 
 mod ctypes;
 mod linux;
-#[cfg(not(test))]
+// Rust 1.16.0: src/libpanic_unwind
+mod panic_unwind;
 mod panicking;
 mod rand;
 mod sys;
 mod sys_common;
 mod libc;
-
-// NOTE These two are "undefined" symbols that LLVM emits but that, AFAIK, we
-// never use
-#[cfg(not(test))]
-#[doc(hidden)]
-#[no_mangle]
-#[unstable(feature = "steed", issue = "0")]
-pub unsafe extern "C" fn __aeabi_unwind_cpp_pr0() {
-    intrinsics::unreachable()
-}
-
-#[cfg(not(test))]
-#[doc(hidden)]
-#[no_mangle]
-#[unstable(feature = "steed", issue = "0")]
-pub unsafe extern "C" fn __aeabi_unwind_cpp_pr1() {
-    intrinsics::unreachable()
-}
@@ -63,8 +63,42 @@ mod not_test {
         1:
         ret
     ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // x0
+    //  size: size_t)      // x1
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        mov x8,#215 // MUNMAP
+        svc #0
+
+        mov x0,#0  // status
+        mov x8,#93 // EXIT
+        svc #0
+    ");
 }
 
+// The TLS block is above the thread pointer (variant I), after a 16 byte
+// thread control block
+pub const TLS_ABOVE_TP: bool = true;
+pub const GAP_ABOVE_TP: usize = 16;
+pub const TP_OFFSET: usize = 0;
+pub const DTP_OFFSET: usize = 0;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    asm!("msr tpidr_el0,$0"::"r"(thread_data)::"volatile");
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    let result;
+    asm!("mrs $0,tpidr_el0":"=r"(result));
+    result
 }
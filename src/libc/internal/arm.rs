@@ -1,3 +1,6 @@
+use libc::*;
+use linux;
+
 #[cfg(not(test))]
 mod not_test {
     // Syscall number is passed in r7, syscall arguments in r0, r1, r2, r3, r4.
@@ -92,8 +95,47 @@ mod not_test {
         @ Return from the function.
         mov pc,lr
     ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // r0
+    //  size: size_t)      // r1
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack. We don't return, so we don't need to save
+    // r7.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        mov r7,#91 @ MUNMAP
+        svc 0
+
+        mov r0,#0 @ status
+        mov r7,#1 @ EXIT
+        svc 0
+    ");
 }
 
+// The TLS block is above the thread pointer (variant I), after an 8 byte
+// thread control block
+pub const TLS_ABOVE_TP: bool = true;
+pub const GAP_ABOVE_TP: usize = 8;
+pub const TP_OFFSET: usize = 0;
+pub const DTP_OFFSET: usize = 0;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    let result = linux::set_tls(thread_data as c_ulong);
+    if result < 0 {
+        panic!("set_thread_pointer: set_tls: {}", result);
+    }
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    // TPIDRURO, which the kernel sets to the value passed to `set_tls`
+    let result;
+    asm!("mrc p15,0,$0,c13,c0,3":"=r"(result));
+    result
 }
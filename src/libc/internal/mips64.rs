@@ -1,3 +1,133 @@
+use libc::*;
+use linux;
+
+#[cfg(not(test))]
+mod not_test {
+    // Syscall number is passed in $2 (v0), syscall arguments in $4, $5, $6, $7,
+    // $8. The arguments are
+    // (flags: c_ulong,           // $4
+    //  child_stack: *mut c_void, // $5
+    //  ptid: *mut c_int,         // $6
+    //  newtls: c_ulong,          // $7
+    //  ctid: *mut c_int)         // $8
+    //
+    // $2 gets the return value, $7 is set if it's an error.
+    //
+    // The n64 ABI passes arguments in $4 to $11. The arguments are
+    // (fn_: extern "C" fn(*mut c_void) -> *mut c_void, // $4
+    //  child_stack: *mut c_void,                       // $5
+    //  flags: c_ulong,                                 // $6
+    //  arg: *mut c_void,                               // $7
+    //  ptid: *mut pid_t,                               // $8
+    //  newtls: *mut c_void,                            // $9
+    //  ctid: *mut pid_t)                               // $10
+    //
+    // Both ABIs return the function result in $2.
+    //
+    // This means we need the following moves:
+    // $6  -> $4 // flags
+    // $5  -> $5 // child_stack
+    // $8  -> $6 // ptid
+    // $9  -> $7 // newtls
+    // $10 -> $8 // ctid
+    //
+    // We save `fn_` and `arg` on the child stack.
+    //
+    // musl: src/thread/mips64/clone.s
+
+    global_asm!("
+        .set push
+        .set noreorder
+
+        .globl __steed_clone
+        __steed_clone:
+
+        # Save `fn_` and `arg` on the child stack.
+        and $5,$5,-16
+        dsubu $5,$5,16
+        sd $4,0($5)
+        sd $7,8($5)
+
+        move $4,$6  # flags
+                    # child_stack
+        move $6,$8  # ptid
+        move $7,$9  # newtls
+        move $8,$10 # ctid
+        li $2,5055  # CLONE
+        syscall
+
+        # Negate the result if there was an error.
+        beq $7,$0,1f
+        nop
+        jr $ra
+        dsubu $2,$0,$2
+
+        # CLONE returns 0 in the child thread, return if we're the parent.
+        1:
+        beq $2,$0,2f
+        nop
+        jr $ra
+        nop
+
+        2:
+        ld $25,0($sp) # fn_
+        ld $4,8($sp)  # arg
+        jalr $25
+        nop
+
+        move $4,$2  # status
+        li $2,5058  # EXIT
+        syscall
+
+        .set pop
+    ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // $4
+    //  size: size_t)      // $5
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+    //
+    // musl: src/thread/mips64/__unmapself.s
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        li $2,5011 # MUNMAP
+        syscall
+
+        li $4,0    # status
+        li $2,5058 # EXIT
+        syscall
+    ");
+}
+
+// The TLS block starts 0x7000 bytes below the thread pointer (variant I), the
+// thread pointer is biased so that more of the block can be reached with 16-bit
+// signed offsets
+pub const TLS_ABOVE_TP: bool = true;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0x7000;
+pub const DTP_OFFSET: usize = 0x8000;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    let result = linux::set_thread_area(thread_data as c_ulong);
+    if result < 0 {
+        panic!("set_thread_pointer: set_thread_area: {}", result);
+    }
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    // UserLocal, emulated by the kernel on CPUs that don't have it
+    let result;
+    asm!(".set push
+          .set mips64r2
+          rdhwr $0,$$29
+          .set pop"
+         :"=r"(result));
+    result
 }
@@ -11,13 +11,188 @@
 use super::*;
 use self::arch::*;
 
-pub struct Buffer(thread);
+use cmp;
+use linux;
+use linux::elf::PT_TLS;
+use sys::phdr;
 
-pub unsafe fn init_main_thread(buffer: *mut Buffer) {
-    let buffer: *mut thread = &mut (*buffer).0;
-    *buffer = thread {
-        this: buffer,
-        thread_id: -1,
+// Thread-local storage
+//
+// Every thread gets a copy of the executable's `PT_TLS` segment, its TLS
+// block, which the code the compiler generates for `#[thread_local]` statics
+// finds relative to the thread pointer. Because we are statically linked
+// there's only this one block, at a fixed offset from the thread pointer,
+// so there's no need for dynamic thread vectors. See "ELF Handling For
+// Thread-Local Storage" by Ulrich Drepper for the layouts.
+//
+// We allocate the TLS block together with our `thread` struct, in what we
+// call the TLS area:
+//
+// - Variant I (`TLS_ABOVE_TP`): the `thread` struct, then the thread pointer,
+//   then a gap (the ABI's thread control block), then the TLS block. On some
+//   architectures the thread pointer is biased by `TP_OFFSET`.
+// - Variant II: the TLS block, then the thread pointer which points to the
+//   `thread` struct. Its first word must point to itself.
+//
+// musl: src/env/__init_tls.c
+struct TlsImage {
+    // The initialization image, `.tdata`
+    image: *const u8,
+    len: usize,
+    // The size of the TLS block, `.tdata` plus `.tbss`
+    size: usize,
+    align: usize,
+}
+
+static mut TLS: TlsImage = TlsImage {
+    image: 0 as *const u8,
+    len: 0,
+    size: 0,
+    align: 1,
+};
+
+fn round_up(n: usize, align: usize) -> usize {
+    (n + align - 1) & !(align - 1)
+}
+
+unsafe fn init_tls_image() {
+    if let Some(tls) = phdr::program_headers().iter().find(|p| p.p_type == PT_TLS) {
+        TLS = TlsImage {
+            image: phdr::load_bias().wrapping_add(tls.p_vaddr as usize) as *const u8,
+            len: tls.p_filesz as usize,
+            size: tls.p_memsz as usize,
+            align: cmp::max(tls.p_align as usize, 1),
+        };
+    }
+}
+
+// The alignment of the TLS area
+unsafe fn tls_area_align() -> usize {
+    cmp::max(TLS.align, mem::align_of::<thread>())
+}
+
+// Offset of the TLS block from the start of the thread control block, i.e.
+// from the unbiased thread pointer. The linker computes the same offsets.
+unsafe fn tls_block_offset() -> isize {
+    if TLS_ABOVE_TP {
+        round_up(GAP_ABOVE_TP, TLS.align) as isize
+    } else {
+        -(round_up(TLS.size, TLS.align) as isize)
+    }
+}
+
+/// Returns the size of the memory needed by `init_tls_area`.
+pub unsafe fn tls_area_size() -> usize {
+    let align = tls_area_align();
+    // We may need to skip up to `align - 1` bytes to align the area
+    align - 1 +
+        if TLS_ABOVE_TP {
+            round_up(mem::size_of::<thread>(), align) + round_up(GAP_ABOVE_TP, TLS.align) +
+            TLS.size
+        } else {
+            round_up(round_up(TLS.size, TLS.align), align) + mem::size_of::<thread>()
+        }
+}
+
+/// Lays out a TLS area in `area`, which must be `tls_area_size()` bytes of
+/// zeroed memory, and returns its `thread` and thread pointer.
+pub unsafe fn init_tls_area(area: *mut u8) -> (*mut thread, *mut ()) {
+    let align = tls_area_align();
+    let start = round_up(area as usize, align);
+
+    // The start of the thread control block
+    let tcb = if TLS_ABOVE_TP {
+        start + round_up(mem::size_of::<thread>(), align)
+    } else {
+        start + round_up(round_up(TLS.size, TLS.align), align)
+    };
+    let thread = if TLS_ABOVE_TP {
+        (tcb - mem::size_of::<thread>()) as *mut thread
+    } else {
+        tcb as *mut thread
     };
-    set_thread_pointer(buffer as *mut _);
+    (*thread).this = thread;
+
+    // `.tbss` is already zeroed
+    let block = (tcb as isize + tls_block_offset()) as *mut u8;
+    ptr::copy_nonoverlapping(TLS.image, block, TLS.len);
+
+    (thread, (tcb + TP_OFFSET) as *mut ())
 }
+
+// The TLS block of the current thread
+#[cfg(not(target_arch = "x86"))]
+unsafe fn tls_block() -> *mut u8 {
+    let tcb = thread_pointer() as usize - TP_OFFSET;
+    (tcb as isize + tls_block_offset()) as *mut u8
+}
+
+/// Returns the `thread` struct of the calling thread.
+pub unsafe fn thread_self() -> *mut thread {
+    let tcb = thread_pointer() as usize - TP_OFFSET;
+    if TLS_ABOVE_TP {
+        (tcb - mem::size_of::<thread>()) as *mut thread
+    } else {
+        tcb as *mut thread
+    }
+}
+
+/// Sets up the `thread` struct and the thread-local storage of the main
+/// thread.
+pub unsafe fn init_main_thread() {
+    init_tls_image();
+
+    let map = mmap(ptr::null_mut(),
+                   tls_area_size(),
+                   PROT_READ | PROT_WRITE,
+                   MAP_PRIVATE | MAP_ANON,
+                   -1,
+                   0);
+    // musl: src/internal/__syscall_ret.c
+    if map as usize > -4096isize as usize {
+        panic!("init_main_thread: mmap: {}", map as isize);
+    }
+
+    let (thread, tp) = init_tls_area(map as *mut u8);
+    (*thread).thread_id = gettid();
+    set_thread_pointer(tp);
+}
+
+// The argument of `__tls_get_addr`
+#[repr(C)]
+pub struct tls_index {
+    // Always 1, the executable
+    #[allow(dead_code)]
+    ti_module: usize,
+    ti_offset: usize,
+}
+
+// Called by code compiled for the general dynamic TLS model that the linker
+// couldn't relax. There's only one module, the executable, whose block is the
+// TLS block.
+//
+// x86 has its own calling convention for this (`___tls_get_addr`) and the
+// linker always relaxes it.
+#[cfg(not(target_arch = "x86"))]
+#[cfg_attr(not(test), no_mangle)]
+pub unsafe extern "C" fn __tls_get_addr(ti: *const tls_index) -> *mut c_void {
+    tls_block().offset((*ti).ti_offset.wrapping_add(DTP_OFFSET) as isize) as *mut c_void
+}
+
+// Returning from a signal handler jumps to the restorer, which must do the
+// `RT_SIGRETURN` syscall. The kernel provides one (in the vDSO or the vector
+// page) everywhere except on x86_64, where we have to bring our own.
+#[cfg(target_arch = "x86_64")]
+pub unsafe fn set_restorer(act: &mut sigaction) {
+    extern {
+        // Defined in internal/x86_64.rs
+        #[link_name = "__steed_restore_rt"]
+        fn restore_rt();
+    }
+
+    act.sa_flags |= linux::SA_RESTORER;
+    act.sa_restorer = restore_rt as usize;
+}
+
+#[cfg(not(target_arch = "x86_64"))]
+pub unsafe fn set_restorer(_act: &mut sigaction) {}
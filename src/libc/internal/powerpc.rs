@@ -86,8 +86,43 @@ mod not_test {
 
         blr
     ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // r3
+    //  size: size_t)      // r4
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        li 0,91 # SYS_MUNMAP
+        sc
+
+        li 3,0 # status
+        li 0,1 # SYS_EXIT
+        sc
+    ");
 }
 
+// The TLS block starts 0x7000 bytes below the thread pointer (variant I), the
+// thread pointer is biased so that more of the block can be reached with 16-bit
+// signed offsets
+pub const TLS_ABOVE_TP: bool = true;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0x7000;
+pub const DTP_OFFSET: usize = 0x8000;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    asm!("mr 2,$0"::"r"(thread_data):"r2":"volatile");
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    let result;
+    asm!("mr $0,2":"=r"(result));
+    result
 }
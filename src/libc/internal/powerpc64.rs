@@ -1,3 +1,188 @@
+#[cfg(not(test))]
+mod not_test {
+    // Syscall number is passed in r0, syscall arguments in r3, r4, r5, r6, r7.
+    // The arguments are
+    // (flags: c_ulong,           // r3
+    //  child_stack: *mut c_void, // r4
+    //  ptid: *mut c_int,         // r5
+    //  newtls: c_ulong,          // r6
+    //  ctid: *mut c_int)         // r7
+    //
+    // r3 gets the return value, the summary overflow bit is set if it's an
+    // error.
+    //
+    // The calling convention passes arguments in registers, from r3 to r9.
+    // (fn_: extern "C" fn(*mut c_void) -> *mut c_void, // r3
+    //  child_stack: *mut c_void,                       // r4
+    //  flags: c_ulong,                                 // r5
+    //  arg: *mut c_void,                               // r6
+    //  ptid: *mut pid_t,                               // r7
+    //  newtls: *mut c_void,                            // r8
+    //  ctid: *mut pid_t)                               // r9
+    //
+    // Both ABIs return the function result in r3.
+    //
+    // This means we need the following moves:
+    // r5 -> r3 // flags
+    // r4 -> r4 // child_stack
+    // r7 -> r5 // ptid
+    // r8 -> r6 // newtls
+    // r9 -> r7 // ctid
+    //
+    // We save `fn_` and `arg` in the initial stack frame of the child.
+    //
+    // Big endian powerpc64 uses the ELFv1 ABI, where a function symbol names a
+    // function descriptor (entry point, TOC, environment) in `.opd`, and so
+    // does a function pointer. Little endian powerpc64 uses ELFv2, which
+    // doesn't have descriptors and wants the address of the function in r12
+    // when calling it through a pointer. The initial stack frames also
+    // differ in size.
+    //
+    // musl: src/thread/powerpc64/clone.s
+
+    #[cfg(target_endian = "big")]
+    global_asm!("
+        .section .opd,\"aw\"
+        .align 3
+
+        .globl __steed_clone
+        .type __steed_clone,@function
+        __steed_clone:
+        .quad .L__steed_clone,.TOC.@tocbase,0
+
+        .globl __steed_unmapself
+        .type __steed_unmapself,@function
+        __steed_unmapself:
+        .quad .L__steed_unmapself,.TOC.@tocbase,0
+
+        .text
+
+        .L__steed_clone:
+
+        # Create the initial stack frame of the child.
+        clrrdi 4,4,4
+        li 0,0
+        stdu 0,-112(4)
+
+        # Save `fn_` and `arg`.
+        std 3,8(4)
+        std 6,16(4)
+
+        mr 3,5   # flags
+                 # child_stack
+        mr 5,7   # ptid
+        mr 6,8   # newtls
+        mr 7,9   # ctid
+        li 0,120 # CLONE
+        sc
+
+        # Negate the result if there was an error.
+        bns+ 1f
+        neg 3,3
+
+        # CLONE returns 0 in the child thread, return if we're the parent.
+        1:
+        cmpwi cr7,3,0
+        bnelr cr7
+
+        ld 3,16(1)  # arg
+        ld 12,8(1)  # fn_, a function descriptor
+        ld 0,0(12)
+        ld 2,8(12)
+        mtctr 0
+        bctrl
+
+        li 0,1 # EXIT
+               # status
+        sc
+
+        # See `__steed_unmapself` below
+        .L__steed_unmapself:
+
+        li 0,91 # MUNMAP
+        sc
+
+        li 3,0 # status
+        li 0,1 # EXIT
+        sc
+    ");
+
+    #[cfg(target_endian = "little")]
+    global_asm!("
+        .globl __steed_clone
+        __steed_clone:
+
+        # Create the initial stack frame of the child.
+        clrrdi 4,4,4
+        li 0,0
+        stdu 0,-32(4)
+
+        # Save `fn_` and `arg`.
+        std 3,8(4)
+        std 6,16(4)
+
+        mr 3,5   # flags
+                 # child_stack
+        mr 5,7   # ptid
+        mr 6,8   # newtls
+        mr 7,9   # ctid
+        li 0,120 # CLONE
+        sc
+
+        # Negate the result if there was an error.
+        bns+ 1f
+        neg 3,3
+
+        # CLONE returns 0 in the child thread, return if we're the parent.
+        1:
+        cmpwi cr7,3,0
+        bnelr cr7
+
+        ld 3,16(1)  # arg
+        ld 12,8(1)  # fn_
+        mtctr 12
+        bctrl
+
+        li 0,1 # EXIT
+               # status
+        sc
+
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        li 0,91 # MUNMAP
+        sc
+
+        li 3,0 # status
+        li 0,1 # EXIT
+        sc
+    ");
+
+    // `__steed_unmapself` unmaps the stack of the calling thread, and then
+    // exits. The arguments are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // r3
+    //  size: size_t)      // r4
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+}
+
+// The TLS block starts 0x7000 bytes below the thread pointer (variant I), the
+// thread pointer is biased so that more of the block can be reached with 16-bit
+// signed offsets
+pub const TLS_ABOVE_TP: bool = true;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0x7000;
+pub const DTP_OFFSET: usize = 0x8000;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    asm!("mr 13,$0"::"r"(thread_data):"r13":"volatile");
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    let result;
+    asm!("mr $0,13":"=r"(result));
+    result
 }
@@ -1,3 +1,119 @@
+#[cfg(not(test))]
+mod not_test {
+    // Syscall number is passed in %g1, syscall arguments in %o0, %o1, %o2, %o3,
+    // %o4. The arguments are
+    // (flags: c_ulong,           // %o0
+    //  child_stack: *mut c_void, // %o1
+    //  ptid: *mut c_int,         // %o2
+    //  newtls: c_ulong,          // %o3
+    //  ctid: *mut c_int)         // %o4
+    //
+    // %o0 gets the return value, the carry flag is set if it's an error. %o1
+    // is 1 in the child thread and 0 in the parent.
+    //
+    // The calling convention passes arguments in %o0 to %o5 and then on the
+    // stack. After `save`, which gives us a new register window, they are in
+    // %i0 to %i5, and in the caller's frame, at the stack bias (2047) plus the
+    // 176 bytes of the register save area and argument slots.
+    // (fn_: extern "C" fn(*mut c_void) -> *mut c_void, // %i0
+    //  child_stack: *mut c_void,                       // %i1
+    //  flags: c_ulong,                                 // %i2
+    //  arg: *mut c_void,                               // %i3
+    //  ptid: *mut pid_t,                               // %i4
+    //  newtls: *mut c_void,                            // %i5
+    //  ctid: *mut pid_t)                               // [%fp+2223]
+    //
+    // Both ABIs return the function result in %o0.
+    //
+    // This means we need the following moves:
+    // %i2          -> %o0 // flags
+    // %i1          -> %o1 // child_stack, minus a stack frame and the bias
+    // %i4          -> %o2 // ptid
+    // %i5          -> %o3 // newtls
+    // [%fp+2223]   -> %o4 // ctid
+    //
+    // We save `fn_` in %g2 and `arg` in %g3.
+    //
+    // glibc: sysdeps/unix/sysv/linux/sparc/sparc64/clone.S
+
+    global_asm!("
+        .globl __steed_clone
+        __steed_clone:
+
+        save %sp,-192,%sp
+
+        mov %i0,%g2 ! fn_
+        mov %i3,%g3 ! arg
+
+        mov %i2,%o0         ! flags
+        and %i1,-16,%o1     ! child_stack
+        sub %o1,2239,%o1    ! 192 + 2047
+        mov %i4,%o2         ! ptid
+        mov %i5,%o3         ! newtls
+        ldx [%fp+2223],%o4  ! ctid
+        mov 217,%g1         ! CLONE
+        ta 0x6d
+
+        ! Negate the result if there was an error.
+        bcs,pn %xcc,2f
+        nop
+
+        ! Return if we're the parent.
+        brnz,pn %o1,1f
+        nop
+        ret
+        restore %o0,%g0,%o0
+
+        1:
+        ! Mark the lowest stack frame
+        mov %g0,%fp
+        call %g2  ! fn_
+        mov %g3,%o0 ! arg, in the delay slot
+
+        mov 1,%g1 ! EXIT
+                  ! status
+        ta 0x6d
+
+        2:
+        sub %g0,%o0,%o0
+        ret
+        restore %o0,%g0,%o0
+    ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // %o0
+    //  size: size_t)      // %o1
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        mov 73,%g1 ! MUNMAP
+        ta 0x6d
+
+        mov 0,%o0 ! status
+        mov 1,%g1 ! EXIT
+        ta 0x6d
+    ");
+}
+
+// The TLS block is below the thread pointer (variant II)
+pub const TLS_ABOVE_TP: bool = false;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0;
+pub const DTP_OFFSET: usize = 0;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
-    let _ = thread_data; // TODO(steed, #127): Set thread-local pointer.
+    asm!("mov $0,%g7"::"r"(thread_data):"g7":"volatile");
+}
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    let result;
+    asm!("mov %g7,$0":"=r"(result));
+    result
 }
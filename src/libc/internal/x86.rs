@@ -123,8 +123,35 @@ mod not_test {
 
         ret
     ");
+
+    // Unmaps the stack of the calling thread, and then exits.
+    //
+    // (base: *mut c_void, // 4(%esp)
+    //  size: size_t)      // 8(%esp)
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack. We don't return, so we don't need to save
+    // any registers.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        mov 4(%esp),%ebx # base
+        mov 8(%esp),%ecx # size
+        mov $91,%eax     # MUNMAP
+        int $0x80
+
+        xor %ebx,%ebx # status
+        mov $1,%eax   # EXIT
+        int $0x80
+    ");
 }
 
+// The TLS block is below the thread pointer (variant II)
+pub const TLS_ABOVE_TP: bool = false;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
     let mut user_desc = linux::user_desc {
         entry_number: -1i32 as u32,
@@ -141,3 +168,10 @@ pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
     asm!("mov $0,%gs"::"r"(((user_desc.entry_number << 3) | 3) as u16)::"volatile");
 }
 
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    // The first word of the thread control block points to itself
+    let result;
+    asm!("mov %gs:0,$0":"=r"(result));
+    result
+}
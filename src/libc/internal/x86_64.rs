@@ -82,11 +82,54 @@ mod not_test {
         1:
         ret
     ");
+
+    // Unmaps the stack of the calling thread, and then exits. The arguments
+    // are already where MUNMAP expects them.
+    //
+    // (base: *mut c_void, // %rdi
+    //  size: size_t)      // %rsi
+    //
+    // Once the stack is gone, we can't touch it anymore, so this has to be
+    // done without using the stack.
+    global_asm!("
+        .globl __steed_unmapself
+        __steed_unmapself:
+
+        mov $11,%rax # MUNMAP
+        syscall
+
+        xor %rdi,%rdi # status
+        mov $60,%rax  # EXIT
+        syscall
+    ");
+
+    // The restorer of our signal handlers, see `set_restorer`
+    global_asm!("
+        .globl __steed_restore_rt
+        __steed_restore_rt:
+
+        mov $15,%rax # RT_SIGRETURN
+        syscall
+    ");
 }
 
+// The TLS block is below the thread pointer (variant II)
+pub const TLS_ABOVE_TP: bool = false;
+pub const GAP_ABOVE_TP: usize = 0;
+pub const TP_OFFSET: usize = 0;
+pub const DTP_OFFSET: usize = 0;
+
 pub unsafe fn set_thread_pointer(thread_data: *mut ()) {
     let result = linux::arch_prctl(linux::ARCH_SET_FS, thread_data as c_ulong);
     if result < 0 {
         panic!("set_thread_pointer: arch_prctl: {}", result);
     }
 }
+
+#[inline(always)]
+pub unsafe fn thread_pointer() -> *mut () {
+    // The first word of the thread control block points to itself
+    let result;
+    asm!("mov %fs:0,$0":"=r"(result));
+    result
+}
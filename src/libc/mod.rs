@@ -1,12 +1,5 @@
 #![allow(non_camel_case_types)]
 
-#![cfg_attr(not(any(target_arch = "aarch64",
-                    target_arch = "arm",
-                    target_arch = "powerpc",
-                    target_arch = "x86",
-                    target_arch = "x86_64")),
-            allow(unused))]
-
 use borrow::Cow;
 use cmp;
 use collections::HashMap;
@@ -15,6 +8,8 @@ use linux;
 use mem;
 use memchr::memchr;
 use ptr;
+use sync::{Once, ONCE_INIT};
+use sync::atomic::{AtomicUsize, Ordering};
 
 pub mod internal;
 
@@ -22,9 +17,11 @@ pub use ctypes::*;
 pub use linux::errno::*;
 
 pub use linux::{gid_t, in_addr, in6_addr, ip_mreq, ipv6_mreq, off_t, off64_t};
-pub use linux::{pid_t, sa_family_t, sockaddr, sockaddr_in, sockaddr_in6};
-pub use linux::{sockaddr_storage, sockaddr_un, socklen_t, stat64, suseconds_t};
-pub use linux::{time_t, timespec, timeval, uid_t};
+pub use linux::{pid_t, rlimit64, sa_family_t, sched_param, sigaction};
+pub use linux::{sighandler_t};
+pub use linux::{sockaddr, sockaddr_in, sockaddr_in6, sockaddr_storage};
+pub use linux::{siginfo_t, sigset_t, sockaddr_un, socklen_t, stack_t, stat64};
+pub use linux::{suseconds_t, time_t, timespec, timeval, uid_t};
 
 pub use linux::{AF_INET, AF_INET6, AF_UNIX};
 pub use linux::{CLONE_CHILD_CLEARTID, CLONE_FILES, CLONE_FS};
@@ -38,28 +35,38 @@ pub use linux::{IP_MULTICAST_TTL, IP_TTL};
 pub use linux::{IPV6_ADD_MEMBERSHIP, IPV6_DROP_MEMBERSHIP};
 pub use linux::{IPV6_MULTICAST_LOOP, IPV6_V6ONLY};
 pub use linux::{IPPROTO_IP, IPPROTO_IPV6, IPPROTO_TCP};
-pub use linux::{MAP_ANONYMOUS, MAP_PRIVATE};
+pub use linux::{MAP_ANONYMOUS, MAP_PRIVATE, MAP_SHARED};
 pub use linux::{MSG_NOSIGNAL};
 pub use linux::{O_ACCMODE, O_APPEND, O_CLOEXEC, O_CREAT, O_DIRECTORY, O_EXCL};
 pub use linux::{O_LARGEFILE, O_NONBLOCK, O_PATH, O_RDONLY, O_RDWR, O_TRUNC};
 pub use linux::{O_WRONLY};
-pub use linux::{PROT_READ, PROT_WRITE};
+pub use linux::{PRIO_PROCESS};
+pub use linux::{PROT_NONE, PROT_READ, PROT_WRITE};
+pub use linux::{RLIM64_INFINITY, RLIMIT_STACK};
 pub use linux::{S_IFMT, S_IFSOCK, S_IFLNK, S_IFREG, S_IFBLK, S_IFDIR, S_IFCHR};
 pub use linux::{S_IFIFO};
+pub use linux::{SCHED_BATCH, SCHED_FIFO, SCHED_IDLE, SCHED_OTHER, SCHED_RR};
+pub use linux::{SCHED_RESET_ON_FORK};
+pub use linux::{SA_ONSTACK, SA_SIGINFO, SIG_DFL, SIGABRT, SIGBUS, SIGSEGV};
+pub use linux::{SIGSTKSZ};
 pub use linux::{SHUT_RD, SHUT_RDWR, SHUT_WR};
 pub use linux::{SO_BROADCAST, SO_ERROR, SO_RCVTIMEO, SO_REUSEADDR};
 pub use linux::{SO_SNDTIMEO};
 pub use linux::{SOCK_CLOEXEC, SOCK_DGRAM, SOCK_STREAM};
 pub use linux::{SOL_SOCKET};
+pub use linux::{SS_DISABLE};
 pub use linux::{SEEK_CUR, SEEK_END, SEEK_SET};
 pub use linux::{TCP_NODELAY};
 
 pub use linux::{accept, accept4, bind, chdir, close, connect, exit_group};
 pub use linux::{fdatasync, fstat64, fsync, ftruncate64, getcwd, getpeername};
-pub use linux::{getsockname, getsockopt, ioctl, link, listen, lstat64, mmap};
-pub use linux::{nanosleep, prctl, pread64, pwrite64, read, recvfrom, rename};
-pub use linux::{rmdir, sched_yield, send, sendto, setsockopt, socket};
-pub use linux::{socketpair, shutdown, symlink, unlink, write};
+pub use linux::{getpriority, getsockname, getsockopt, gettid, ioctl, link};
+pub use linux::{listen, lstat64, mmap, mprotect, munmap, nanosleep, prctl};
+pub use linux::{pread64, prlimit64, pwrite64, read, recvfrom, rename, rmdir};
+pub use linux::{sched_getaffinity, sched_getparam, sched_getscheduler};
+pub use linux::{sched_setaffinity, sched_setscheduler, sched_yield, send};
+pub use linux::{sendto, setpriority, setsockopt, sigaltstack, socket};
+pub use linux::{socketpair, shutdown, symlink, tkill, unlink, write};
 
 pub type mode_t = u32;
 
@@ -90,7 +97,6 @@ pub struct EnvVal {
 
 static mut ENV_PTR: *const *const u8 = 0 as *const *const u8;
 static mut ENV: *mut HashMap<&'static [u8], EnvVal> = 0 as *mut _;
-#[allow(unused)]
 static mut AUXVAL: *const usize = 0 as *const usize;
 
 fn parse_env_str(input: &'static CStr) -> Option<(&'static [u8], &'static [u8])> {
@@ -118,23 +124,56 @@ unsafe fn make_static(s: &[u8]) -> &'static [u8] {
 pub unsafe fn _init(argc: isize, argv: *const *const u8) {
     ENV_PTR = argv.offset(argc + 1) as *const *const u8;
     let mut ptr = ENV_PTR;
-    ENV = Box::into_raw(Box::new(HashMap::new()));
     while !(*ptr).is_null() {
-        if let Some((key, value)) = parse_env_str(CStr::from_ptr(*ptr as *const _)) {
-            if !(*ENV).contains_key(key) {
-                (*ENV).insert(key, EnvVal {
-                    key: Cow::from(key),
-                    value: Cow::from(value),
-                });
-            }
-        }
         ptr = ptr.offset(1);
     }
     AUXVAL = ptr.offset(1) as *const usize;
 }
 
+// The environment is only parsed into a map when it's first accessed
+unsafe fn env_map() -> &'static mut HashMap<&'static [u8], EnvVal> {
+    static INIT: Once = ONCE_INIT;
+    INIT.call_once(|| {
+        let mut map = HashMap::new();
+        let mut ptr = ENV_PTR;
+        while !(*ptr).is_null() {
+            if let Some((key, value)) = parse_env_str(CStr::from_ptr(*ptr as *const _)) {
+                if !map.contains_key(key) {
+                    map.insert(key, EnvVal {
+                        key: Cow::from(key),
+                        value: Cow::from(value),
+                    });
+                }
+            }
+            ptr = ptr.offset(1);
+        }
+        ENV = Box::into_raw(Box::new(map));
+    });
+    &mut *ENV
+}
+
+/// Returns the auxiliary vector, a list of (type, value) pairs terminated by
+/// an `AT_NULL` entry, or a null pointer if `_init` hasn't run yet.
+pub unsafe fn auxv() -> *const [c_ulong; 2] {
+    AUXVAL as *const [c_ulong; 2]
+}
+
+pub unsafe fn getauxval(type_: c_ulong) -> Option<c_ulong> {
+    let mut entry = auxv();
+    if entry.is_null() {
+        return None;
+    }
+    while (*entry)[0] != linux::AT_NULL {
+        if (*entry)[0] == type_ {
+            return Some((*entry)[1]);
+        }
+        entry = entry.offset(1);
+    }
+    None
+}
+
 pub unsafe fn getenv(key: &[u8]) -> Option<&'static [u8]> {
-    (*ENV).get(key).map(|v| make_static(&v.value))
+    env_map().get(key).map(|v| make_static(&v.value))
 }
 
 pub unsafe fn setenv(key: &[u8], value: &[u8]) -> c_int {
@@ -144,7 +183,7 @@ pub unsafe fn setenv(key: &[u8], value: &[u8]) -> c_int {
     let key = Cow::from(key.to_owned());
     let value = Cow::from(value.to_owned());
     let key_static = make_static(&key);
-    (*ENV).insert(key_static, EnvVal { key: key, value: value });
+    env_map().insert(key_static, EnvVal { key: key, value: value });
     0
 }
 
@@ -152,12 +191,12 @@ pub unsafe fn unsetenv(key: &[u8]) -> c_int {
     if invalid_env_key(key) {
         return -linux::errno::EINVAL;
     }
-    (*ENV).remove(key);
+    env_map().remove(key);
     0
 }
 
 pub unsafe fn env() -> &'static HashMap<&'static [u8], EnvVal> {
-    &*ENV
+    env_map()
 }
 
 pub unsafe fn environ() -> *const *const c_char {
@@ -212,17 +251,69 @@ pub unsafe fn lseek64(fd: c_int, offset: off64_t, whence: c_uint) -> off64_t {
     }
 }
 
+// musl: src/signal/sigaction.c
+pub unsafe fn sigaction(sig: c_int, act: *const sigaction, oact: *mut sigaction) -> c_int {
+    let mut ksa;
+    let mut act = act;
+    if !act.is_null() {
+        ksa = *act;
+        internal::set_restorer(&mut ksa);
+        act = &ksa;
+    }
+    linux::rt_sigaction(sig, act, oact, mem::size_of::<sigset_t>())
+}
+
+// musl: src/exit/abort.c
+pub unsafe fn abort() -> ! {
+    let tid = linux::gettid();
+    linux::tkill(tid, SIGABRT);
+
+    // SIGABRT was caught by a handler that returned, or it's ignored or
+    // blocked: terminate with its default action anyway
+    let mut action: sigaction = mem::zeroed();
+    action.sa_handler = SIG_DFL;
+    linux::rt_sigaction(SIGABRT, &action, ptr::null_mut(), mem::size_of::<sigset_t>());
+    linux::tkill(tid, SIGABRT);
+    let mut set = sigset_t { sig: [0; linux::_NSIG_WORDS] };
+    set.sig[0] = 1 << (SIGABRT - 1);
+    linux::rt_sigprocmask(linux::SIG_UNBLOCK, &set, ptr::null_mut(), mem::size_of::<sigset_t>());
+
+    // Unreachable
+    ::intrinsics::abort()
+}
+
 #[derive(Clone, Copy)]
 pub struct pthread_attr_t {
+    stack_addr: *mut c_void,
     stack_size: usize,
+    guard_size: usize,
 }
 
-struct thread {
+pub struct thread {
     // Required, because one cannot easily read out register containing the
-    // pointer to this structure on some platforms.
+    // pointer to this structure on some platforms. It's also what the ABI
+    // requires the thread pointer to point to on variant II architectures.
     this: *mut thread,
     thread_id: pid_t,
-}
+    // The lowest address of the stack and its size, without the guard page
+    // below it. All zero for the main thread, whose stack the kernel set up.
+    stack: *mut c_void,
+    stack_size: usize,
+    guard_size: usize,
+    // The mapping with the guard page, the stack and this struct, which the
+    // thread unmaps itself if it's detached, `pthread_join` does otherwise
+    map_base: *mut c_void,
+    map_size: usize,
+    detach_state: AtomicUsize,
+    start_routine: Option<extern "C" fn(*mut c_void) -> *mut c_void>,
+    start_arg: *mut c_void,
+}
+
+// Values of `thread.detach_state`
+// musl: src/internal/pthread_impl.h
+const DT_JOINABLE: usize = 0;
+const DT_EXITING: usize = 1;
+const DT_DETACHED: usize = 2;
 
 #[derive(Clone, Copy)]
 pub struct pthread_t {
@@ -231,7 +322,10 @@ pub struct pthread_t {
 
 pub unsafe fn pthread_attr_init(attr: *mut pthread_attr_t) -> c_int {
     *attr = pthread_attr_t {
+        stack_addr: ptr::null_mut(),
         stack_size: 0,
+        // musl: src/internal/pthread_impl.h (`DEFAULT_GUARD_SIZE`)
+        guard_size: 4096,
     };
     0
 }
@@ -248,13 +342,12 @@ pub unsafe fn pthread_attr_setstacksize(attr: *mut pthread_attr_t,
     0
 }
 
-/*
 pub unsafe fn pthread_attr_getstack(attr: *const pthread_attr_t,
                                     stackaddr: *mut *mut c_void,
                                     stacksize: *mut size_t)
     -> c_int
 {
-    *stackaddr = ptr::null_mut();
+    *stackaddr = (*attr).stack_addr;
     *stacksize = (*attr).stack_size;
     0
 }
@@ -263,48 +356,40 @@ pub unsafe fn pthread_attr_getguardsize(attr: *const pthread_attr_t,
                                         guardsize: *mut size_t)
     -> c_int
 {
-    *guardsize = 0;
+    *guardsize = (*attr).guard_size;
     0
 }
 
+// Only knows about the stacks we allocated, the main thread gets a zeroed
+// `pthread_attr_t`
 pub unsafe fn pthread_getattr_np(pthread: pthread_t,
                                  attr: *mut pthread_attr_t)
     -> c_int
 {
-    pthread_attr_init(attr)
+    let thread = pthread.thread;
+    *attr = pthread_attr_t {
+        stack_addr: (*thread).stack,
+        stack_size: (*thread).stack_size,
+        guard_size: (*thread).guard_size,
+    };
+    0
 }
-*/
 
-#[cfg(not(any(target_arch = "aarch64",
-              target_arch = "arm",
-              target_arch = "powerpc",
-              target_arch = "x86",
-              target_arch = "x86_64")))]
-pub unsafe fn pthread_create(pthread: *mut pthread_t,
-                             attr: *const pthread_attr_t,
-                             start_routine: extern "C" fn(*mut c_void) -> *mut c_void,
-                             arg: *mut c_void)
-    -> c_int
-{
-    if false {
-        let flags = CLONE_VM | CLONE_FS | CLONE_FILES | CLONE_SIGHAND
-            | CLONE_THREAD | CLONE_SYSVSEM | CLONE_SETTLS
-            | CLONE_CHILD_CLEARTID | CLONE_PARENT_SETTID;
-        mmap(ptr::null_mut(), 0, PROT_READ | PROT_WRITE, MAP_PRIVATE |
-             MAP_ANON, -1, 0);
+// Bionic has this, glibc and musl don't. Returns 0 once the thread has exited.
+pub unsafe fn pthread_gettid_np(pthread: pthread_t) -> pid_t {
+    ptr::read_volatile(&(*pthread.thread).thread_id)
+}
+
+pub unsafe fn pthread_self() -> pthread_t {
+    pthread_t {
+        thread: internal::thread_self(),
     }
-    unimplemented!();
 }
+
 // Heavily simplified (i.e. less features) version of pthread_create.
 // musl: src/thread/pthread_create.c
 //
-// Doesn't care about signals, thread-local storage and perhaps other things
-// yet.
-#[cfg(any(target_arch = "aarch64",
-          target_arch = "arm",
-          target_arch = "powerpc",
-          target_arch = "x86",
-          target_arch = "x86_64"))]
+// Doesn't care about signals and perhaps other things yet.
 pub unsafe fn pthread_create(pthread: *mut pthread_t,
                              attr: *const pthread_attr_t,
                              start_routine: extern "C" fn(*mut c_void) -> *mut c_void,
@@ -320,10 +405,13 @@ pub unsafe fn pthread_create(pthread: *mut pthread_t,
     let mask = align - 1;
     let stack_size = ((*attr).stack_size + mask) & !mask;
 
-    // TODO(steed, #131): Make sure this is freed on normal and abnormal thread
-    // exit.
+    let page_mask = getauxval(linux::AT_PAGESZ).unwrap() as usize - 1;
+    let guard_size = ((*attr).guard_size + page_mask) & !page_mask;
+
+    // The guard page, the stack and the TLS area on top of it
+    let size = guard_size + stack_size + internal::tls_area_size();
     let map = mmap(ptr::null_mut(),
-                   stack_size + mem::size_of::<thread>(),
+                   size,
                    PROT_READ | PROT_WRITE,
                    MAP_PRIVATE | MAP_ANON,
                    -1,
@@ -334,18 +422,36 @@ pub unsafe fn pthread_create(pthread: *mut pthread_t,
         return -(map as c_int);
     }
 
-    let stack = map.offset(stack_size as isize);
-    let thread = stack as *mut thread;
-    (*thread).this = thread;
+    // A stack overflow runs into the guard page and faults instead of
+    // overwriting whatever is mapped below the stack
+    if guard_size != 0 {
+        let result = mprotect(map, guard_size, PROT_NONE);
+        if result < 0 {
+            munmap(map, size);
+            return -result;
+        }
+    }
 
-    let child_tid = syscall_clone(start_routine,
+    let stack_start = map.offset(guard_size as isize);
+    let stack = stack_start.offset(stack_size as isize);
+    let (thread, tp) = internal::init_tls_area(stack as *mut u8);
+    (*thread).stack = stack_start;
+    (*thread).stack_size = stack_size;
+    (*thread).guard_size = guard_size;
+    (*thread).map_base = map;
+    (*thread).map_size = size;
+    (*thread).start_routine = Some(start_routine);
+    (*thread).start_arg = arg;
+
+    let child_tid = syscall_clone(start,
                                   stack,
                                   flags,
-                                  arg,
-                                  &mut (*thread).thread_id,
                                   thread as *mut c_void,
+                                  &mut (*thread).thread_id,
+                                  tp as *mut c_void,
                                   &mut (*thread).thread_id);
     if child_tid < 0 {
+        munmap(map, size);
         return -child_tid;
     }
     *pthread = pthread_t {
@@ -354,11 +460,35 @@ pub unsafe fn pthread_create(pthread: *mut pthread_t,
     0
 }
 
-#[cfg(any(target_arch = "aarch64",
-          target_arch = "arm",
-          target_arch = "powerpc",
-          target_arch = "x86",
-          target_arch = "x86_64"))]
+// The entry point of threads created by `pthread_create`
+extern "C" fn start(thread: *mut c_void) -> *mut c_void {
+    unsafe {
+        let thread = thread as *mut thread;
+        let start_routine = (*thread).start_routine.unwrap();
+        start_routine((*thread).start_arg);
+
+        // musl: src/thread/pthread_create.c (`__pthread_exit`)
+        let state = (*thread).detach_state.compare_and_swap(DT_JOINABLE,
+                                                             DT_EXITING,
+                                                             Ordering::SeqCst);
+        if state == DT_DETACHED {
+            // Nobody is going to join us, so we have to free our stack
+            // ourselves. The kernel must not clear our thread ID in the
+            // unmapped memory (which could be mapped again by then) and no
+            // signal handler may run on the unmapped stack.
+            let all = linux::sigset_t { sig: [!0; linux::_NSIG_WORDS] };
+            linux::rt_sigprocmask(linux::SIG_BLOCK,
+                                  &all,
+                                  ptr::null_mut(),
+                                  mem::size_of::<linux::sigset_t>());
+            linux::set_tid_address(ptr::null_mut());
+            unmapself((*thread).map_base, (*thread).map_size);
+        }
+    }
+    // The thread exits when we return
+    ptr::null_mut()
+}
+
 extern {
     // Defined in internal/<arch>.rs.
     //
@@ -384,79 +514,56 @@ extern {
                      ptid: *mut pid_t,
                      newtls: *mut c_void,
                      ctid: *mut pid_t) -> pid_t;
-}
 
-/*
-#[inline(always)]
-#[cfg(target_arch = "x86_64")]
-unsafe fn thread_self() -> *mut thread {
-    let result;
-    asm!("mov %fs:0,$0":"=r"(result));
-    result
-}
-
-#[inline(always)]
-pub unsafe fn pthread_self() -> pthread_t {
-    pthread_t {
-        thread: thread_self(),
-    }
-}
-
-pub unsafe fn pthread_detach(thread: pthread_t) -> c_int {
-    unimplemented!();
+    // Defined in internal/<arch>.rs.
+    //
+    // Does MUNMAP(base, size) followed by EXIT(0), without using the stack in
+    // between.
+    #[link_name = "__steed_unmapself"]
+    fn unmapself(base: *mut c_void, size: size_t) -> !;
 }
-*/
 
-#[cfg(not(any(target_arch = "aarch64",
-              target_arch = "arm",
-              target_arch = "powerpc",
-              target_arch = "x86",
-              target_arch = "x86_64")))]
-pub unsafe fn pthread_join(pthread: pthread_t, retval: *mut *mut c_void)
-    -> c_int
-{
-    if false {
-        let thread = pthread.thread;
-        linux::futex(&mut (*thread).thread_id as *mut _ as *mut u32,
-                     linux::FUTEX_WAIT,
-                     (*thread).thread_id as u32,
-                     ptr::null(),
-                     ptr::null_mut(),
-                     0);
+// musl: src/thread/pthread_detach.c
+pub unsafe fn pthread_detach(pthread: pthread_t) -> c_int {
+    let thread = pthread.thread;
+    let state = (*thread).detach_state.compare_and_swap(DT_JOINABLE,
+                                                         DT_DETACHED,
+                                                         Ordering::SeqCst);
+    // The thread is already exiting, too late to leave the clean up to it
+    if state != DT_JOINABLE {
+        return pthread_join(pthread, ptr::null_mut());
     }
-    unimplemented!();
+    0
 }
 
-#[cfg(any(target_arch = "aarch64",
-          target_arch = "arm",
-          target_arch = "powerpc",
-          target_arch = "x86",
-          target_arch = "x86_64"))]
 pub unsafe fn pthread_join(pthread: pthread_t, retval: *mut *mut c_void)
     -> c_int
 {
     assert!(retval.is_null());
     let thread = pthread.thread;
 
-    let tmp = (*thread).thread_id;
-    // 0 would mean that the thread has exited already (CLONE_CHILD_CLEARTID
-    // flag on the clone syscall).
-    if tmp == 0 {
-        return 0;
-    }
-    // TODO(steed, #130): Why does FUTEX_WAIT_PRIVATE not work?
-    let res = linux::futex(&mut (*thread).thread_id as *mut _ as *mut u32,
-                           linux::FUTEX_WAIT,
-                           (*thread).thread_id as u32,
-                           ptr::null(),
-                           ptr::null_mut(),
-                           0);
-    if res == -EAGAIN {
-        return 0;
-    }
-    if res < 0 {
-        return -res;
+    loop {
+        let tmp = ptr::read_volatile(&(*thread).thread_id);
+        // 0 means that the thread has exited (CLONE_CHILD_CLEARTID flag on the
+        // clone syscall), it doesn't use its stack anymore.
+        if tmp == 0 {
+            break;
+        }
+        // TODO(steed, #130): Why does FUTEX_WAIT_PRIVATE not work?
+        let res = linux::futex(&mut (*thread).thread_id as *mut _ as *mut u32,
+                               linux::FUTEX_WAIT,
+                               tmp as u32,
+                               ptr::null(),
+                               ptr::null_mut(),
+                               0);
+        if res < 0 && res != -EAGAIN && res != -EINTR {
+            return -res;
+        }
     }
+
+    // `thread` lives in the mapping
+    let (map_base, map_size) = ((*thread).map_base, (*thread).map_size);
+    munmap(map_base, map_size);
     0
 }
 
@@ -25,6 +25,12 @@ pub const SO_BROADCAST: c_int = 6;
 
 pub const SIGCHLD: c_ulong = 17;
 
+// include/uapi/asm-generic/signal.h
+pub const SIGBUS: c_int = 7;
+pub const SA_ONSTACK: c_ulong = 0x08000000;
+pub const SA_SIGINFO: c_ulong = 0x00000004;
+pub const SIGSTKSZ: size_t = 16384;
+
 // include/linux/net.h
 pub const SOCK_STREAM: c_int = 1;
 pub const SOCK_DGRAM: c_int = 2;
@@ -57,4 +63,8 @@ pub struct stat64 {
     __unused: [c_int; 2],
 }
 
+// arch/arm64/include/asm/elf.h (and the AArch64 ELF ABI for R_AARCH64_NONE)
+pub const R_AARCH64_NONE: u32 = 0;
+pub const R_AARCH64_RELATIVE: u32 = 1027;
+
 type blksize_t = i32;
@@ -25,6 +25,12 @@ pub const SO_BROADCAST: c_int = 6;
 
 pub const SIGCHLD: c_ulong = 17;
 
+// arch/arm/include/uapi/asm/signal.h
+pub const SIGBUS: c_int = 7;
+pub const SA_ONSTACK: c_ulong = 0x08000000;
+pub const SA_SIGINFO: c_ulong = 0x00000004;
+pub const SIGSTKSZ: size_t = 8192;
+
 // include/linux/net.h
 pub const SOCK_STREAM: c_int = 1;
 pub const SOCK_DGRAM: c_int = 2;
@@ -63,4 +69,19 @@ pub struct stat64 {
     pub st_ino: ino64_t,
 }
 
+// arch/arm/include/asm/elf.h (and the ARM ELF ABI for R_ARM_RELATIVE)
+pub const R_ARM_NONE: u32 = 0;
+pub const R_ARM_RELATIVE: u32 = 23;
+
 pub type blksize_t = i32;
+
+// arch/arm/include/uapi/asm/unistd.h
+#[allow(non_upper_case_globals)]
+pub const __ARM_NR_set_tls: usize = 0x0f0005;
+
+// arch/arm/kernel/traps.c
+#[inline(always)]
+pub unsafe fn set_tls(val: c_ulong) -> ssize_t {
+    // An ARM private system call, not in the regular table
+    ::sc::syscall1(__ARM_NR_set_tls, val as usize) as ssize_t
+}
@@ -0,0 +1,356 @@
+#![allow(non_camel_case_types)]
+
+// include/uapi/linux/elf.h
+
+pub type Elf32_Addr = u32;
+pub type Elf32_Half = u16;
+pub type Elf32_Off = u32;
+pub type Elf32_Sword = i32;
+pub type Elf32_Word = u32;
+
+pub type Elf64_Addr = u64;
+pub type Elf64_Half = u16;
+pub type Elf64_Off = u64;
+pub type Elf64_Sword = i32;
+pub type Elf64_Word = u32;
+pub type Elf64_Xword = u64;
+pub type Elf64_Sxword = i64;
+
+// These constants are for the segment types stored in the image headers
+pub const PT_NULL: u32 = 0;
+pub const PT_LOAD: u32 = 1;
+pub const PT_DYNAMIC: u32 = 2;
+pub const PT_INTERP: u32 = 3;
+pub const PT_NOTE: u32 = 4;
+pub const PT_SHLIB: u32 = 5;
+pub const PT_PHDR: u32 = 6;
+pub const PT_TLS: u32 = 7;
+pub const PT_LOOS: u32 = 0x60000000;
+pub const PT_HIOS: u32 = 0x6fffffff;
+pub const PT_LOPROC: u32 = 0x70000000;
+pub const PT_HIPROC: u32 = 0x7fffffff;
+pub const PT_GNU_EH_FRAME: u32 = 0x6474e550;
+pub const PT_GNU_STACK: u32 = PT_LOOS + 0x474e551;
+pub const PT_GNU_RELRO: u32 = PT_LOOS + 0x474e552;
+
+// arch/arm/include/asm/elf.h
+pub const PT_ARM_EXIDX: u32 = PT_LOPROC + 1;
+
+// These constants define the permissions on sections in the program header,
+// p_flags.
+pub const PF_R: u32 = 0x4;
+pub const PF_W: u32 = 0x2;
+pub const PF_X: u32 = 0x1;
+
+// sh_type
+pub const SHT_NULL: u32 = 0;
+pub const SHT_PROGBITS: u32 = 1;
+pub const SHT_SYMTAB: u32 = 2;
+pub const SHT_STRTAB: u32 = 3;
+pub const SHT_RELA: u32 = 4;
+pub const SHT_HASH: u32 = 5;
+pub const SHT_DYNAMIC: u32 = 6;
+pub const SHT_NOTE: u32 = 7;
+pub const SHT_NOBITS: u32 = 8;
+pub const SHT_REL: u32 = 9;
+pub const SHT_SHLIB: u32 = 10;
+pub const SHT_DYNSYM: u32 = 11;
+
+// special section indexes
+pub const SHN_UNDEF: u16 = 0;
+
+pub const STT_NOTYPE: u8 = 0;
+pub const STT_OBJECT: u8 = 1;
+pub const STT_FUNC: u8 = 2;
+pub const STT_SECTION: u8 = 3;
+pub const STT_FILE: u8 = 4;
+
+pub const STB_LOCAL: u8 = 0;
+pub const STB_GLOBAL: u8 = 1;
+pub const STB_WEAK: u8 = 2;
+
+#[allow(non_snake_case)]
+pub fn ELF_ST_BIND(x: u8) -> u8 {
+    x >> 4
+}
+
+#[allow(non_snake_case)]
+pub fn ELF_ST_TYPE(x: u8) -> u8 {
+    x & 0xf
+}
+
+#[allow(non_snake_case)]
+pub fn ELF32_R_SYM(x: Elf32_Word) -> u32 {
+    x >> 8
+}
+
+#[allow(non_snake_case)]
+pub fn ELF32_R_TYPE(x: Elf32_Word) -> u32 {
+    x & 0xff
+}
+
+#[allow(non_snake_case)]
+pub fn ELF64_R_SYM(i: Elf64_Xword) -> u32 {
+    (i >> 32) as u32
+}
+
+#[allow(non_snake_case)]
+pub fn ELF64_R_TYPE(i: Elf64_Xword) -> u32 {
+    (i & 0xffff_ffff) as u32
+}
+
+// This is the info that is needed to parse the dynamic section of the file
+pub const DT_NULL: i64 = 0;
+pub const DT_NEEDED: i64 = 1;
+pub const DT_PLTRELSZ: i64 = 2;
+pub const DT_PLTGOT: i64 = 3;
+pub const DT_HASH: i64 = 4;
+pub const DT_STRTAB: i64 = 5;
+pub const DT_SYMTAB: i64 = 6;
+pub const DT_RELA: i64 = 7;
+pub const DT_RELASZ: i64 = 8;
+pub const DT_RELAENT: i64 = 9;
+pub const DT_STRSZ: i64 = 10;
+pub const DT_SYMENT: i64 = 11;
+pub const DT_INIT: i64 = 12;
+pub const DT_FINI: i64 = 13;
+pub const DT_SONAME: i64 = 14;
+pub const DT_RPATH: i64 = 15;
+pub const DT_SYMBOLIC: i64 = 16;
+pub const DT_REL: i64 = 17;
+pub const DT_RELSZ: i64 = 18;
+pub const DT_RELENT: i64 = 19;
+pub const DT_PLTREL: i64 = 20;
+pub const DT_DEBUG: i64 = 21;
+pub const DT_TEXTREL: i64 = 22;
+pub const DT_JMPREL: i64 = 23;
+pub const DT_VERSYM: i64 = 0x6ffffff0;
+pub const DT_RELACOUNT: i64 = 0x6ffffff9;
+pub const DT_RELCOUNT: i64 = 0x6ffffffa;
+pub const DT_FLAGS_1: i64 = 0x6ffffffb;
+pub const DT_VERDEF: i64 = 0x6ffffffc;
+pub const DT_VERDEFNUM: i64 = 0x6ffffffd;
+pub const DT_VERNEED: i64 = 0x6ffffffe;
+pub const DT_VERNEEDNUM: i64 = 0x6fffffff;
+
+// Symbol versioning, which the kernel headers don't cover. See the LSB:
+// http://refspecs.linuxfoundation.org/LSB_5.0.0/LSB-Core-generic/LSB-Core-generic/symversion.html
+pub const VER_FLG_BASE: u16 = 0x1;
+pub const VER_FLG_WEAK: u16 = 0x2;
+pub const VERSYM_HIDDEN: u16 = 0x8000;
+pub const VERSYM_VERSION: u16 = 0x7fff;
+
+pub const EI_NIDENT: usize = 16;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_hdr {
+    pub e_ident: [u8; EI_NIDENT],
+    pub e_type: Elf32_Half,
+    pub e_machine: Elf32_Half,
+    pub e_version: Elf32_Word,
+    pub e_entry: Elf32_Addr,
+    pub e_phoff: Elf32_Off,
+    pub e_shoff: Elf32_Off,
+    pub e_flags: Elf32_Word,
+    pub e_ehsize: Elf32_Half,
+    pub e_phentsize: Elf32_Half,
+    pub e_phnum: Elf32_Half,
+    pub e_shentsize: Elf32_Half,
+    pub e_shnum: Elf32_Half,
+    pub e_shstrndx: Elf32_Half,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_hdr {
+    pub e_ident: [u8; EI_NIDENT],
+    pub e_type: Elf64_Half,
+    pub e_machine: Elf64_Half,
+    pub e_version: Elf64_Word,
+    pub e_entry: Elf64_Addr,
+    pub e_phoff: Elf64_Off,
+    pub e_shoff: Elf64_Off,
+    pub e_flags: Elf64_Word,
+    pub e_ehsize: Elf64_Half,
+    pub e_phentsize: Elf64_Half,
+    pub e_phnum: Elf64_Half,
+    pub e_shentsize: Elf64_Half,
+    pub e_shnum: Elf64_Half,
+    pub e_shstrndx: Elf64_Half,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_phdr {
+    pub p_type: Elf32_Word,
+    pub p_offset: Elf32_Off,
+    pub p_vaddr: Elf32_Addr,
+    pub p_paddr: Elf32_Addr,
+    pub p_filesz: Elf32_Word,
+    pub p_memsz: Elf32_Word,
+    pub p_flags: Elf32_Word,
+    pub p_align: Elf32_Word,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_phdr {
+    pub p_type: Elf64_Word,
+    pub p_flags: Elf64_Word,
+    pub p_offset: Elf64_Off,
+    pub p_vaddr: Elf64_Addr,
+    pub p_paddr: Elf64_Addr,
+    pub p_filesz: Elf64_Xword,
+    pub p_memsz: Elf64_Xword,
+    pub p_align: Elf64_Xword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_sym {
+    pub st_name: Elf32_Word,
+    pub st_value: Elf32_Addr,
+    pub st_size: Elf32_Word,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: Elf32_Half,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_sym {
+    pub st_name: Elf64_Word,
+    pub st_info: u8,
+    pub st_other: u8,
+    pub st_shndx: Elf64_Half,
+    pub st_value: Elf64_Addr,
+    pub st_size: Elf64_Xword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_shdr {
+    pub sh_name: Elf32_Word,
+    pub sh_type: Elf32_Word,
+    pub sh_flags: Elf32_Word,
+    pub sh_addr: Elf32_Addr,
+    pub sh_offset: Elf32_Off,
+    pub sh_size: Elf32_Word,
+    pub sh_link: Elf32_Word,
+    pub sh_info: Elf32_Word,
+    pub sh_addralign: Elf32_Word,
+    pub sh_entsize: Elf32_Word,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_shdr {
+    pub sh_name: Elf64_Word,
+    pub sh_type: Elf64_Word,
+    pub sh_flags: Elf64_Xword,
+    pub sh_addr: Elf64_Addr,
+    pub sh_offset: Elf64_Off,
+    pub sh_size: Elf64_Xword,
+    pub sh_link: Elf64_Word,
+    pub sh_info: Elf64_Word,
+    pub sh_addralign: Elf64_Xword,
+    pub sh_entsize: Elf64_Xword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_dyn {
+    pub d_tag: Elf32_Sword,
+    // `d_un.d_val` / `d_un.d_ptr`
+    pub d_val: Elf32_Word,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_dyn {
+    pub d_tag: Elf64_Sxword,
+    // `d_un.d_val` / `d_un.d_ptr`
+    pub d_val: Elf64_Xword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_rel {
+    pub r_offset: Elf32_Addr,
+    pub r_info: Elf32_Word,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_rel {
+    pub r_offset: Elf64_Addr,
+    pub r_info: Elf64_Xword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf32_rela {
+    pub r_offset: Elf32_Addr,
+    pub r_info: Elf32_Word,
+    pub r_addend: Elf32_Sword,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf64_rela {
+    pub r_offset: Elf64_Addr,
+    pub r_info: Elf64_Xword,
+    pub r_addend: Elf64_Sxword,
+}
+
+// Same layout on 32-bit and 64-bit
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf_verdef {
+    pub vd_version: u16,
+    pub vd_flags: u16,
+    pub vd_ndx: u16,
+    pub vd_cnt: u16,
+    pub vd_hash: u32,
+    pub vd_aux: u32,
+    pub vd_next: u32,
+}
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct elf_verdaux {
+    pub vda_name: u32,
+    pub vda_next: u32,
+}
+
+// include/linux/elf.h
+#[cfg(target_pointer_width = "32")]
+pub type elfhdr = elf32_hdr;
+#[cfg(target_pointer_width = "32")]
+pub type elf_phdr = elf32_phdr;
+#[cfg(target_pointer_width = "32")]
+pub type elf_shdr = elf32_shdr;
+#[cfg(target_pointer_width = "32")]
+pub type elf_sym = elf32_sym;
+#[cfg(target_pointer_width = "32")]
+pub type elf_dyn = elf32_dyn;
+#[cfg(target_pointer_width = "32")]
+pub type elf_rel = elf32_rel;
+#[cfg(target_pointer_width = "32")]
+pub type elf_rela = elf32_rela;
+
+#[cfg(target_pointer_width = "64")]
+pub type elfhdr = elf64_hdr;
+#[cfg(target_pointer_width = "64")]
+pub type elf_phdr = elf64_phdr;
+#[cfg(target_pointer_width = "64")]
+pub type elf_shdr = elf64_shdr;
+#[cfg(target_pointer_width = "64")]
+pub type elf_sym = elf64_sym;
+#[cfg(target_pointer_width = "64")]
+pub type elf_dyn = elf64_dyn;
+#[cfg(target_pointer_width = "64")]
+pub type elf_rel = elf64_rel;
+#[cfg(target_pointer_width = "64")]
+pub type elf_rela = elf64_rela;
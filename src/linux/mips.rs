@@ -24,6 +24,12 @@ pub const SO_BROADCAST: c_int = 0x0020;
 
 pub const SIGCHLD: c_ulong = 18;
 
+// arch/mips/include/uapi/asm/signal.h
+pub const SIGBUS: c_int = 10;
+pub const SA_ONSTACK: c_uint = 0x08000000;
+pub const SA_SIGINFO: c_uint = 0x00000008;
+pub const SIGSTKSZ: size_t = 8192;
+
 // arch/mips/include/asm/socket.h
 pub const SOCK_DGRAM: c_int = 1;
 pub const SOCK_STREAM: c_int = 2;
@@ -57,4 +63,17 @@ pub struct stat64 {
     __st_pad5: [c_long; 14],
 }
 
+// arch/mips/include/asm/elf.h
+pub const R_MIPS_NONE: u32 = 0;
+pub const R_MIPS_REL32: u32 = 3;
+
+// The MIPS ABI supplement, the kernel doesn't deal with dynamic sections
+pub const DT_MIPS_LOCAL_GOTNO: i64 = 0x7000000a;
+
 pub type blksize_t = i32;
+
+// arch/mips/kernel/syscall.c
+#[inline(always)]
+pub unsafe fn set_thread_area(addr: c_ulong) -> ssize_t {
+    syscall!(SET_THREAD_AREA, addr) as ssize_t
+}
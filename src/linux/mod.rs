@@ -36,6 +36,9 @@ mod arch;
 
 mod types;
 
+// Definitions from the ELF specification
+pub mod elf;
+
 // Generated from the Linux source tree using generate/errno.py
 pub mod errno;
 
@@ -46,6 +49,35 @@ use ptr;
 pub use self::arch::*;
 pub use self::types::*;
 
+// include/uapi/linux/auxvec.h
+pub const AT_NULL: c_ulong = 0;
+pub const AT_IGNORE: c_ulong = 1;
+pub const AT_EXECFD: c_ulong = 2;
+pub const AT_PHDR: c_ulong = 3;
+pub const AT_PHENT: c_ulong = 4;
+pub const AT_PHNUM: c_ulong = 5;
+pub const AT_PAGESZ: c_ulong = 6;
+pub const AT_BASE: c_ulong = 7;
+pub const AT_FLAGS: c_ulong = 8;
+pub const AT_ENTRY: c_ulong = 9;
+pub const AT_NOTELF: c_ulong = 10;
+pub const AT_UID: c_ulong = 11;
+pub const AT_EUID: c_ulong = 12;
+pub const AT_GID: c_ulong = 13;
+pub const AT_EGID: c_ulong = 14;
+pub const AT_PLATFORM: c_ulong = 15;
+pub const AT_HWCAP: c_ulong = 16;
+pub const AT_CLKTCK: c_ulong = 17;
+pub const AT_SECURE: c_ulong = 23;
+pub const AT_BASE_PLATFORM: c_ulong = 24;
+pub const AT_RANDOM: c_ulong = 25;
+pub const AT_HWCAP2: c_ulong = 26;
+pub const AT_EXECFN: c_ulong = 31;
+
+// arch/*/include/uapi/asm/auxvec.h (same value on every architecture)
+pub const AT_SYSINFO_EHDR: c_ulong = 33;
+pub const AT_MINSIGSTKSZ: c_ulong = 51;
+
 // include/uapi/linux/fcntl.h
 pub const AT_FDCWD: c_int = -100;
 pub const AT_REMOVEDIR: c_int = 0x200;
@@ -135,10 +167,19 @@ pub const TCP_NODELAY: c_int = 1;
 pub const MSG_NOSIGNAL: c_int = 0x4000;
 
 // include/uapi/asm-generic/mman-common.h
+pub const MAP_SHARED: c_int = 0x01;
 pub const MAP_PRIVATE: c_int = 0x02;
+pub const PROT_NONE: c_int = 0x0;
 pub const PROT_READ: c_int = 0x1;
 pub const PROT_WRITE: c_int = 0x2;
 
+// include/uapi/asm-generic/resource.h
+pub const RLIMIT_STACK: c_uint = 3;
+
+// include/uapi/linux/resource.h
+pub const PRIO_PROCESS: c_int = 0;
+pub const RLIM64_INFINITY: u64 = !0;
+
 // include/uapi/linux/sched.h
 pub const CLONE_CHILD_CLEARTID: c_ulong = 0x00200000;
 pub const CLONE_FILES: c_ulong = 0x00000400;
@@ -149,13 +190,61 @@ pub const CLONE_SIGHAND: c_ulong = 0x00000800;
 pub const CLONE_SYSVSEM: c_ulong = 0x00040000;
 pub const CLONE_THREAD: c_ulong = 0x00010000;
 pub const CLONE_VM: c_ulong = 0x00000100;
+pub const SCHED_OTHER: c_int = 0;
+pub const SCHED_FIFO: c_int = 1;
+pub const SCHED_RR: c_int = 2;
+pub const SCHED_BATCH: c_int = 3;
+pub const SCHED_IDLE: c_int = 5;
+pub const SCHED_RESET_ON_FORK: c_int = 0x40000000;
 
 // include/uapi/linux/futex.h
 pub const FUTEX_WAIT: c_int = 0;
 pub const FUTEX_WAKE: c_int = 1;
+pub const FUTEX_LOCK_PI: c_int = 6;
+pub const FUTEX_UNLOCK_PI: c_int = 7;
+pub const FUTEX_TRYLOCK_PI: c_int = 8;
+pub const FUTEX_WAIT_BITSET: c_int = 9;
 pub const FUTEX_PRIVATE_FLAG: c_int = 128;
 pub const FUTEX_WAIT_PRIVATE: c_int = FUTEX_WAIT | FUTEX_PRIVATE_FLAG;
 pub const FUTEX_WAKE_PRIVATE: c_int = FUTEX_WAKE | FUTEX_PRIVATE_FLAG;
+pub const FUTEX_LOCK_PI_PRIVATE: c_int = FUTEX_LOCK_PI | FUTEX_PRIVATE_FLAG;
+pub const FUTEX_UNLOCK_PI_PRIVATE: c_int = FUTEX_UNLOCK_PI | FUTEX_PRIVATE_FLAG;
+pub const FUTEX_TRYLOCK_PI_PRIVATE: c_int = FUTEX_TRYLOCK_PI | FUTEX_PRIVATE_FLAG;
+pub const FUTEX_WAIT_BITSET_PRIVATE: c_int = FUTEX_WAIT_BITSET | FUTEX_PRIVATE_FLAG;
+pub const FUTEX_WAITERS: u32 = 0x80000000;
+pub const FUTEX_OWNER_DIED: u32 = 0x40000000;
+pub const FUTEX_TID_MASK: u32 = 0x3fffffff;
+pub const FUTEX_BITSET_MATCH_ANY: u32 = 0xffffffff;
+
+// include/uapi/asm-generic/signal-defs.h
+pub const SIG_DFL: sighandler_t = 0;
+pub const SIG_IGN: sighandler_t = 1;
+#[cfg(not(any(target_arch = "mips",
+              target_arch = "mips64",
+              target_arch = "sparc64")))]
+pub const SIG_BLOCK: c_int = 0;
+#[cfg(not(any(target_arch = "mips",
+              target_arch = "mips64",
+              target_arch = "sparc64")))]
+pub const SIG_UNBLOCK: c_int = 1;
+
+// arch/mips/include/uapi/asm/signal.h, arch/sparc/include/uapi/asm/signal.h
+#[cfg(any(target_arch = "mips",
+          target_arch = "mips64",
+          target_arch = "sparc64"))]
+pub const SIG_BLOCK: c_int = 1;
+#[cfg(any(target_arch = "mips",
+          target_arch = "mips64",
+          target_arch = "sparc64"))]
+pub const SIG_UNBLOCK: c_int = 2;
+
+// include/uapi/asm-generic/signal.h
+pub const SIGABRT: c_int = 6;
+pub const SIGSEGV: c_int = 11;
+pub const SIGPIPE: c_int = 13;
+
+// include/uapi/linux/signal.h
+pub const SS_DISABLE: c_int = 2;
 
 // kernel/time/posix-timers.c
 #[inline(always)]
@@ -1091,6 +1180,60 @@ pub unsafe fn sched_yield() -> ssize_t {
     syscall!(SCHED_YIELD) as ssize_t
 }
 
+// kernel/sched/core.c
+#[inline(always)]
+pub unsafe fn sched_setaffinity(pid: pid_t,
+                                len: c_uint,
+                                user_mask_ptr: *const c_ulong)
+    -> c_int
+{
+    syscall!(SCHED_SETAFFINITY, pid, len, user_mask_ptr) as c_int
+}
+
+// kernel/sched/core.c
+#[inline(always)]
+pub unsafe fn sched_getaffinity(pid: pid_t,
+                                len: c_uint,
+                                user_mask_ptr: *mut c_ulong)
+    -> c_int
+{
+    syscall!(SCHED_GETAFFINITY, pid, len, user_mask_ptr) as c_int
+}
+
+// kernel/sched/core.c
+#[inline(always)]
+pub unsafe fn sched_setscheduler(pid: pid_t,
+                                 policy: c_int,
+                                 param: *const sched_param)
+    -> c_int
+{
+    syscall!(SCHED_SETSCHEDULER, pid, policy, param) as c_int
+}
+
+// kernel/sched/core.c
+#[inline(always)]
+pub unsafe fn sched_getscheduler(pid: pid_t) -> c_int {
+    syscall!(SCHED_GETSCHEDULER, pid) as c_int
+}
+
+// kernel/sched/core.c
+#[inline(always)]
+pub unsafe fn sched_getparam(pid: pid_t, param: *mut sched_param) -> c_int {
+    syscall!(SCHED_GETPARAM, pid, param) as c_int
+}
+
+// kernel/sys.c
+#[inline(always)]
+pub unsafe fn getpriority(which: c_int, who: c_int) -> c_int {
+    syscall!(GETPRIORITY, which, who) as c_int
+}
+
+// kernel/sys.c
+#[inline(always)]
+pub unsafe fn setpriority(which: c_int, who: c_int, niceval: c_int) -> c_int {
+    syscall!(SETPRIORITY, which, who, niceval) as c_int
+}
+
 // kernel/sched/core.c
 #[inline(always)]
 pub unsafe fn mmap(addr: *mut c_void,
@@ -1128,6 +1271,18 @@ pub unsafe fn mmap(addr: *mut c_void,
     mmap(addr, length, prot, flags, fd, offset)
 }
 
+// mm/mprotect.c
+#[inline(always)]
+pub unsafe fn mprotect(start: *mut c_void, len: size_t, prot: c_int) -> c_int {
+    syscall!(MPROTECT, start, len, prot) as c_int
+}
+
+// mm/mmap.c
+#[inline(always)]
+pub unsafe fn munmap(addr: *mut c_void, len: size_t) -> c_int {
+    syscall!(MUNMAP, addr, len) as c_int
+}
+
 // kernel/time/hrtimer.c
 #[inline(always)]
 pub unsafe fn nanosleep(rqtp: *const timespec, rmtp: *mut timespec) -> ssize_t {
@@ -1147,6 +1302,90 @@ pub unsafe fn futex(uaddr: *mut u32,
     syscall!(FUTEX, uaddr, op, val, utime, uaddr2, val3) as c_int
 }
 
+// kernel/futex.c
+#[inline(always)]
+pub unsafe fn set_robust_list(head: *mut robust_list_head, len: size_t) -> c_int {
+    syscall!(SET_ROBUST_LIST, head, len) as c_int
+}
+
+// kernel/signal.c
+#[inline(always)]
+pub unsafe fn rt_sigaction(sig: c_int,
+                           act: *const sigaction,
+                           oact: *mut sigaction,
+                           sigsetsize: size_t)
+    -> c_int
+{
+    #[cfg(not(target_arch = "sparc64"))]
+    #[inline(always)]
+    unsafe fn rt_sigaction(sig: c_int,
+                           act: *const sigaction,
+                           oact: *mut sigaction,
+                           sigsetsize: size_t)
+        -> c_int
+    {
+        syscall!(RT_SIGACTION, sig, act, oact, sigsetsize) as c_int
+    }
+    #[cfg(target_arch = "sparc64")]
+    #[inline(always)]
+    unsafe fn rt_sigaction(sig: c_int,
+                           act: *const sigaction,
+                           oact: *mut sigaction,
+                           sigsetsize: size_t)
+        -> c_int
+    {
+        // The extra argument is the signal restorer, which we never set.
+        syscall!(RT_SIGACTION, sig, act, oact, 0, sigsetsize) as c_int
+    }
+    rt_sigaction(sig, act, oact, sigsetsize)
+}
+
+// kernel/signal.c
+#[inline(always)]
+pub unsafe fn rt_sigprocmask(how: c_int,
+                             nset: *const sigset_t,
+                             oset: *mut sigset_t,
+                             sigsetsize: size_t)
+    -> c_int
+{
+    syscall!(RT_SIGPROCMASK, how, nset, oset, sigsetsize) as c_int
+}
+
+// kernel/signal.c
+#[inline(always)]
+pub unsafe fn tkill(pid: pid_t, sig: c_int) -> c_int {
+    syscall!(TKILL, pid, sig) as c_int
+}
+
+// kernel/signal.c
+#[inline(always)]
+pub unsafe fn sigaltstack(uss: *const stack_t, uoss: *mut stack_t) -> c_int {
+    syscall!(SIGALTSTACK, uss, uoss) as c_int
+}
+
+// kernel/fork.c
+#[inline(always)]
+pub unsafe fn set_tid_address(tidptr: *mut c_int) -> pid_t {
+    syscall!(SET_TID_ADDRESS, tidptr) as pid_t
+}
+
+// kernel/sys.c
+#[inline(always)]
+pub unsafe fn prlimit64(pid: pid_t,
+                        resource: c_uint,
+                        new_rlim: *const rlimit64,
+                        old_rlim: *mut rlimit64)
+    -> c_int
+{
+    syscall!(PRLIMIT64, pid, resource, new_rlim, old_rlim) as c_int
+}
+
+// kernel/sys.c
+#[inline(always)]
+pub unsafe fn gettid() -> pid_t {
+    syscall!(GETTID) as pid_t
+}
+
 // fs/dcache.c
 #[inline(always)]
 pub unsafe fn getcwd(buf: *mut c_char, size: size_t) -> ssize_t {
@@ -16,6 +16,12 @@ pub const O_TRUNC: c_int = 0x0400;
 
 pub const SIGCHLD: c_ulong = 17;
 
+// arch/sparc/include/uapi/asm/signal.h
+pub const SIGBUS: c_int = 10;
+pub const SA_ONSTACK: c_ulong = 0x1;
+pub const SA_SIGINFO: c_ulong = 0x200;
+pub const SIGSTKSZ: size_t = 16384;
+
 pub const SO_RCVTIMEO: c_int = 0x2000;
 pub const SO_SNDTIMEO: c_int = 0x4000;
 pub const SO_ERROR: c_int = 0x1007;
@@ -151,6 +151,130 @@ pub struct __kernel_sockaddr_storage {
     __ss_data: [c_char; _K_SS_MAXSIZE - 2],
 }
 
+// include/uapi/asm-generic/signal.h
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+pub const _NSIG: usize = 64;
+// arch/mips/include/uapi/asm/signal.h
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+pub const _NSIG: usize = 128;
+#[cfg(target_pointer_width = "32")]
+pub const _NSIG_BPW: usize = 32;
+#[cfg(target_pointer_width = "64")]
+pub const _NSIG_BPW: usize = 64;
+pub const _NSIG_WORDS: usize = _NSIG / _NSIG_BPW;
+
+// include/uapi/asm-generic/signal-defs.h
+pub type sighandler_t = usize;
+
+// include/uapi/asm-generic/signal.h
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct sigset_t {
+    pub sig: [c_ulong; _NSIG_WORDS],
+}
+
+// include/linux/signal_types.h (`struct sigaction` as seen by rt_sigaction)
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct sigaction {
+    pub sa_handler: sighandler_t,
+    pub sa_flags: c_ulong,
+    pub sa_restorer: usize,
+    pub sa_mask: sigset_t,
+}
+
+// arch/mips/include/uapi/asm/signal.h
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct sigaction {
+    pub sa_flags: c_uint,
+    pub sa_handler: sighandler_t,
+    pub sa_mask: sigset_t,
+}
+
+// include/uapi/asm-generic/signal.h
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct stack_t {
+    pub ss_sp: *mut c_void,
+    pub ss_flags: c_int,
+    pub ss_size: size_t,
+}
+
+// arch/mips/include/uapi/asm/signal.h
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct stack_t {
+    pub ss_sp: *mut c_void,
+    pub ss_size: size_t,
+    pub ss_flags: c_int,
+}
+
+// include/uapi/asm-generic/siginfo.h
+pub const SI_MAX_SIZE: usize = 128;
+#[cfg(target_pointer_width = "32")]
+pub const SI_PAD_SIZE: usize = (SI_MAX_SIZE - 3 * 4) / 4;
+#[cfg(target_pointer_width = "64")]
+pub const SI_PAD_SIZE: usize = (SI_MAX_SIZE - 4 * 4) / 4;
+
+// The fields of `union __sifields` depend on the signal, we only need the
+// `si_addr` of `SIGSEGV` and `SIGBUS`, its first word.
+#[repr(C)]
+pub struct siginfo_t {
+    pub si_signo: c_int,
+    // arch/mips/include/uapi/asm/siginfo.h swaps these two
+    #[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+    pub si_errno: c_int,
+    pub si_code: c_int,
+    #[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+    pub si_errno: c_int,
+    // The union is pointer aligned
+    #[cfg(target_pointer_width = "64")]
+    _align: c_int,
+    _sifields: [c_int; SI_PAD_SIZE],
+}
+
+impl siginfo_t {
+    pub unsafe fn si_addr(&self) -> *mut c_void {
+        *(self._sifields.as_ptr() as *const *mut c_void)
+    }
+}
+
+// include/uapi/linux/futex.h
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct robust_list {
+    pub next: *mut robust_list,
+}
+
+// include/uapi/linux/futex.h
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct robust_list_head {
+    pub list: robust_list,
+    pub futex_offset: c_long,
+    pub list_op_pending: *mut robust_list,
+}
+
+// include/uapi/linux/sched/types.h
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct sched_param {
+    pub sched_priority: c_int,
+}
+
+// include/uapi/linux/resource.h
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct rlimit64 {
+    pub rlim_cur: u64,
+    pub rlim_max: u64,
+}
+
 // Where from?
 pub type blkcnt64_t = i64;
 #[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
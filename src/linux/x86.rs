@@ -25,6 +25,12 @@ pub const SO_BROADCAST: c_int = 6;
 
 pub const SIGCHLD: c_ulong = 17;
 
+// arch/x86/include/uapi/asm/signal.h
+pub const SIGBUS: c_int = 7;
+pub const SA_ONSTACK: c_ulong = 0x08000000;
+pub const SA_SIGINFO: c_ulong = 0x00000004;
+pub const SIGSTKSZ: size_t = 8192;
+
 // include/linux/net.h
 pub const SOCK_STREAM: c_int = 1;
 pub const SOCK_DGRAM: c_int = 2;
@@ -85,6 +91,10 @@ pub struct stat64 {
     pub st_ino: ino64_t,
 }
 
+// arch/x86/include/asm/elf.h
+pub const R_386_NONE: u32 = 0;
+pub const R_386_RELATIVE: u32 = 8;
+
 pub type blksize_t = i32;
 
 #[derive(Clone, Copy)]
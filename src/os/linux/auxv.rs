@@ -0,0 +1,83 @@
+//! The auxiliary vector
+//!
+//! The kernel passes a list of (type, value) pairs to every new process, on
+//! the stack, right after the environment. They describe the process and the
+//! system it's running on: the page size, the hardware capabilities of the
+//! CPU, where the vDSO was mapped and so on. See `getauxval(3)`.
+//!
+//! ```
+//! #![feature(auxv)]
+//!
+//! use std::os::linux::auxv::{self, AT_PAGESZ};
+//!
+//! println!("page size: {:?}", auxv::getauxval(AT_PAGESZ));
+//!
+//! for (type_, value) in auxv::entries() {
+//!     println!("{}: {:#x}", type_, value);
+//! }
+//! ```
+
+#![unstable(feature = "auxv", issue = "0")]
+
+use ffi::CStr;
+use fmt;
+use libc;
+use os::raw::c_ulong;
+
+pub use linux::{AT_NULL, AT_IGNORE, AT_EXECFD, AT_PHDR, AT_PHENT, AT_PHNUM};
+pub use linux::{AT_PAGESZ, AT_BASE, AT_FLAGS, AT_ENTRY, AT_NOTELF, AT_UID};
+pub use linux::{AT_EUID, AT_GID, AT_EGID, AT_PLATFORM, AT_HWCAP, AT_CLKTCK};
+pub use linux::{AT_SECURE, AT_BASE_PLATFORM, AT_RANDOM, AT_HWCAP2, AT_EXECFN};
+pub use linux::{AT_SYSINFO_EHDR, AT_MINSIGSTKSZ};
+
+/// Returns the value of the entry of type `type_`, or `None` if the kernel
+/// didn't pass one.
+///
+/// Some values are pointers, e.g. those of `AT_EXECFN` and `AT_RANDOM`; see
+/// `execfn` and `random` for safe access to what they point to.
+pub fn getauxval(type_: c_ulong) -> Option<c_ulong> {
+    unsafe { libc::getauxval(type_) }
+}
+
+/// Returns an iterator over all the entries, as (type, value) pairs.
+pub fn entries() -> Entries {
+    Entries { entry: unsafe { libc::auxv() } }
+}
+
+/// Returns the path the executable was started with (`AT_EXECFN`), i.e. the
+/// path that was passed to `execve`.
+pub fn execfn() -> Option<&'static CStr> {
+    getauxval(AT_EXECFN).map(|ptr| unsafe { CStr::from_ptr(ptr as *const _) })
+}
+
+/// Returns the 16 random bytes the kernel provides to every process
+/// (`AT_RANDOM`).
+pub fn random() -> Option<&'static [u8; 16]> {
+    getauxval(AT_RANDOM).map(|ptr| unsafe { &*(ptr as *const [u8; 16]) })
+}
+
+/// Iterator over the entries of the auxiliary vector, see `entries`
+pub struct Entries {
+    entry: *const [c_ulong; 2],
+}
+
+impl Iterator for Entries {
+    type Item = (c_ulong, c_ulong);
+
+    fn next(&mut self) -> Option<(c_ulong, c_ulong)> {
+        unsafe {
+            if self.entry.is_null() || (*self.entry)[0] == AT_NULL {
+                return None;
+            }
+            let entry = *self.entry;
+            self.entry = self.entry.offset(1);
+            Some((entry[0], entry[1]))
+        }
+    }
+}
+
+impl fmt::Debug for Entries {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("Entries { .. }")
+    }
+}
@@ -12,5 +12,8 @@
 
 #![stable(feature = "raw_ext", since = "1.1.0")]
 
+pub mod auxv;
 pub mod raw;
 pub mod fs;
+pub mod sync;
+pub mod thread;
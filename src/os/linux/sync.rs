@@ -0,0 +1,671 @@
+//! Linux-specific synchronization primitives
+//!
+//! `PiMutex` is a mutex with priority inheritance, for real-time threads (see
+//! `std::os::linux::thread::set_sched_policy`) that share data with lower
+//! priority threads.
+//!
+//! ```
+//! #![feature(linux_sync)]
+//!
+//! use std::os::linux::sync::PiMutex;
+//! use std::sync::Arc;
+//! use std::thread;
+//!
+//! let mutex = Arc::new(PiMutex::new(0));
+//! let c_mutex = mutex.clone();
+//!
+//! thread::spawn(move || {
+//!     *c_mutex.lock().unwrap() = 10;
+//! }).join().unwrap();
+//! assert_eq!(*mutex.lock().unwrap(), 10);
+//! ```
+//!
+//! `SharedMutex`, `SharedCondvar` and `Semaphore` work across processes when
+//! placed in memory mapped with `MAP_SHARED`, such as a `SharedMemory`. Forked
+//! children inherit anonymous mappings, and unrelated processes can map the
+//! same file.
+//!
+//! ```no_run
+//! #![feature(linux_sync)]
+//!
+//! use std::fs::OpenOptions;
+//! use std::mem;
+//! use std::os::linux::sync::{SharedMemory, SharedMutex};
+//!
+//! let file = OpenOptions::new().read(true).write(true).create(true)
+//!     .open("/dev/shm/counter").unwrap();
+//! file.set_len(mem::size_of::<SharedMutex<u64>>() as u64).unwrap();
+//!
+//! // A zero-filled `SharedMutex` is unlocked
+//! let counter = unsafe { SharedMemory::<SharedMutex<u64>>::from_file(&file).unwrap() };
+//! *counter.lock() += 1;
+//! ```
+
+#![unstable(feature = "linux_sync", issue = "0")]
+
+use cell::UnsafeCell;
+use cmp;
+use fmt;
+use fs::File;
+use io;
+use libc;
+use marker;
+use mem;
+use ops::{Deref, DerefMut};
+use os::unix::io::AsRawFd;
+use ptr;
+use sync::{LockResult, TryLockError, TryLockResult, WaitTimeoutResult};
+use sync::atomic::AtomicU32;
+use sync::atomic::Ordering::{Acquire, Relaxed, SeqCst};
+use sys::condvar::Condvar as SysCondvar;
+use sys::cvt_ptr;
+use sys::futex::{shared_futex_wait, shared_futex_wake};
+use sys::mutex as sys;
+use sys_common::FromInner;
+use sys_common::poison;
+use time::{Duration, Instant};
+
+/// A mutual exclusion primitive with priority inheritance
+///
+/// This works like `std::sync::Mutex`, but while a thread is blocked on the
+/// lock, the owner of the lock runs with at least the priority of the blocked
+/// thread, see `FUTEX_LOCK_PI` in `futex(2)`. This prevents a low priority
+/// thread holding the lock from being preempted by medium priority threads
+/// while a high priority thread waits for it (priority inversion).
+///
+/// # Poisoning
+///
+/// Besides being poisoned when a thread panics while holding it, like
+/// `Mutex`, a `PiMutex` is poisoned when its owner exits without unlocking it,
+/// for example after leaking the guard with `mem::forget`. Where a robust
+/// pthread mutex would make `pthread_mutex_lock` fail with `EOWNERDEAD`, the
+/// next thread to lock the `PiMutex` gets the lock wrapped in a
+/// `PoisonError`, instead of blocking forever.
+pub struct PiMutex<T: ?Sized> {
+    // Boxed, so that the mutex doesn't move while it's linked into the robust
+    // list of its owner
+    inner: Box<sys::PiMutex>,
+    poison: poison::Flag,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for PiMutex<T> { }
+unsafe impl<T: ?Sized + Send> Sync for PiMutex<T> { }
+
+/// An RAII implementation of a "scoped lock" of a `PiMutex`. When this
+/// structure is dropped (falls out of scope), the lock will be unlocked.
+///
+/// This structure is created by the `lock` and `try_lock` methods on
+/// `PiMutex`.
+#[must_use]
+pub struct PiMutexGuard<'a, T: ?Sized + 'a> {
+    lock: &'a PiMutex<T>,
+    poison: poison::Guard,
+}
+
+// The kernel knows the owner of the lock by its thread id, so it must be
+// unlocked by the thread that locked it
+impl<'a, T: ?Sized> !marker::Send for PiMutexGuard<'a, T> {}
+
+impl<T> PiMutex<T> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    pub fn new(t: T) -> PiMutex<T> {
+        let m = PiMutex {
+            inner: box sys::PiMutex::new(),
+            poison: poison::Flag::new(),
+            data: UnsafeCell::new(t),
+        };
+        unsafe {
+            m.inner.init();
+        }
+        m
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    ///
+    /// # Errors
+    ///
+    /// If the mutex is poisoned, this call will return an error instead.
+    pub fn into_inner(self) -> LockResult<T> {
+        unsafe {
+            let (inner, poison, data) = {
+                let PiMutex { ref inner, ref poison, ref data } = self;
+                (ptr::read(inner), ptr::read(poison), ptr::read(data))
+            };
+            mem::forget(self);
+            inner.destroy();  // Keep in sync with the `Drop` impl.
+            drop(inner);
+
+            poison::map_result(poison.borrow(), |_| data.into_inner())
+        }
+    }
+}
+
+impl<T: ?Sized> PiMutex<T> {
+    /// Acquires the mutex, blocking the current thread until it is able to do
+    /// so.
+    ///
+    /// While the current thread is blocked, the owner of the mutex inherits
+    /// its priority if it's higher than its own.
+    ///
+    /// # Errors
+    ///
+    /// If the mutex is poisoned, this call will return an error once the mutex
+    /// is acquired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the lock is already held by the current thread.
+    pub fn lock(&self) -> LockResult<PiMutexGuard<T>> {
+        unsafe {
+            if self.inner.lock() {
+                self.poison.set();
+            }
+            PiMutexGuard::new(self)
+        }
+    }
+
+    /// Attempts to acquire this lock, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// If the lock could not be acquired at this time, `WouldBlock` is
+    /// returned. If the mutex is poisoned, this call will return `Poisoned`
+    /// if the mutex would otherwise be acquired.
+    pub fn try_lock(&self) -> TryLockResult<PiMutexGuard<T>> {
+        unsafe {
+            match self.inner.try_lock() {
+                Some(owner_died) => {
+                    if owner_died {
+                        self.poison.set();
+                    }
+                    Ok(PiMutexGuard::new(self)?)
+                }
+                None => Err(TryLockError::WouldBlock),
+            }
+        }
+    }
+
+    /// Determines whether the mutex is poisoned.
+    pub fn is_poisoned(&self) -> bool {
+        self.poison.get()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `PiMutex` mutably, no actual locking needs
+    /// to take place.
+    ///
+    /// # Errors
+    ///
+    /// If the mutex is poisoned, this call will return an error instead.
+    pub fn get_mut(&mut self) -> LockResult<&mut T> {
+        let data = unsafe { &mut *self.data.get() };
+        poison::map_result(self.poison.borrow(), |_| data)
+    }
+}
+
+unsafe impl<#[may_dangle] T: ?Sized> Drop for PiMutex<T> {
+    fn drop(&mut self) {
+        // IMPORTANT: This code must be kept in sync with `PiMutex::into_inner`.
+        unsafe { self.inner.destroy() }
+    }
+}
+
+impl<T: Default> Default for PiMutex<T> {
+    fn default() -> PiMutex<T> {
+        PiMutex::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for PiMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Ok(guard) => write!(f, "PiMutex {{ data: {:?} }}", &*guard),
+            Err(TryLockError::Poisoned(err)) => {
+                write!(f, "PiMutex {{ data: Poisoned({:?}) }}", &**err.get_ref())
+            },
+            Err(TryLockError::WouldBlock) => write!(f, "PiMutex {{ <locked> }}")
+        }
+    }
+}
+
+impl<'mutex, T: ?Sized> PiMutexGuard<'mutex, T> {
+    unsafe fn new(lock: &'mutex PiMutex<T>) -> LockResult<PiMutexGuard<'mutex, T>> {
+        poison::map_result(lock.poison.borrow(), |guard| {
+            PiMutexGuard {
+                lock: lock,
+                poison: guard,
+            }
+        })
+    }
+}
+
+impl<'mutex, T: ?Sized> Deref for PiMutexGuard<'mutex, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'mutex, T: ?Sized> DerefMut for PiMutexGuard<'mutex, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for PiMutexGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.lock.poison.done(&self.poison);
+            self.lock.inner.unlock();
+        }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for PiMutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PiMutexGuard")
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+/// A mutual exclusion primitive that works across processes
+///
+/// Unlike `std::sync::Mutex`, which only synchronizes the threads of one
+/// process, a `SharedMutex` placed in memory shared with other processes
+/// (`MAP_SHARED`, see `SharedMemory`) is locked and unlocked by all of them.
+///
+/// The mutex doesn't refer to any memory outside of itself and all zero bytes
+/// are an unlocked mutex, so it can be used in place by every process mapping
+/// it. For the same reason, `T` should not contain pointers or anything else
+/// that is only meaningful in one process.
+///
+/// There is no poisoning: a panic while holding the lock unlocks it, and a
+/// lock held by a process that exits or gets killed stays locked.
+#[repr(C)]
+pub struct SharedMutex<T: ?Sized> {
+    inner: sys::Mutex,
+    data: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for SharedMutex<T> { }
+unsafe impl<T: ?Sized + Send> Sync for SharedMutex<T> { }
+
+/// An RAII implementation of a "scoped lock" of a `SharedMutex`. When this
+/// structure is dropped (falls out of scope), the lock will be unlocked.
+///
+/// This structure is created by the `lock` and `try_lock` methods on
+/// `SharedMutex`.
+#[must_use]
+pub struct SharedMutexGuard<'a, T: ?Sized + 'a> {
+    lock: &'a SharedMutex<T>,
+}
+
+impl<T> SharedMutex<T> {
+    /// Creates a new mutex in an unlocked state ready for use.
+    pub const fn new(t: T) -> SharedMutex<T> {
+        SharedMutex {
+            inner: sys::Mutex::new(),
+            data: UnsafeCell::new(t),
+        }
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    pub fn into_inner(self) -> T {
+        unsafe { self.data.into_inner() }
+    }
+}
+
+impl<T: ?Sized> SharedMutex<T> {
+    /// Acquires the mutex, blocking the current thread until it is able to do
+    /// so.
+    pub fn lock(&self) -> SharedMutexGuard<T> {
+        unsafe {
+            self.inner.lock_shared();
+        }
+        SharedMutexGuard { lock: self }
+    }
+
+    /// Attempts to acquire this lock, without blocking.
+    ///
+    /// Returns `None` if the lock is held by another thread or process.
+    pub fn try_lock(&self) -> Option<SharedMutexGuard<T>> {
+        if unsafe { self.inner.try_lock() } {
+            Some(SharedMutexGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the `SharedMutex` mutably, no actual locking
+    /// needs to take place.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.data.get() }
+    }
+}
+
+impl<T: Default> Default for SharedMutex<T> {
+    fn default() -> SharedMutex<T> {
+        SharedMutex::new(Default::default())
+    }
+}
+
+impl<T: ?Sized + fmt::Debug> fmt::Debug for SharedMutex<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.try_lock() {
+            Some(guard) => write!(f, "SharedMutex {{ data: {:?} }}", &*guard),
+            None => write!(f, "SharedMutex {{ <locked> }}")
+        }
+    }
+}
+
+impl<'mutex, T: ?Sized> Deref for SharedMutexGuard<'mutex, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.data.get() }
+    }
+}
+
+impl<'mutex, T: ?Sized> DerefMut for SharedMutexGuard<'mutex, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.data.get() }
+    }
+}
+
+impl<'a, T: ?Sized> Drop for SharedMutexGuard<'a, T> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            self.lock.inner.unlock_shared();
+        }
+    }
+}
+
+impl<'a, T: ?Sized + fmt::Debug> fmt::Debug for SharedMutexGuard<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedMutexGuard")
+            .field("lock", &self.lock)
+            .finish()
+    }
+}
+
+/// A condition variable that works across processes
+///
+/// This is the `std::sync::Condvar` counterpart of `SharedMutex`: placed in
+/// shared memory, it wakes up threads of any process waiting on it. All zero
+/// bytes are a valid `SharedCondvar`.
+///
+/// Unlike `Condvar`, a `SharedCondvar` doesn't check that it's always used
+/// with the same mutex.
+#[repr(C)]
+pub struct SharedCondvar {
+    inner: SysCondvar,
+}
+
+impl SharedCondvar {
+    /// Creates a new condition variable which is ready to be waited on and
+    /// notified.
+    pub const fn new() -> SharedCondvar {
+        SharedCondvar { inner: SysCondvar::new() }
+    }
+
+    /// Blocks the current thread until this condition variable receives a
+    /// notification.
+    ///
+    /// The lock of `guard` is released while waiting and acquired again before
+    /// returning. Like with `Condvar`, spurious wakeups are possible.
+    pub fn wait<'a, T: ?Sized>(&self, guard: SharedMutexGuard<'a, T>)
+                               -> SharedMutexGuard<'a, T> {
+        unsafe {
+            self.inner.wait_shared(&guard.lock.inner);
+        }
+        guard
+    }
+
+    /// Waits on this condition variable for a notification, timing out after
+    /// the specified duration.
+    ///
+    /// The returned `WaitTimeoutResult` tells whether the wait timed out.
+    pub fn wait_timeout<'a, T: ?Sized>(&self, guard: SharedMutexGuard<'a, T>, dur: Duration)
+                                       -> (SharedMutexGuard<'a, T>, WaitTimeoutResult) {
+        let success = unsafe {
+            self.inner.wait_timeout_shared(&guard.lock.inner, dur)
+        };
+        (guard, WaitTimeoutResult::from_inner(!success))
+    }
+
+    /// Wakes up one blocked thread on this condvar.
+    pub fn notify_one(&self) {
+        unsafe { self.inner.notify_one_shared() }
+    }
+
+    /// Wakes up all blocked threads on this condvar.
+    pub fn notify_all(&self) {
+        unsafe { self.inner.notify_all_shared() }
+    }
+}
+
+impl Default for SharedCondvar {
+    fn default() -> SharedCondvar {
+        SharedCondvar::new()
+    }
+}
+
+impl fmt::Debug for SharedCondvar {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("SharedCondvar { .. }")
+    }
+}
+
+/// A counting semaphore that works across processes
+///
+/// `wait` takes one unit from the semaphore, blocking while there is none, and
+/// `post` gives one back. Placed in shared memory, it can for example hand out
+/// jobs to worker processes. All zero bytes are a semaphore with a value of
+/// 0.
+#[repr(C)]
+pub struct Semaphore {
+    value: AtomicU32,
+    // The number of threads (may be) sleeping in `wait`, so that `post` can
+    // skip the wake syscall if there are none
+    waiters: AtomicU32,
+}
+
+impl Semaphore {
+    /// Creates a new semaphore with the initial `value`.
+    pub const fn new(value: u32) -> Semaphore {
+        Semaphore {
+            value: AtomicU32::new(value),
+            waiters: AtomicU32::new(0),
+        }
+    }
+
+    /// Decrements the value of the semaphore, blocking the current thread
+    /// while it's 0.
+    pub fn wait(&self) {
+        self.wait_optional_timeout(None);
+    }
+
+    /// Like `wait`, but gives up after `dur`.
+    ///
+    /// Returns `false` if the wait timed out without decrementing the value.
+    pub fn wait_timeout(&self, dur: Duration) -> bool {
+        self.wait_optional_timeout(Some(dur))
+    }
+
+    /// Decrements the value of the semaphore if it isn't 0.
+    ///
+    /// Returns `false` if the value was 0.
+    pub fn try_wait(&self) -> bool {
+        let mut value = self.value.load(Relaxed);
+        while value != 0 {
+            match self.value.compare_exchange_weak(value, value - 1, Acquire, Relaxed) {
+                Ok(_) => return true,
+                Err(v) => value = v,
+            }
+        }
+        false
+    }
+
+    fn wait_optional_timeout(&self, timeout: Option<Duration>) -> bool {
+        // Measured as elapsed time rather than as a deadline, which might not
+        // be representable for large timeouts
+        let start = Instant::now();
+        loop {
+            if self.try_wait() {
+                return true;
+            }
+            let remaining = match timeout {
+                Some(timeout) => {
+                    let elapsed = start.elapsed();
+                    if elapsed >= timeout {
+                        return false;
+                    }
+                    Some(timeout - elapsed)
+                }
+                None => None,
+            };
+
+            // Both this increment and the one of the value in `post` are
+            // `SeqCst`: either `post` sees us waiting and wakes us up, or the
+            // futex wait sees the new value and returns right away.
+            self.waiters.fetch_add(1, SeqCst);
+            shared_futex_wait(&self.value, 0, remaining);
+            self.waiters.fetch_sub(1, SeqCst);
+        }
+    }
+
+    /// Increments the value of the semaphore, waking up a thread blocked in
+    /// `wait` if there is one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value would overflow a `u32`.
+    pub fn post(&self) {
+        if self.value.fetch_add(1, SeqCst) == u32::max_value() {
+            panic!("semaphore value overflowed");
+        }
+        if self.waiters.load(SeqCst) != 0 {
+            shared_futex_wake(&self.value);
+        }
+    }
+
+    /// Returns the current value of the semaphore.
+    ///
+    /// Other threads and processes may change it at any time, so this is only
+    /// a hint.
+    pub fn value(&self) -> u32 {
+        self.value.load(Relaxed)
+    }
+}
+
+impl Default for Semaphore {
+    fn default() -> Semaphore {
+        Semaphore::new(0)
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("value", &self.value())
+            .finish()
+    }
+}
+
+/// A value in memory mapped with `MAP_SHARED`
+///
+/// This is where `SharedMutex`, `SharedCondvar` and `Semaphore` are meant to
+/// live. An anonymous mapping created by `new` is shared with the children
+/// the process forks afterwards, a file mapping created by `from_file` with
+/// every process that maps the same file.
+///
+/// Since other processes may still be using it, the value is never dropped:
+/// dropping a `SharedMemory` only unmaps it.
+pub struct SharedMemory<T> {
+    ptr: *mut T,
+}
+
+unsafe impl<T: Send + Sync> Send for SharedMemory<T> { }
+unsafe impl<T: Send + Sync> Sync for SharedMemory<T> { }
+
+impl<T> SharedMemory<T> {
+    /// Moves `value` into a new anonymous shared mapping.
+    pub fn new(value: T) -> io::Result<SharedMemory<T>> {
+        unsafe {
+            let memory = SharedMemory::map(-1)?;
+            ptr::write(memory.ptr, value);
+            Ok(memory)
+        }
+    }
+
+    /// Maps the beginning of `file`, which must be open for reading and
+    /// writing, as a `T`.
+    ///
+    /// Fails with `InvalidInput` if the file is smaller than a `T`.
+    ///
+    /// # Safety
+    ///
+    /// The contents of the file must be a valid `T`. A file just grown with
+    /// `set_len` is zero-filled, which is valid for the synchronization
+    /// primitives of this module and for integers.
+    pub unsafe fn from_file(file: &File) -> io::Result<SharedMemory<T>> {
+        if file.metadata()?.len() < mem::size_of::<T>() as u64 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput,
+                                      "file is too small for the value"));
+        }
+        SharedMemory::map(file.as_raw_fd())
+    }
+
+    unsafe fn map(fd: libc::c_int) -> io::Result<SharedMemory<T>> {
+        let flags = if fd < 0 {
+            libc::MAP_SHARED | libc::MAP_ANON
+        } else {
+            libc::MAP_SHARED
+        };
+        let map = cvt_ptr(libc::mmap(ptr::null_mut(),
+                                     SharedMemory::<T>::len(),
+                                     libc::PROT_READ | libc::PROT_WRITE,
+                                     flags,
+                                     fd,
+                                     0))?;
+        Ok(SharedMemory { ptr: map as *mut T })
+    }
+
+    // The length of the mapping. `mmap` rejects a length of 0, so a
+    // zero-sized `T` still gets (a page of) memory.
+    fn len() -> usize {
+        cmp::max(mem::size_of::<T>(), 1)
+    }
+}
+
+impl<T> Deref for SharedMemory<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.ptr }
+    }
+}
+
+impl<T> Drop for SharedMemory<T> {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, SharedMemory::<T>::len());
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedMemory<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SharedMemory")
+            .field("value", &**self)
+            .finish()
+    }
+}
@@ -0,0 +1,268 @@
+//! Linux-specific thread functionality
+//!
+//! CPU affinity, scheduling policy and nice value of the current thread, and
+//! of spawned threads through `JoinHandleExt`. Threads are identified by
+//! their kernel thread id, see `sched_setaffinity(2)`, `sched(7)` and
+//! `setpriority(2)`.
+//!
+//! ```
+//! #![feature(linux_thread)]
+//!
+//! use std::os::linux::thread::{self, CpuSet};
+//!
+//! println!("{} CPUs available", thread::available_parallelism().unwrap());
+//!
+//! // Pin the current thread to the first CPU
+//! let mut cpus = CpuSet::new();
+//! cpus.set(0);
+//! thread::set_affinity(&cpus).unwrap();
+//! ```
+
+#![unstable(feature = "linux_thread", issue = "0")]
+
+use fmt;
+use io;
+use libc;
+use mem;
+use os::raw::c_ulong;
+use sys::cvt;
+use sys_common::AsInner;
+use thread::JoinHandle;
+
+/// The number of CPUs a `CpuSet` can hold
+pub const CPU_SETSIZE: usize = 1024;
+
+const BITS: usize = 8 * mem::size_of::<c_ulong>();
+
+/// A set of CPUs, numbered from 0 to `CPU_SETSIZE - 1`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct CpuSet {
+    bits: [c_ulong; CPU_SETSIZE / BITS],
+}
+
+impl CpuSet {
+    /// Creates an empty set.
+    pub fn new() -> CpuSet {
+        CpuSet { bits: [0; CPU_SETSIZE / BITS] }
+    }
+
+    /// Adds `cpu` to the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not less than `CPU_SETSIZE`.
+    pub fn set(&mut self, cpu: usize) {
+        assert!(cpu < CPU_SETSIZE, "CPU index out of range");
+        self.bits[cpu / BITS] |= 1 << (cpu % BITS);
+    }
+
+    /// Removes `cpu` from the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cpu` is not less than `CPU_SETSIZE`.
+    pub fn clear(&mut self, cpu: usize) {
+        assert!(cpu < CPU_SETSIZE, "CPU index out of range");
+        self.bits[cpu / BITS] &= !(1 << (cpu % BITS));
+    }
+
+    /// Returns `true` if `cpu` is in the set.
+    pub fn is_set(&self, cpu: usize) -> bool {
+        cpu < CPU_SETSIZE && self.bits[cpu / BITS] & (1 << (cpu % BITS)) != 0
+    }
+
+    /// Returns the number of CPUs in the set.
+    pub fn count(&self) -> usize {
+        self.bits.iter().map(|b| b.count_ones() as usize).sum()
+    }
+}
+
+impl Default for CpuSet {
+    fn default() -> CpuSet {
+        CpuSet::new()
+    }
+}
+
+impl fmt::Debug for CpuSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries((0..CPU_SETSIZE).filter(|&cpu| self.is_set(cpu))).finish()
+    }
+}
+
+/// A scheduling policy, see `sched(7)`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SchedPolicy {
+    /// The default time-sharing policy (`SCHED_OTHER`)
+    Other,
+    /// First-in, first-out real-time policy (`SCHED_FIFO`) with a static
+    /// priority from 1 (lowest) to 99 (highest)
+    Fifo(i32),
+    /// Round-robin real-time policy (`SCHED_RR`) with a static priority from
+    /// 1 (lowest) to 99 (highest)
+    RoundRobin(i32),
+    /// Time-sharing policy for CPU-intensive batch jobs (`SCHED_BATCH`)
+    Batch,
+    /// Policy for very low priority background jobs (`SCHED_IDLE`)
+    Idle,
+}
+
+/// Returns the kernel thread id of the current thread.
+pub fn tid() -> libc::pid_t {
+    unsafe { libc::gettid() }
+}
+
+/// Returns the set of CPUs the current thread may run on.
+pub fn affinity() -> io::Result<CpuSet> {
+    get_affinity(tid())
+}
+
+/// Restricts the current thread to the CPUs in `cpus`.
+pub fn set_affinity(cpus: &CpuSet) -> io::Result<()> {
+    set_affinity_of(tid(), cpus)
+}
+
+/// Returns the scheduling policy of the current thread.
+pub fn sched_policy() -> io::Result<SchedPolicy> {
+    get_sched_policy(tid())
+}
+
+/// Sets the scheduling policy of the current thread.
+///
+/// The real-time policies usually require `CAP_SYS_NICE` or a suitable
+/// `RLIMIT_RTPRIO`.
+pub fn set_sched_policy(policy: SchedPolicy) -> io::Result<()> {
+    set_sched_policy_of(tid(), policy)
+}
+
+/// Returns the nice value of the current thread, from -20 (highest priority)
+/// to 19 (lowest priority).
+pub fn nice() -> io::Result<i32> {
+    get_nice(tid())
+}
+
+/// Sets the nice value of the current thread.
+pub fn set_nice(nice: i32) -> io::Result<()> {
+    set_nice_of(tid(), nice)
+}
+
+/// Returns the number of CPUs the current thread may run on, which is a
+/// reasonable default for the number of worker threads.
+pub fn available_parallelism() -> io::Result<usize> {
+    affinity().map(|cpus| cpus.count())
+}
+
+/// Linux-specific extensions to `thread::JoinHandle`
+pub trait JoinHandleExt {
+    /// Returns the kernel thread id of the thread, or 0 once it has exited.
+    fn tid(&self) -> libc::pid_t;
+
+    /// Returns the set of CPUs the thread may run on.
+    fn affinity(&self) -> io::Result<CpuSet>;
+
+    /// Restricts the thread to the CPUs in `cpus`.
+    fn set_affinity(&self, cpus: &CpuSet) -> io::Result<()>;
+
+    /// Returns the scheduling policy of the thread.
+    fn sched_policy(&self) -> io::Result<SchedPolicy>;
+
+    /// Sets the scheduling policy of the thread.
+    fn set_sched_policy(&self, policy: SchedPolicy) -> io::Result<()>;
+
+    /// Returns the nice value of the thread.
+    fn nice(&self) -> io::Result<i32>;
+
+    /// Sets the nice value of the thread.
+    fn set_nice(&self, nice: i32) -> io::Result<()>;
+}
+
+impl<T> JoinHandleExt for JoinHandle<T> {
+    fn tid(&self) -> libc::pid_t {
+        unsafe { libc::pthread_gettid_np(self.as_inner().id()) }
+    }
+    fn affinity(&self) -> io::Result<CpuSet> {
+        get_affinity(live_tid(self.tid())?)
+    }
+    fn set_affinity(&self, cpus: &CpuSet) -> io::Result<()> {
+        set_affinity_of(live_tid(self.tid())?, cpus)
+    }
+    fn sched_policy(&self) -> io::Result<SchedPolicy> {
+        get_sched_policy(live_tid(self.tid())?)
+    }
+    fn set_sched_policy(&self, policy: SchedPolicy) -> io::Result<()> {
+        set_sched_policy_of(live_tid(self.tid())?, policy)
+    }
+    fn nice(&self) -> io::Result<i32> {
+        get_nice(live_tid(self.tid())?)
+    }
+    fn set_nice(&self, nice: i32) -> io::Result<()> {
+        set_nice_of(live_tid(self.tid())?, nice)
+    }
+}
+
+// The syscalls take 0 to mean the calling thread, which is not what we want
+// for a thread that has already exited.
+fn live_tid(tid: libc::pid_t) -> io::Result<libc::pid_t> {
+    if tid == 0 {
+        Err(io::Error::from_raw_os_error(libc::ESRCH))
+    } else {
+        Ok(tid)
+    }
+}
+
+fn get_affinity(tid: libc::pid_t) -> io::Result<CpuSet> {
+    let mut cpus = CpuSet::new();
+    // Returns the number of bytes the kernel filled in, the rest stays zeroed
+    cvt(unsafe {
+        libc::sched_getaffinity(tid,
+                                mem::size_of::<CpuSet>() as libc::c_uint,
+                                cpus.bits.as_mut_ptr())
+    })?;
+    Ok(cpus)
+}
+
+fn set_affinity_of(tid: libc::pid_t, cpus: &CpuSet) -> io::Result<()> {
+    cvt(unsafe {
+        libc::sched_setaffinity(tid,
+                                mem::size_of::<CpuSet>() as libc::c_uint,
+                                cpus.bits.as_ptr())
+    })?;
+    Ok(())
+}
+
+fn get_sched_policy(tid: libc::pid_t) -> io::Result<SchedPolicy> {
+    let policy = cvt(unsafe { libc::sched_getscheduler(tid) })?;
+    let mut param = libc::sched_param { sched_priority: 0 };
+    cvt(unsafe { libc::sched_getparam(tid, &mut param) })?;
+    match policy & !libc::SCHED_RESET_ON_FORK {
+        libc::SCHED_OTHER => Ok(SchedPolicy::Other),
+        libc::SCHED_FIFO => Ok(SchedPolicy::Fifo(param.sched_priority)),
+        libc::SCHED_RR => Ok(SchedPolicy::RoundRobin(param.sched_priority)),
+        libc::SCHED_BATCH => Ok(SchedPolicy::Batch),
+        libc::SCHED_IDLE => Ok(SchedPolicy::Idle),
+        _ => Err(io::Error::new(io::ErrorKind::Other, "unknown scheduling policy")),
+    }
+}
+
+fn set_sched_policy_of(tid: libc::pid_t, policy: SchedPolicy) -> io::Result<()> {
+    let (policy, priority) = match policy {
+        SchedPolicy::Other => (libc::SCHED_OTHER, 0),
+        SchedPolicy::Fifo(priority) => (libc::SCHED_FIFO, priority),
+        SchedPolicy::RoundRobin(priority) => (libc::SCHED_RR, priority),
+        SchedPolicy::Batch => (libc::SCHED_BATCH, 0),
+        SchedPolicy::Idle => (libc::SCHED_IDLE, 0),
+    };
+    let param = libc::sched_param { sched_priority: priority };
+    cvt(unsafe { libc::sched_setscheduler(tid, policy, &param) })?;
+    Ok(())
+}
+
+fn get_nice(tid: libc::pid_t) -> io::Result<i32> {
+    // The syscall returns `20 - nice`, so that it's never negative
+    let ret = cvt(unsafe { libc::getpriority(libc::PRIO_PROCESS, tid) })?;
+    Ok(20 - ret)
+}
+
+fn set_nice_of(tid: libc::pid_t, nice: i32) -> io::Result<()> {
+    cvt(unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, nice) })?;
+    Ok(())
+}
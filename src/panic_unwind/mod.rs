@@ -0,0 +1,85 @@
+// Copyright 2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Implementation of panics via stack unwinding
+//!
+//! This module is an implementation of panics in Rust which uses the Itanium
+//! style stack unwinding. Upstream this is the `panic_unwind` crate, but we
+//! can't link to a separate panic runtime because we are `std` itself, so the
+//! crate lives on as a module that `panicking` calls directly.
+//!
+//! Unwinding is only supported on the architectures our unwinder knows about
+//! (see `sys::unwind`). Everywhere else panics abort the process.
+
+use any::Any;
+use intrinsics;
+use mem;
+use raw;
+
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+#[path = "gcc.rs"]
+mod imp;
+
+#[cfg(not(any(target_arch = "aarch64",
+              target_arch = "arm",
+              target_arch = "x86",
+              target_arch = "x86_64")))]
+mod imp {
+    use any::Any;
+    use intrinsics;
+
+    pub unsafe fn panic(_data: Box<Any + Send>) -> u32 {
+        intrinsics::abort()
+    }
+
+    pub fn payload() -> *mut u8 {
+        0 as *mut u8
+    }
+
+    pub unsafe fn cleanup(_ptr: *mut u8) -> Box<Any + Send> {
+        intrinsics::abort()
+    }
+}
+
+pub mod dwarf;
+
+// Entry point for catching an exception, implemented using the `try` intrinsic
+// in the compiler.
+//
+// The interaction between the `payload` function and the compiler is pretty
+// hairy and tightly coupled, for more information see the compiler's
+// implementation of this.
+pub unsafe fn __rust_maybe_catch_panic(f: fn(*mut u8),
+                                       data: *mut u8,
+                                       data_ptr: *mut usize,
+                                       vtable_ptr: *mut usize)
+                                       -> u32 {
+    let mut payload = imp::payload();
+    if intrinsics::try(f, data, &mut payload as *mut _ as *mut _) == 0 {
+        0
+    } else {
+        let obj = mem::transmute::<_, raw::TraitObject>(imp::cleanup(payload));
+        *data_ptr = obj.data as usize;
+        *vtable_ptr = obj.vtable as usize;
+        1
+    }
+}
+
+// Entry point for raising an exception, just delegates to the platform-specific
+// implementation.
+pub unsafe fn __rust_start_panic(data: usize, vtable: usize) -> u32 {
+    imp::panic(mem::transmute(raw::TraitObject {
+        data: data as *mut (),
+        vtable: vtable as *mut (),
+    }))
+}
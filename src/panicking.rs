@@ -1,18 +1,215 @@
+use any::Any;
+use cell::Cell;
 use core::fmt;
-use io::Write;
+use io::{LOCAL_STDERR, Write};
+use mem;
+use ptr;
+use raw;
 
-use {io, process};
+use panic_unwind::{__rust_maybe_catch_panic, __rust_start_panic};
+use sync::atomic::{AtomicBool, Ordering};
+use sys::stdio::Stderr;
+use sys_common::backtrace;
+use sys_common::thread_info;
+use thread::LocalKeyState;
 
+thread_local! { static PANIC_COUNT: Cell<usize> = Cell::new(0) }
+
+/// Adds `amt` to the number of panics the current thread is unwinding from,
+/// returning the new count.
+#[unstable(feature = "update_panic_count", issue = "0")]
+pub fn update_panic_count(amt: isize) -> usize {
+    PANIC_COUNT.with(|c| {
+        let next = (c.get() as isize + amt) as usize;
+        c.set(next);
+        next
+    })
+}
+
+/// Determines whether the current thread is unwinding because of panic.
+pub fn panicking() -> bool {
+    update_panic_count(0) != 0
+}
+
+// Rust 1.16.0
+/// Invoke a closure, capturing the cause of an unwinding panic if one occurs.
+pub unsafe fn try<R, F: FnOnce() -> R>(f: F) -> Result<R, Box<Any + Send>> {
+    struct Data<F, R> {
+        f: F,
+        r: R,
+    }
+
+    // We do some sketchy operations with ownership here for the sake of
+    // performance. The `Data` structure is never actually fully valid, but
+    // instead it always contains at least one uninitialized field. We can only
+    // pass pointers down to `__rust_maybe_catch_panic` (can't pass objects by
+    // value), so we do all the ownership tracking here manully.
+    //
+    // Note that this is all invariant on the values provided, so this should
+    // be safe, and an undefined behavior is prevented by the fact that we
+    // only ever read one of the fields.
+    let mut any_data = 0;
+    let mut any_vtable = 0;
+    let mut data = Data {
+        f: f,
+        r: mem::uninitialized(),
+    };
+
+    let r = __rust_maybe_catch_panic(do_call::<F, R>,
+                                     &mut data as *mut _ as *mut u8,
+                                     &mut any_data,
+                                     &mut any_vtable);
+
+    return if r == 0 {
+        debug_assert!(update_panic_count(0) == 0);
+        Ok(data.r)
+    } else {
+        update_panic_count(-1);
+        debug_assert!(update_panic_count(0) == 0);
+        Err(mem::transmute(raw::TraitObject {
+            data: any_data as *mut _,
+            vtable: any_vtable as *mut _,
+        }))
+    };
+
+    fn do_call<F: FnOnce() -> R, R>(data: *mut u8) {
+        unsafe {
+            let data = data as *mut Data<F, R>;
+            let f = ptr::read(&mut (*data).f);
+            ptr::write(&mut (*data).r, f());
+        }
+    }
+}
+
+#[cfg(not(test))]
 #[lang = "panic_fmt"]
 pub extern "C" fn rust_begin_panic(msg: fmt::Arguments,
                                    file: &'static str,
                                    line: u32)
                                    -> ! {
-    let mut stderr = io::stderr();
-    stderr.write_all(b"panicked at '")
-        .ok()
-        .and_then(|_| stderr.write_fmt(msg).ok())
-        .and_then(|_| writeln!(stderr, "', {}:{}", file, line).ok());
+    begin_panic_fmt(&msg, &(file, line))
+}
+
+/// The entry point of panics with a formatted message, from libcore or the
+/// `panic!` macro. The payload of the panic is the message as a `String`.
+#[unstable(feature = "libstd_sys_internals",
+           reason = "used by the panic! macro",
+           issue = "0")]
+#[inline(never)] #[cold]
+pub fn begin_panic_fmt(msg: &fmt::Arguments,
+                       file_line: &(&'static str, u32)) -> ! {
+    let mut s = String::new();
+    let _ = fmt::write(&mut s, *msg);
+    begin_panic(s, file_line)
+}
+
+/// The entry point of panics from the `panic!` macro with a single argument,
+/// which becomes the payload of the panic as is. A literal message stays a
+/// `&'static str`.
+#[unstable(feature = "libstd_sys_internals",
+           reason = "used by the panic! macro",
+           issue = "0")]
+#[inline(never)] #[cold]
+pub fn begin_panic<M: Any + Send>(msg: M, file_line: &(&'static str, u32)) -> ! {
+    rust_panic_with_hook(Box::new(msg), file_line)
+}
+
+#[inline(never)]
+#[cold]
+fn rust_panic_with_hook(msg: Box<Any + Send>,
+                        file_line: &(&'static str, u32)) -> ! {
+    let (file, line) = *file_line;
+    let panics = update_panic_count(1);
+
+    // If this is the third nested call (e.g. panics == 2, this is 0-indexed),
+    // printing the message probably triggered the last panic, otherwise the
+    // double-panic check below would have aborted the process. In this case
+    // abort the process real quickly as we don't want to try printing it
+    // again as it'll probably just panic again.
+    if panics > 2 {
+        let _ = writeln!(Stderr::new(), "thread panicked while processing panic. aborting.");
+        unsafe { ::intrinsics::abort() }
+    }
+
+    {
+        let s = match msg.downcast_ref::<&'static str>() {
+            Some(s) => *s,
+            None => match msg.downcast_ref::<String>() {
+                Some(s) => &s[..],
+                None => "Box<Any>",
+            }
+        };
+
+        let thread = thread_info::current_thread();
+        let name = thread.as_ref()
+            .and_then(|t| t.name())
+            .unwrap_or("<unnamed>");
+
+        let write = |err: &mut Write| {
+            let _ = writeln!(err,
+                             "thread '{}' panicked at '{}', {}:{}",
+                             name,
+                             s,
+                             file,
+                             line);
+
+            static FIRST_PANIC: AtomicBool = AtomicBool::new(true);
+            if backtrace::log_enabled() {
+                let _ = backtrace::write(err);
+            } else if FIRST_PANIC.compare_and_swap(true, false, Ordering::SeqCst) {
+                let _ = writeln!(err, "note: Run with `RUST_BACKTRACE=1` for a backtrace.");
+            }
+        };
+
+        // The message goes to the handle installed with `io::set_panic`, if
+        // any, and otherwise to the raw stderr, as the panic may have happened
+        // while the locked `io::stderr()` was in use
+        let prev = match LOCAL_STDERR.state() {
+            LocalKeyState::Valid => LOCAL_STDERR.with(|slot| slot.borrow_mut().take()),
+            _ => None,
+        };
+        match prev {
+            Some(mut stderr) => {
+                write(&mut *stderr);
+                let mut stderr = Some(stderr);
+                LOCAL_STDERR.with(|slot| {
+                    *slot.borrow_mut() = stderr.take();
+                });
+            }
+            None => write(&mut Stderr::new()),
+        }
+    }
+
+    if panics > 1 {
+        // If a thread panics while it's already unwinding then we
+        // have limited options. Currently our preference is to
+        // just abort. In the future we may consider resuming
+        // unwinding or otherwise exiting the thread cleanly.
+        let _ = writeln!(Stderr::new(), "thread panicked while panicking. aborting.");
+        unsafe { ::intrinsics::abort() }
+    }
+
+    rust_panic(msg)
+}
+
+/// Shim around rust_panic. Called by resume_unwind.
+pub fn update_count_then_panic(msg: Box<Any + Send>) -> ! {
+    update_panic_count(1);
+    rust_panic(msg)
+}
+
+/// A private no-mangle function on which to slap yer breakpoints.
+#[no_mangle]
+#[allow(private_no_mangle_fns)] // yes we get it, but we like breakpoints
+pub fn rust_panic(msg: Box<Any + Send>) -> ! {
+    let code = unsafe {
+        let obj = mem::transmute::<_, raw::TraitObject>(msg);
+        __rust_start_panic(obj.data as usize, obj.vtable as usize)
+    };
 
-    process::exit(101)
+    // `__rust_start_panic` only returns if the unwinder couldn't find a frame
+    // to unwind to, e.g. because the binary has no unwind tables
+    let mut stderr = Stderr::new();
+    let _ = writeln!(stderr, "fatal runtime error: failed to initiate panic, error {}", code);
+    unsafe { ::intrinsics::abort() }
 }
@@ -22,11 +22,12 @@
             issue = "0")]
 #![doc(hidden)]
 
-#[cfg(issue = "12")]
 // Reexport some of our utilities which are expected by other crates.
 pub use panicking::{begin_panic, begin_panic_fmt, update_panic_count};
 
 use core::intrinsics;
+#[cfg(not(test))]
+use linux::elf::elf_dyn;
 
 // The bottom of the program's stack
 //
@@ -64,14 +65,21 @@ impl Stack {
 // So this what we'll do, expressed in Rust code:
 //
 // ``` rust
-// start($BSP)
+// start($BSP, &_DYNAMIC)
 // ```
 //
-// Call some `start` function whose argument is the pointer to the bottom of the
-// stack (`$BSP`).
+// Call some `start` function whose arguments are the pointer to the bottom of
+// the stack (`$BSP`) and the address of our dynamic section (`_DYNAMIC`).
 //
 // Most Rust operations, like using a local/stack variable, will modify the
 // register where `$BSP` resides so we have to use assembly to avoid that.
+//
+// The dynamic section only exists in position independent executables
+// (static-pie), which can be loaded at any address and have to relocate
+// themselves before they can use any global, see `sys::reloc`. We compute its
+// address relative to the program counter, as anything else would need a
+// relocation. `_DYNAMIC` is weak so that it resolves to null in regular
+// static executables.
 
 #[cfg(not(test))]
 #[cfg(target_arch = "x86_64")]
@@ -79,7 +87,10 @@ impl Stack {
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("mov %rsp, %rdi
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              mov %rsp, %rdi
+              lea _DYNAMIC(%rip), %rsi
               call _start_rust"
              :
              :
@@ -95,11 +106,14 @@ pub extern "C" fn entry() -> ! {
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("mov %esp, %eax
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              mov %esp, %eax
               push %eax
               push %eax
               call 1f
-              1: push %eax
+              1: addl $$_DYNAMIC-1b, (%esp)
+              push %eax
               call _start_rust"
              :
              :
@@ -115,8 +129,14 @@ pub extern "C" fn entry() -> ! {
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("mov r0, sp
-              b _start_rust"
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              ldr r1, 1f
+              add r1, pc, r1
+              mov r0, sp
+              2: b _start_rust
+              .align 2
+              1: .word _DYNAMIC-2b"
              :
              :
              :
@@ -131,7 +151,11 @@ pub extern "C" fn entry() -> ! {
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("mov x0, sp
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              mov x0, sp
+              adrp x1, _DYNAMIC
+              add x1, x1, #:lo12:_DYNAMIC
               b _start_rust"
              :
              :
@@ -141,14 +165,35 @@ pub extern "C" fn entry() -> ! {
     }
 }
 
+// `jal` and `jalr` to a link-time address don't work if we have been loaded
+// somewhere else, so we get our own address and `_start_rust`'s relative to
+// `_gp`, which is what `$$gp` must point to anyway.
 #[cfg(not(test))]
 #[cfg(target_arch = "mips")]
 #[export_name = "__start"]
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("move $$4, $$sp
-              jal _start_rust"
+        asm!(".set push
+              .set noreorder
+              .weak _DYNAMIC
+              .hidden _DYNAMIC
+              bal 1f
+              move $$fp, $$0
+              .gpword .
+              .gpword _start_rust
+              .gpword _DYNAMIC
+              1: lw $$gp, 0($$ra)
+              subu $$gp, $$ra, $$gp
+              move $$4, $$sp
+              lw $$5, 8($$ra)
+              addu $$5, $$5, $$gp
+              lw $$25, 4($$ra)
+              addu $$25, $$25, $$gp
+              and $$sp, $$sp, -8
+              jalr $$25
+              subu $$sp, $$sp, 16
+              .set pop"
              :
              :
              :
@@ -164,8 +209,50 @@ pub extern "C" fn entry() -> ! {
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("move $$4, $$sp
-              jal _start_rust"
+        asm!(".set push
+              .set noreorder
+              .weak _DYNAMIC
+              .hidden _DYNAMIC
+              bal 1f
+              move $$fp, $$0
+              .gpdword .
+              .gpdword _start_rust
+              .gpdword _DYNAMIC
+              1: ld $$gp, 0($$ra)
+              dsubu $$gp, $$ra, $$gp
+              move $$4, $$sp
+              ld $$5, 16($$ra)
+              daddu $$5, $$5, $$gp
+              ld $$25, 8($$ra)
+              daddu $$25, $$25, $$gp
+              and $$sp, $$sp, -16
+              jalr $$25
+              nop
+              .set pop"
+             :
+             :
+             :
+             : "volatile");
+        intrinsics::unreachable()
+    }
+}
+
+#[cfg(not(test))]
+#[cfg(target_arch = "powerpc")]
+#[export_name = "_start"]
+#[naked]
+pub extern "C" fn entry() -> ! {
+    unsafe {
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              mr 3, 1
+              bl 1f
+              .long _DYNAMIC-.
+              1: mflr 4
+              lwz 5, -4(4)
+              addi 4, 4, -4
+              add 4, 4, 5
+              b _start_rust"
              :
              :
              :
@@ -175,12 +262,20 @@ pub extern "C" fn entry() -> ! {
 }
 
 #[cfg(not(test))]
-#[cfg(any(target_arch = "powerpc", target_arch = "powerpc64"))]
+#[cfg(target_arch = "powerpc64")]
 #[export_name = "_start"]
 #[naked]
 pub extern "C" fn entry() -> ! {
     unsafe {
-        asm!("mr 3, 1
+        asm!(".weak _DYNAMIC
+              .hidden _DYNAMIC
+              mr 3, 1
+              bl 1f
+              .long _DYNAMIC-.
+              1: mflr 4
+              lwa 5, -4(4)
+              addi 4, 4, -4
+              add 4, 4, 5
               b _start_rust"
              :
              :
@@ -198,19 +293,21 @@ pub extern "C" fn entry() -> ! {
 // guess, is related to the `fn main() { .. }` function one writes in their
 // crates. But the relationship is not that simple due to the presence of the
 // `start` lang item. The next section explains how that lang item works.
+//
+// Before any of that, we apply our own relocations, if we have any.
 #[cfg(not(test))]
 #[inline(never)]
 #[export_name = "_start_rust"]
-pub extern "C" fn start(sp: &'static Stack) -> ! {
+pub extern "C" fn start(sp: &'static Stack, dynamic: *const elf_dyn) -> ! {
     extern "C" {
         fn main(argc: isize, argv: *const *const u8) -> isize;
     }
 
     unsafe {
-        let mut buffer = ::mem::uninitialized();
-        ::libc::internal::init_main_thread(&mut buffer);
+        ::sys::reloc::relocate(sp as *const Stack as *const usize, dynamic);
+
+        ::libc::internal::init_main_thread();
         ::libc::_init(sp.argc(), sp.argv());
-        ::sys::args::init(sp.argc(), sp.argv());
         ::linux::exit_group(main(sp.argc(), sp.argv()) as i32)
     }
 }
@@ -232,22 +329,6 @@ pub extern "C" fn start(sp: &'static Stack) -> ! {
 // `main` function within the executable source code.
 #[cfg(not(test))]
 #[lang = "start"]
-extern "C" fn lang_start(main: *const u8,
-                         _argc: isize,
-                         _argv: *const *const u8)
-                         -> isize {
-    use core::mem;
-
-    unsafe {
-        (mem::transmute::<_, fn()>(main))();
-    }
-
-    0
-}
-
-#[cfg(issue = "14")]
-#[cfg(not(test))]
-#[lang = "start"]
 fn lang_start(main: *const u8, argc: isize, argv: *const *const u8) -> isize {
     use mem;
     use panic;
@@ -274,6 +355,8 @@ fn lang_start(main: *const u8, argc: isize, argv: *const *const u8) -> isize {
 
         // Let's run some code!
         let res = panic::catch_unwind(mem::transmute::<_, fn()>(main));
+        // steed: The C library would run these when `exit` is called
+        sys::fast_thread_local::run_dtors();
         sys_common::cleanup();
         res.is_err()
     };
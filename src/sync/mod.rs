@@ -22,23 +22,23 @@ pub use alloc::arc::{Arc, Weak};
 #[stable(feature = "rust1", since = "1.0.0")]
 pub use core::sync::atomic;
 
-// #[stable(feature = "rust1", since = "1.0.0")]
-// pub use self::barrier::{Barrier, BarrierWaitResult};
-// #[stable(feature = "rust1", since = "1.0.0")]
-// pub use self::condvar::{Condvar, WaitTimeoutResult};
+#[stable(feature = "rust1", since = "1.0.0")]
+pub use self::barrier::{Barrier, BarrierWaitResult};
+#[stable(feature = "rust1", since = "1.0.0")]
+pub use self::condvar::{Condvar, WaitTimeoutResult};
 #[stable(feature = "rust1", since = "1.0.0")]
 pub use self::mutex::{Mutex, MutexGuard};
-// #[stable(feature = "rust1", since = "1.0.0")]
-// pub use self::once::{Once, OnceState, ONCE_INIT};
-// #[stable(feature = "rust1", since = "1.0.0")]
-// pub use sys_common::poison::{PoisonError, TryLockError, TryLockResult, LockResult};
+#[stable(feature = "rust1", since = "1.0.0")]
+pub use self::once::{Once, OnceState, ONCE_INIT};
+#[stable(feature = "rust1", since = "1.0.0")]
+pub use sys_common::poison::{PoisonError, TryLockError, TryLockResult, LockResult};
 #[stable(feature = "rust1", since = "1.0.0")]
 pub use self::rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-// pub mod mpsc;
+pub mod mpsc;
 
-// mod barrier;
-// mod condvar;
+mod barrier;
+mod condvar;
 mod mutex;
-// mod once;
+mod once;
 mod rwlock;
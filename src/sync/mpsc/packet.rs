@@ -0,0 +1,220 @@
+// The state shared by the two halves of a channel
+//
+// Unlike upstream, which has specialized lock-free flavors for oneshot,
+// stream, shared and synchronous channels, all channels here are a queue
+// behind a (futex) mutex, with a condition variable for the receiver to wait
+// on and two for blocked senders of bounded channels.
+
+use collections::VecDeque;
+use mem;
+use sync::{Condvar, Mutex, MutexGuard};
+use sys_common::poison::PoisonError;
+use time::{Duration, Instant};
+
+pub struct Packet<T> {
+    state: Mutex<State<T>>,
+    // Signaled when a message is sent or the last sender is gone
+    recv_ready: Condvar,
+    // Signaled once for every message received from a bounded channel, which
+    // makes room for one blocked sender, and when the receiver is gone
+    send_ready: Condvar,
+    // Signaled when the message of a rendezvous sender is taken or the
+    // receiver is gone. There's room for only one message in flight, so at
+    // most one sender waits on it.
+    taken: Condvar,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    // `None` for asynchronous channels, the buffer size otherwise
+    bound: Option<usize>,
+    senders: usize,
+    receiver_alive: bool,
+    receiver_waiting: bool,
+    // Number of messages sent and received so far; lets a sender on a
+    // rendezvous channel know when its message has been taken
+    sent: u64,
+    received: u64,
+}
+
+pub enum Failure {
+    Empty,
+    Disconnected,
+}
+
+pub enum RecvTimeoutFailure {
+    Timeout,
+    Disconnected,
+}
+
+pub enum TrySendFailure<T> {
+    Full(T),
+    Disconnected(T),
+}
+
+impl<T> Packet<T> {
+    pub fn new(bound: Option<usize>) -> Packet<T> {
+        Packet {
+            state: Mutex::new(State {
+                queue: VecDeque::new(),
+                bound: bound,
+                senders: 1,
+                receiver_alive: true,
+                receiver_waiting: false,
+                sent: 0,
+                received: 0,
+            }),
+            recv_ready: Condvar::new(),
+            send_ready: Condvar::new(),
+            taken: Condvar::new(),
+        }
+    }
+
+    // No user code runs while the lock is held, so there's nothing that could
+    // leave the state inconsistent
+    fn lock(&self) -> MutexGuard<State<T>> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, condvar: &Condvar, guard: MutexGuard<'a, State<T>>)
+                -> MutexGuard<'a, State<T>> {
+        condvar.wait(guard).unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn push(&self, state: &mut State<T>, t: T) -> u64 {
+        state.queue.push_back(t);
+        state.sent += 1;
+        self.recv_ready.notify_one();
+        state.sent - 1
+    }
+
+    pub fn send(&self, t: T) -> Result<(), T> {
+        let mut state = self.lock();
+        // A rendezvous channel has room for one message in flight
+        let cap = state.bound.map(|bound| if bound == 0 { 1 } else { bound });
+        if let Some(cap) = cap {
+            while state.receiver_alive && state.queue.len() >= cap {
+                state = self.wait(&self.send_ready, state);
+            }
+        }
+        if !state.receiver_alive {
+            return Err(t);
+        }
+        let ticket = self.push(&mut state, t);
+
+        if state.bound == Some(0) {
+            // Wait for the receiver to take our message. If it goes away in
+            // the meantime, the message is handed back.
+            while state.receiver_alive && state.received <= ticket {
+                state = self.wait(&self.taken, state);
+            }
+            if state.received <= ticket {
+                return Err(state.queue.pop_front().unwrap());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn try_send(&self, t: T) -> Result<(), TrySendFailure<T>> {
+        let mut state = self.lock();
+        if !state.receiver_alive {
+            return Err(TrySendFailure::Disconnected(t));
+        }
+        let full = match state.bound {
+            // A rendezvous only succeeds if the receiver is already waiting
+            Some(0) => !state.receiver_waiting || !state.queue.is_empty(),
+            Some(bound) => state.queue.len() >= bound,
+            None => false,
+        };
+        if full {
+            return Err(TrySendFailure::Full(t));
+        }
+        self.push(&mut state, t);
+        Ok(())
+    }
+
+    fn pop(&self, state: &mut State<T>) -> Option<T> {
+        let t = state.queue.pop_front();
+        if t.is_some() {
+            state.received += 1;
+            match state.bound {
+                Some(0) => {
+                    self.taken.notify_one();
+                    self.send_ready.notify_one();
+                }
+                Some(_) => self.send_ready.notify_one(),
+                None => {}
+            }
+        }
+        t
+    }
+
+    pub fn try_recv(&self) -> Result<T, Failure> {
+        let mut state = self.lock();
+        match self.pop(&mut state) {
+            Some(t) => Ok(t),
+            None if state.senders == 0 => Err(Failure::Disconnected),
+            None => Err(Failure::Empty),
+        }
+    }
+
+    pub fn recv(&self, timeout: Option<Duration>) -> Result<T, RecvTimeoutFailure> {
+        // Measured as elapsed time rather than as a deadline, which might not
+        // be representable for large timeouts
+        let start = Instant::now();
+        let mut state = self.lock();
+        loop {
+            if let Some(t) = self.pop(&mut state) {
+                return Ok(t);
+            }
+            if state.senders == 0 {
+                return Err(RecvTimeoutFailure::Disconnected);
+            }
+
+            state.receiver_waiting = true;
+            state = match timeout {
+                None => self.recv_ready.wait(state).unwrap_or_else(PoisonError::into_inner),
+                Some(timeout) => {
+                    let elapsed = start.elapsed();
+                    if elapsed >= timeout {
+                        state.receiver_waiting = false;
+                        return Err(RecvTimeoutFailure::Timeout);
+                    }
+                    self.recv_ready.wait_timeout(state, timeout - elapsed)
+                        .unwrap_or_else(PoisonError::into_inner).0
+                }
+            };
+            state.receiver_waiting = false;
+        }
+    }
+
+    pub fn clone_sender(&self) {
+        self.lock().senders += 1;
+    }
+
+    pub fn drop_sender(&self) {
+        let mut state = self.lock();
+        state.senders -= 1;
+        if state.senders == 0 {
+            self.recv_ready.notify_all();
+        }
+    }
+
+    pub fn drop_receiver(&self) {
+        let queue = {
+            let mut state = self.lock();
+            state.receiver_alive = false;
+            self.send_ready.notify_all();
+            self.taken.notify_all();
+            // Pending messages are dropped, except for the one of a blocked
+            // rendezvous sender, which gets it back
+            if state.bound == Some(0) {
+                VecDeque::new()
+            } else {
+                mem::replace(&mut state.queue, VecDeque::new())
+            }
+        };
+        // The destructors of the messages may use channels themselves
+        drop(queue);
+    }
+}
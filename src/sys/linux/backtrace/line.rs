@@ -0,0 +1,331 @@
+//! Line number information
+//!
+//! Addresses are mapped to source lines by running the line number programs
+//! of the `.debug_line` section, see DWARF-4 Section 6.2, "Line Number
+//! Information". Versions 2 to 4 of the format are supported.
+//!
+//! We only ever look up a handful of addresses, when printing a backtrace, so
+//! rather than building a table we run every program until we hit the
+//! address.
+
+#![allow(non_upper_case_globals)]
+
+use mem;
+
+const DW_LNS_copy: u8 = 0x01;
+const DW_LNS_advance_pc: u8 = 0x02;
+const DW_LNS_advance_line: u8 = 0x03;
+const DW_LNS_set_file: u8 = 0x04;
+const DW_LNS_const_add_pc: u8 = 0x08;
+const DW_LNS_fixed_advance_pc: u8 = 0x09;
+
+const DW_LNE_end_sequence: u8 = 0x01;
+const DW_LNE_set_address: u8 = 0x02;
+
+/// Finds the source file and line of the instruction at `address`
+pub fn find(debug_line: &[u8], address: usize) -> Option<(Vec<u8>, u32)> {
+    let mut units = Reader { data: debug_line };
+    while !units.data.is_empty() {
+        let unit = try_opt!(Unit::parse(&mut units));
+        if let Some(unit) = unit {
+            if let Some((file, line)) = unit.find(address) {
+                return Some((try_opt!(unit.file(file)), line as u32));
+            }
+        }
+    }
+    None
+}
+
+// A bounds checked cursor
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+#[repr(C, packed)]
+struct Unaligned<T>(T);
+
+impl<'a> Reader<'a> {
+    fn read<T: Copy>(&mut self) -> Option<T> {
+        let bytes = try_opt!(self.split(mem::size_of::<T>()));
+        let Unaligned(value) = unsafe { *(bytes.as_ptr() as *const Unaligned<T>) };
+        Some(value)
+    }
+
+    fn read_uleb128(&mut self) -> Option<u64> {
+        let mut result = 0;
+        let mut shift = 0;
+        loop {
+            let byte = try_opt!(self.read::<u8>());
+            if shift < 64 {
+                result |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return Some(result);
+            }
+        }
+    }
+
+    fn read_sleb128(&mut self) -> Option<i64> {
+        let mut result = 0;
+        let mut shift = 0;
+        let mut byte;
+        loop {
+            byte = try_opt!(self.read::<u8>());
+            if shift < 64 {
+                result |= ((byte & 0x7f) as u64) << shift;
+            }
+            shift += 7;
+            if byte & 0x80 == 0 {
+                break;
+            }
+        }
+        // sign-extend
+        if shift < 64 && (byte & 0x40) != 0 {
+            result |= !0 << shift;
+        }
+        Some(result as i64)
+    }
+
+    // Reads a NUL terminated string, without the terminator
+    fn read_cstr(&mut self) -> Option<&'a [u8]> {
+        let len = try_opt!(self.data.iter().position(|&b| b == 0));
+        let string = &self.data[..len];
+        self.data = &self.data[len + 1..];
+        Some(string)
+    }
+
+    // Reads the next `len` bytes
+    fn split(&mut self, len: usize) -> Option<&'a [u8]> {
+        if len > self.data.len() {
+            return None;
+        }
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Some(head)
+    }
+}
+
+// The header and line number program of a unit
+struct Unit<'a> {
+    minimum_instruction_length: u8,
+    line_base: i8,
+    line_range: u8,
+    opcode_base: u8,
+    standard_opcode_lengths: &'a [u8],
+    include_directories: &'a [u8],
+    file_names: &'a [u8],
+    program: &'a [u8],
+}
+
+// A row of the line number matrix, we don't care about the other columns
+#[derive(Clone, Copy)]
+struct Row {
+    address: usize,
+    file: u64,
+    line: u64,
+}
+
+impl Row {
+    fn new() -> Row {
+        Row {
+            address: 0,
+            file: 1,
+            line: 1,
+        }
+    }
+}
+
+impl<'a> Unit<'a> {
+    // Parses the next unit. Returns `Some(None)` for units we can't handle,
+    // and `None` if the section is malformed.
+    fn parse(units: &mut Reader<'a>) -> Option<Option<Unit<'a>>> {
+        let length = try_opt!(units.read::<u32>());
+        let (length, offset_size) = if length == 0xffff_ffff {
+            (try_opt!(units.read::<u64>()), 8)
+        } else {
+            (length as u64, 4)
+        };
+        let mut unit = Reader { data: try_opt!(units.split(length as usize)) };
+
+        let version = try_opt!(unit.read::<u16>());
+        if version < 2 || version > 4 {
+            return Some(None);
+        }
+        let header_length = if offset_size == 8 {
+            try_opt!(unit.read::<u64>())
+        } else {
+            try_opt!(unit.read::<u32>()) as u64
+        };
+        let mut header = Reader { data: try_opt!(unit.split(header_length as usize)) };
+
+        let minimum_instruction_length = try_opt!(header.read::<u8>());
+        if version >= 4 {
+            // maximum_operations_per_instruction, only used by VLIW
+            // architectures
+            try_opt!(header.read::<u8>());
+        }
+        let _default_is_stmt = try_opt!(header.read::<u8>());
+        let line_base = try_opt!(header.read::<i8>());
+        let line_range = try_opt!(header.read::<u8>());
+        let opcode_base = try_opt!(header.read::<u8>());
+        if line_range == 0 || opcode_base == 0 {
+            return Some(None);
+        }
+        let standard_opcode_lengths = try_opt!(header.split(opcode_base as usize - 1));
+
+        // Both tables are sequences of entries terminated by an empty name
+        let include_directories = header.data;
+        while !try_opt!(header.read_cstr()).is_empty() {}
+        let file_names = header.data;
+
+        Some(Some(Unit {
+            minimum_instruction_length: minimum_instruction_length,
+            line_base: line_base,
+            line_range: line_range,
+            opcode_base: opcode_base,
+            standard_opcode_lengths: standard_opcode_lengths,
+            include_directories: include_directories,
+            file_names: file_names,
+            program: unit.data,
+        }))
+    }
+
+    // Runs the line number program; returns the file index and the line of
+    // `address`
+    fn find(&self, address: usize) -> Option<(u64, u64)> {
+        let mut program = Reader { data: self.program };
+        let min_inst_length = self.minimum_instruction_length as usize;
+
+        let mut row = Row::new();
+        let mut previous: Option<Row> = None;
+        while !program.data.is_empty() {
+            let opcode = try_opt!(program.read::<u8>());
+            let mut append = false;
+            let mut end_sequence = false;
+
+            if opcode >= self.opcode_base {
+                // Special opcode
+                let adjusted = opcode - self.opcode_base;
+                let advance = (adjusted / self.line_range) as usize * min_inst_length;
+                row.address = row.address.wrapping_add(advance);
+                let line_advance = self.line_base as i64 + (adjusted % self.line_range) as i64;
+                row.line = (row.line as i64).wrapping_add(line_advance) as u64;
+                append = true;
+            } else {
+                match opcode {
+                    0 => {
+                        // Extended opcode
+                        let len = try_opt!(program.read_uleb128());
+                        let mut extended = Reader { data: try_opt!(program.split(len as usize)) };
+                        match try_opt!(extended.read::<u8>()) {
+                            DW_LNE_end_sequence => {
+                                append = true;
+                                end_sequence = true;
+                            }
+                            DW_LNE_set_address => {
+                                row.address = match extended.data.len() {
+                                    4 => try_opt!(extended.read::<u32>()) as usize,
+                                    8 => try_opt!(extended.read::<u64>()) as usize,
+                                    _ => return None,
+                                };
+                            }
+                            // DW_LNE_define_file, DW_LNE_set_discriminator
+                            // and vendor extensions
+                            _ => {}
+                        }
+                    }
+                    DW_LNS_copy => append = true,
+                    DW_LNS_advance_pc => {
+                        let advance = try_opt!(program.read_uleb128()) as usize * min_inst_length;
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    DW_LNS_advance_line => {
+                        let advance = try_opt!(program.read_sleb128());
+                        row.line = (row.line as i64).wrapping_add(advance) as u64;
+                    }
+                    DW_LNS_set_file => row.file = try_opt!(program.read_uleb128()),
+                    DW_LNS_const_add_pc => {
+                        let adjusted = 255 - self.opcode_base;
+                        let advance = (adjusted / self.line_range) as usize * min_inst_length;
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    DW_LNS_fixed_advance_pc => {
+                        let advance = try_opt!(program.read::<u16>()) as usize;
+                        row.address = row.address.wrapping_add(advance);
+                    }
+                    _ => {
+                        // An opcode that doesn't affect the columns we track,
+                        // skip its ULEB128 operands
+                        let operands = self.standard_opcode_lengths[opcode as usize - 1];
+                        for _ in 0..operands {
+                            try_opt!(program.read_uleb128());
+                        }
+                    }
+                }
+            }
+
+            if append {
+                if let Some(previous) = previous {
+                    if previous.address <= address && address < row.address {
+                        return Some((previous.file, previous.line));
+                    }
+                }
+
+                if end_sequence {
+                    previous = None;
+                    row = Row::new();
+                } else {
+                    previous = Some(row);
+                }
+            }
+        }
+
+        None
+    }
+
+    // Returns the path of file number `index`
+    fn file(&self, index: u64) -> Option<Vec<u8>> {
+        let mut files = Reader { data: self.file_names };
+        let mut i = 1;
+        loop {
+            let name = try_opt!(files.read_cstr());
+            if name.is_empty() {
+                // Not in the table, might have been defined by the line number
+                // program
+                return None;
+            }
+            let directory = try_opt!(files.read_uleb128());
+            let _mtime = try_opt!(files.read_uleb128());
+            let _length = try_opt!(files.read_uleb128());
+
+            if i == index {
+                let mut path = vec![];
+                // Directory 0 is the compilation directory, which is only
+                // recorded in `.debug_info`
+                if !name.starts_with(b"/") && directory != 0 {
+                    path.extend_from_slice(try_opt!(self.directory(directory)));
+                    path.push(b'/');
+                }
+                path.extend_from_slice(name);
+                return Some(path);
+            }
+            i += 1;
+        }
+    }
+
+    fn directory(&self, index: u64) -> Option<&'a [u8]> {
+        let mut directories = Reader { data: self.include_directories };
+        let mut i = 1;
+        loop {
+            let directory = try_opt!(directories.read_cstr());
+            if directory.is_empty() {
+                return None;
+            }
+            if i == index {
+                return Some(directory);
+            }
+            i += 1;
+        }
+    }
+}
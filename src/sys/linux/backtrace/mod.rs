@@ -0,0 +1,83 @@
+//! Backtraces
+//!
+//! The frames of the current thread are walked by our unwinder (see
+//! `sys::unwind`), using the same unwind tables panics use, and are symbolized
+//! with the symbol table and the `.debug_line` section of our own executable,
+//! which we read back from `/proc/self/exe`. There's no libbacktrace involved.
+//!
+//! Architectures the unwinder doesn't support get empty backtraces.
+
+use io::prelude::*;
+use io;
+use sys_common::backtrace::{output, output_fileline};
+
+mod line;
+mod object;
+
+pub use self::object::Object;
+
+// Upper bound on the number of frames we print, in case the stack is corrupt
+const MAX_FRAMES: usize = 100;
+
+/// Calls `f` with the instruction pointer of every frame of the current
+/// thread, innermost first, for as long as it returns `true`.
+///
+/// These are return addresses, i.e. they point *after* the call instruction.
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+#[inline(never)]
+pub fn trace(mut f: &mut FnMut(usize) -> bool) {
+    use ctypes::c_void;
+    use sys::unwind as uw;
+
+    unsafe {
+        uw::_Unwind_Backtrace(trace_fn, &mut f as *mut &mut FnMut(usize) -> bool as *mut c_void);
+    }
+
+    extern "C" fn trace_fn(ctx: *mut uw::_Unwind_Context,
+                           arg: *mut c_void)
+                           -> uw::_Unwind_Reason_Code {
+        unsafe {
+            let f = &mut *(arg as *mut &mut FnMut(usize) -> bool);
+            let ip = uw::_Unwind_GetIP(ctx);
+            if ip != 0 && (*f)(ip) {
+                uw::_URC_NO_REASON
+            } else {
+                uw::_URC_END_OF_STACK
+            }
+        }
+    }
+}
+
+#[cfg(not(any(target_arch = "aarch64",
+              target_arch = "arm",
+              target_arch = "x86",
+              target_arch = "x86_64")))]
+pub fn trace(_f: &mut FnMut(usize) -> bool) {
+    // TODO(steed): Walk the stack using frame pointers
+}
+
+/// Prints a backtrace of the current thread to `w`.
+pub fn write(w: &mut Write) -> io::Result<()> {
+    let mut ips = Vec::new();
+    trace(&mut |ip| {
+        ips.push(ip);
+        ips.len() < MAX_FRAMES
+    });
+
+    writeln!(w, "stack backtrace:")?;
+
+    // Without the executable we can still print the addresses
+    let object = Object::open().ok();
+    for (i, &ip) in ips.iter().enumerate() {
+        let object = object.as_ref();
+        output(w, i as isize, ip, object.and_then(|o| o.symbol(ip)).map(|(_, name)| name))?;
+        if let Some((file, line)) = object.and_then(|o| o.location(ip)) {
+            output_fileline(w, &file, line, false)?;
+        }
+    }
+
+    Ok(())
+}
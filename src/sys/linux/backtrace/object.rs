@@ -0,0 +1,170 @@
+//! Symbols and line numbers of the running executable
+//!
+//! Neither the symbol table nor the debug information are loaded into memory,
+//! so we read the sections we need from the executable file.
+
+use fs::File;
+use io::{self, ErrorKind};
+use linux::elf::{ELF_ST_TYPE, SHN_UNDEF, SHT_SYMTAB, STT_FUNC};
+use linux::elf::{elf_shdr, elf_sym, elfhdr};
+use mem;
+use slice;
+use sys::ext::fs::FileExt;
+use sys::phdr;
+
+use super::line;
+
+pub struct Object {
+    // Function symbols, sorted by address
+    symbols: Vec<Symbol>,
+    strtab: Vec<u8>,
+    debug_line: Vec<u8>,
+    load_bias: usize,
+}
+
+struct Symbol {
+    address: usize,
+    size: usize,
+    // Offset of the name in `strtab`
+    name: usize,
+}
+
+impl Object {
+    /// Reads the symbol table and the line number information of the running
+    /// executable.
+    pub fn open() -> io::Result<Object> {
+        let file = File::open("/proc/self/exe")?;
+
+        let header = read_array::<elfhdr>(&file, 0, 1)?[0];
+        if &header.e_ident[..4] != b"\x7fELF" ||
+           header.e_shentsize as usize != mem::size_of::<elf_shdr>() {
+            return Err(io::Error::new(ErrorKind::InvalidData, "not an ELF executable"));
+        }
+        let sections = read_array::<elf_shdr>(&file,
+                                              header.e_shoff as u64,
+                                              header.e_shnum as usize)?;
+        let shstrtab = match sections.get(header.e_shstrndx as usize) {
+            Some(section) => read_section(&file, section)?,
+            None => vec![],
+        };
+
+        let mut object = Object {
+            symbols: vec![],
+            strtab: vec![],
+            debug_line: vec![],
+            load_bias: phdr::load_bias(),
+        };
+        for section in &sections {
+            if section.sh_type == SHT_SYMTAB {
+                let symbols = read_array::<elf_sym>(&file,
+                                                    section.sh_offset as u64,
+                                                    section.sh_size as usize /
+                                                    mem::size_of::<elf_sym>())?;
+                object.symbols = symbols.iter()
+                    .filter(|sym| {
+                        ELF_ST_TYPE(sym.st_info) == STT_FUNC && sym.st_shndx != SHN_UNDEF
+                    })
+                    .map(|sym| {
+                        Symbol {
+                            address: function_address(sym.st_value as usize),
+                            size: sym.st_size as usize,
+                            name: sym.st_name as usize,
+                        }
+                    })
+                    .collect();
+                object.symbols.sort_by_key(|sym| sym.address);
+                if let Some(strtab) = sections.get(section.sh_link as usize) {
+                    object.strtab = read_section(&file, strtab)?;
+                }
+            } else if cstr(&shstrtab, section.sh_name as usize) == b".debug_line" {
+                object.debug_line = read_section(&file, section)?;
+            }
+        }
+
+        Ok(object)
+    }
+
+    /// Finds the function `ip`, the instruction pointer of a frame, is in.
+    ///
+    /// Returns the address of the function and its (mangled) name.
+    pub fn symbol(&self, ip: usize) -> Option<(usize, &[u8])> {
+        let address = self.link_address(ip);
+        let i = match self.symbols.binary_search_by_key(&address, |sym| sym.address) {
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
+        };
+
+        let symbol = &self.symbols[i];
+        if symbol.size != 0 && address - symbol.address >= symbol.size {
+            return None;
+        }
+        Some((symbol.address.wrapping_add(self.load_bias), cstr(&self.strtab, symbol.name)))
+    }
+
+    /// Finds the source file and line of `ip`, the instruction pointer of a
+    /// frame.
+    pub fn location(&self, ip: usize) -> Option<(Vec<u8>, u32)> {
+        line::find(&self.debug_line, self.link_address(ip))
+    }
+
+    // Translates the instruction pointer of a frame, which is a return
+    // address, to an address inside the call instruction, as the linker saw
+    // it
+    fn link_address(&self, ip: usize) -> usize {
+        ip.wrapping_sub(1).wrapping_sub(self.load_bias)
+    }
+}
+
+// The least significant bit of the address of a Thumb function is set
+#[cfg(target_arch = "arm")]
+fn function_address(value: usize) -> usize {
+    value & !1
+}
+
+#[cfg(not(target_arch = "arm"))]
+fn function_address(value: usize) -> usize {
+    value
+}
+
+// The NUL terminated string at `offset` in the string table `table`
+fn cstr(table: &[u8], offset: usize) -> &[u8] {
+    let string = if offset < table.len() { &table[offset..] } else { &[] };
+    let len = string.iter().position(|&b| b == 0).unwrap_or(string.len());
+    &string[..len]
+}
+
+fn read_section(file: &File, section: &elf_shdr) -> io::Result<Vec<u8>> {
+    read_array(file, section.sh_offset as u64, section.sh_size as usize)
+}
+
+// Reads `count` `T`s from `file`, starting at `offset`
+fn read_array<T: Copy>(file: &File, offset: u64, count: usize) -> io::Result<Vec<T>> {
+    let mut array = Vec::with_capacity(count);
+    unsafe {
+        let bytes = slice::from_raw_parts_mut(array.as_mut_ptr() as *mut u8,
+                                              count * mem::size_of::<T>());
+        read_exact_at(file, bytes, offset)?;
+        array.set_len(count);
+    }
+    Ok(array)
+}
+
+fn read_exact_at(file: &File, mut buf: &mut [u8], mut offset: u64) -> io::Result<()> {
+    while !buf.is_empty() {
+        match file.read_at(buf, offset) {
+            Ok(0) => {
+                return Err(io::Error::new(ErrorKind::UnexpectedEof,
+                                          "failed to fill whole buffer"))
+            }
+            Ok(n) => {
+                let tmp = buf;
+                buf = &mut tmp[n..];
+                offset += n as u64;
+            }
+            Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use sync::atomic::AtomicU32;
+use sync::atomic::Ordering::Relaxed;
+use sys::futex::{futex_wait, futex_wake, futex_wake_all};
+use sys::futex::{shared_futex_wait, shared_futex_wake, shared_futex_wake_all};
+use sys::mutex::Mutex;
+use time::Duration;
+
+// The `_shared` methods are for a condition variable placed in memory shared
+// with other processes, used together with the `_shared` methods of `Mutex`.
+#[repr(C)]
+pub struct Condvar {
+    // The value of this atomic is simply incremented on every notification.
+    // This is used by `wait` to detect a notification that happens between
+    // unlocking the mutex and going to sleep, in which case the futex wait
+    // returns right away instead of missing it.
+    futex: AtomicU32,
+}
+
+unsafe impl Send for Condvar {}
+unsafe impl Sync for Condvar {}
+
+impl Condvar {
+    pub const fn new() -> Condvar {
+        Condvar { futex: AtomicU32::new(0) }
+    }
+
+    #[inline]
+    pub unsafe fn init(&mut self) {}
+
+    // All the memory orderings here are `Relaxed`, because synchronization is
+    // done by unlocking and locking the mutex.
+
+    pub unsafe fn notify_one(&self) {
+        self.futex.fetch_add(1, Relaxed);
+        futex_wake(&self.futex);
+    }
+
+    pub unsafe fn notify_all(&self) {
+        self.futex.fetch_add(1, Relaxed);
+        futex_wake_all(&self.futex);
+    }
+
+    pub unsafe fn notify_one_shared(&self) {
+        self.futex.fetch_add(1, Relaxed);
+        shared_futex_wake(&self.futex);
+    }
+
+    pub unsafe fn notify_all_shared(&self) {
+        self.futex.fetch_add(1, Relaxed);
+        shared_futex_wake_all(&self.futex);
+    }
+
+    pub unsafe fn wait(&self, mutex: &Mutex) {
+        self.wait_optional_timeout(mutex, None, false);
+    }
+
+    pub unsafe fn wait_timeout(&self, mutex: &Mutex, dur: Duration) -> bool {
+        self.wait_optional_timeout(mutex, Some(dur), false)
+    }
+
+    pub unsafe fn wait_shared(&self, mutex: &Mutex) {
+        self.wait_optional_timeout(mutex, None, true);
+    }
+
+    pub unsafe fn wait_timeout_shared(&self, mutex: &Mutex, dur: Duration) -> bool {
+        self.wait_optional_timeout(mutex, Some(dur), true)
+    }
+
+    unsafe fn wait_optional_timeout(&self,
+                                    mutex: &Mutex,
+                                    timeout: Option<Duration>,
+                                    shared: bool)
+                                    -> bool {
+        // Examine the notification counter _before_ we unlock the mutex.
+        let futex_value = self.futex.load(Relaxed);
+
+        // Unlock the mutex before going to sleep.
+        if shared {
+            mutex.unlock_shared();
+        } else {
+            mutex.unlock();
+        }
+
+        // Wait, but only if there hasn't been any notification since we
+        // unlocked the mutex.
+        let r = if shared {
+            shared_futex_wait(&self.futex, futex_value, timeout)
+        } else {
+            futex_wait(&self.futex, futex_value, timeout)
+        };
+
+        // Lock the mutex again.
+        if shared {
+            mutex.lock_shared();
+        } else {
+            mutex.lock();
+        }
+
+        r
+    }
+
+    #[inline]
+    pub unsafe fn destroy(&self) {}
+}
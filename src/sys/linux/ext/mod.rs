@@ -6,6 +6,8 @@ pub mod fs;
 pub mod io;
 // Rust 1.16.0 (no tests)
 pub mod net;
+// Rust 1.16.0 (only `ExitStatusExt`)
+pub mod process;
 
 #[stable(feature = "steed", since = "1.0.0")]
 pub mod prelude {
@@ -19,10 +21,12 @@ pub mod prelude {
     pub use super::fs::DirEntryExt;
     #[doc(no_inline)] #[unstable(feature = "file_offset", issue = "35918")]
     pub use super::fs::FileExt;
+    #[doc(no_inline)] #[stable(feature = "steed", since = "1.0.0")]
+    pub use super::process::ExitStatusExt;
     /*
     #[doc(no_inline)] #[stable(feature = "steed", since = "1.0.0")]
     pub use super::thread::JoinHandleExt;
     #[doc(no_inline)] #[stable(feature = "steed", since = "1.0.0")]
-    pub use super::process::{CommandExt, ExitStatusExt};
+    pub use super::process::CommandExt;
     */
 }
@@ -0,0 +1,37 @@
+//! Destructors of `#[thread_local]` keys
+//!
+//! glibc lets `std` register them with `__cxa_thread_atexit_impl` and runs
+//! them when the thread exits. We have neither a C library nor `pthread_exit`,
+//! so we keep the list ourselves, in a thread local, and the code that starts
+//! threads calls `run_dtors` once the thread's closure (or `main`) returns.
+
+use ptr;
+
+type List = Vec<(*mut u8, unsafe extern "C" fn(*mut u8))>;
+
+#[thread_local]
+static mut DTORS: *mut List = 0 as *mut _;
+
+/// Registers `dtor` to be called with `t` when the current thread exits.
+pub unsafe fn register_dtor(t: *mut u8, dtor: unsafe extern "C" fn(*mut u8)) {
+    if DTORS.is_null() {
+        DTORS = Box::into_raw(Box::new(Vec::new()));
+    }
+    (*DTORS).push((t, dtor));
+}
+
+/// Runs the destructors registered by the current thread, most recently
+/// registered first, like C++ does.
+///
+/// Destructors may register new destructors (by accessing other keys), those
+/// run too.
+pub unsafe fn run_dtors() {
+    while !DTORS.is_null() {
+        let list = Box::from_raw(DTORS);
+        DTORS = ptr::null_mut();
+        for (t, dtor) in list.into_iter().rev() {
+            dtor(t);
+        }
+    }
+}
+
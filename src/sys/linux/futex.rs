@@ -0,0 +1,71 @@
+use linux;
+use ptr;
+use sync::atomic::AtomicU32;
+use sys::time::Instant;
+use time::Duration;
+
+// The functions below only work for threads of the same process. Their
+// `shared_` variants also work for futexes in memory shared with other
+// processes (`MAP_SHARED`), at the cost of a slower lookup in the kernel.
+
+// Blocks while `*futex == expected`, for at most `timeout` if given. Returns
+// `false` if the wait timed out. Spurious wakeups are possible.
+pub fn futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+    wait(futex, expected, timeout, linux::FUTEX_PRIVATE_FLAG)
+}
+
+pub fn shared_futex_wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>) -> bool {
+    wait(futex, expected, timeout, 0)
+}
+
+// Wakes up at most one thread blocked on `futex`. Returns `true` if a thread
+// was woken up.
+pub fn futex_wake(futex: &AtomicU32) -> bool {
+    wake(futex, 1, linux::FUTEX_PRIVATE_FLAG) > 0
+}
+
+pub fn shared_futex_wake(futex: &AtomicU32) -> bool {
+    wake(futex, 1, 0) > 0
+}
+
+// Wakes up all threads blocked on `futex`.
+pub fn futex_wake_all(futex: &AtomicU32) {
+    wake(futex, i32::max_value() as u32, linux::FUTEX_PRIVATE_FLAG);
+}
+
+pub fn shared_futex_wake_all(futex: &AtomicU32) {
+    wake(futex, i32::max_value() as u32, 0);
+}
+
+fn wait(futex: &AtomicU32, expected: u32, timeout: Option<Duration>, flags: i32) -> bool {
+    // `FUTEX_WAIT_BITSET` takes an absolute `CLOCK_MONOTONIC` deadline, so
+    // that retrying after a signal doesn't extend the wait. A deadline too far
+    // in the future to be represented means no timeout at all.
+    let deadline = timeout.and_then(|dur| Instant::now().checked_add_duration(&dur));
+    let ts_ptr = deadline.as_ref().map(|t| t.as_timespec() as *const _).unwrap_or(ptr::null());
+    loop {
+        let r = unsafe {
+            linux::futex(futex as *const AtomicU32 as *mut u32,
+                         linux::FUTEX_WAIT_BITSET | flags,
+                         expected,
+                         ts_ptr,
+                         ptr::null_mut(),
+                         linux::FUTEX_BITSET_MATCH_ANY)
+        };
+        if r == -linux::errno::EINTR {
+            continue;
+        }
+        return r != -linux::errno::ETIMEDOUT;
+    }
+}
+
+fn wake(futex: &AtomicU32, count: u32, flags: i32) -> i32 {
+    unsafe {
+        linux::futex(futex as *const AtomicU32 as *mut u32,
+                     linux::FUTEX_WAKE | flags,
+                     count,
+                     ptr::null(),
+                     ptr::null_mut(),
+                     0)
+    }
+}
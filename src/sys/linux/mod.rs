@@ -1,14 +1,28 @@
 #![allow(non_camel_case_types)]
 
+// Like `try!` but for `Option`s
+macro_rules! try_opt {
+    ($e:expr) => {
+        match $e {
+            Some(e) => e,
+            None => return None,
+        }
+    }
+}
+
 // Rust 1.16.0
 pub mod args;
+pub mod backtrace;
+pub mod condvar;
 // Rust 1.16.0
 pub mod env;
 pub mod ext;
+pub mod fast_thread_local;
 // Rust 1.16.0 (very close)
 pub mod fd;
 // Rust 1.16.0 (own implementation of readdir and canonicalize)
 pub mod fs;
+pub mod futex;
 pub mod memchr;
 // Rust 1.17.0
 pub mod mutex;
@@ -16,28 +30,65 @@ pub mod mutex;
 pub mod net;
 // Rust 1.16.0
 pub mod os_str;
+pub mod parker;
 // Rust 1.16.0
 pub mod path;
+pub mod phdr;
 pub mod pipe;
 #[cfg_attr(not(issue = "11"), allow(unused_imports))]
 #[cfg_attr(not(issue = "11"), allow(unused_variables))]
 pub mod process;
 pub mod os;
 pub mod rand;
-// Rust 1.17.0
+pub mod reloc;
+// Rust 1.62.0 (see the file for deviations)
 pub mod rwlock;
+// Rust 1.16.0
 pub mod stack_overflow;
+// Rust 1.16.0 (infallible constructors)
+pub mod stdio;
 pub mod time;
 // Rust 1.15.0
 pub mod thread;
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+pub mod unwind;
+pub mod vdso;
 
 pub use os::linux as platform;
 
-use intrinsics;
 use io::Error;
 use io::ErrorKind;
 use io::Result;
 use libc;
+use linux;
+use mem;
+
+// Rust 1.16.0: src/libstd/sys/unix/mod.rs
+pub fn init() {
+    // By default, some platforms will send a *signal* when an EPIPE error
+    // would otherwise be delivered. This runtime doesn't install a SIGPIPE
+    // handler, causing it to kill the program, which isn't exactly what we
+    // want!
+    //
+    // Hence, we set SIGPIPE to ignore when the program starts up in order
+    // to prevent this problem.
+    unsafe {
+        reset_sigpipe();
+    }
+
+    unsafe fn reset_sigpipe() {
+        let mut action: linux::sigaction = mem::zeroed();
+        action.sa_handler = linux::SIG_IGN;
+        assert_eq!(linux::rt_sigaction(linux::SIGPIPE,
+                                       &action,
+                                       ::ptr::null_mut(),
+                                       mem::size_of::<linux::sigset_t>()),
+                   0);
+    }
+}
 
 // Rust 1.16.0: src/libstd/sys/unix/mod.rs
 pub fn decode_error_kind(errno: i32) -> ErrorKind {
@@ -93,6 +144,16 @@ pub fn cvtu(ret: isize) -> Result<usize> {
     cvt(ret).map(|r| r as usize)
 }
 
+// For system calls returning addresses, which may look negative on 32-bit
+// targets. musl: src/internal/__syscall_ret.c
+pub fn cvt_ptr<T>(ret: *mut T) -> Result<*mut T> {
+    if ret as usize > -4096isize as usize {
+        Err(Error::from_raw_os_error(-(ret as isize) as i32))
+    } else {
+        Ok(ret)
+    }
+}
+
 pub fn cvt_r<I: Cvt, F: FnMut() -> I>(mut f: F) -> Result<I> {
     loop {
         match cvt(f()) {
@@ -103,5 +164,5 @@ pub fn cvt_r<I: Cvt, F: FnMut() -> I>(mut f: F) -> Result<I> {
 }
 
 pub unsafe fn abort_internal() -> ! {
-    intrinsics::abort()
+    libc::abort()
 }
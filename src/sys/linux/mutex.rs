@@ -8,71 +8,131 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use linux;
 use cell::UnsafeCell;
+use io;
+use libc;
+use linux::{self, robust_list, robust_list_head};
+use mem;
 use ptr;
-use sync::atomic::{AtomicBool, AtomicPtr, AtomicUsize, Ordering};
-use thread;
-use usize;
+use sync::atomic::{self, AtomicU32, AtomicUsize};
+use sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use sys::futex::{futex_wait, futex_wake, shared_futex_wait, shared_futex_wake};
 
+// A mutex as described in Ulrich Drepper's "Futexes Are Tricky" (mutex2). The
+// futex word has three states:
+//
+// - 0: unlocked
+// - 1: locked, no other threads waiting
+// - 2: locked, and other threads (may be) waiting
+//
+// Only the last one makes `unlock` issue a wake syscall, so an uncontended
+// lock/unlock pair never enters the kernel.
+//
+// The `_shared` variants of `lock` and `unlock` use the futex operations that
+// work across processes, for a mutex placed in memory shared with them. A
+// mutex must consistently be used through one set of methods or the other.
+#[repr(C)]
 pub struct Mutex {
-    // TOTHINK: as locked is only 1 bit we can save some space by
-    // using part of 'waiters' field to store 'locked' bit. This way
-    // we don't need 'locked' field and save some memory.
-    locked: UnsafeCell<AtomicBool>,
-    // Number of threads blocked in kernel
-    waiters: AtomicUsize,
+    futex: AtomicU32,
 }
 
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+const CONTENDED: u32 = 2;
+
 unsafe impl Send for Mutex {}
 unsafe impl Sync for Mutex {}
 
 impl Mutex {
     pub const fn new() -> Mutex {
-        Mutex {
-            locked: UnsafeCell::new(AtomicBool::new(false)),
-            waiters: AtomicUsize::new(0),
-        }
-    }
-    #[inline]
-    pub unsafe fn locked_raw(&self) -> &mut AtomicBool {
-        &mut *self.locked.get()
+        Mutex { futex: AtomicU32::new(UNLOCKED) }
     }
     #[inline]
     pub unsafe fn init(&self) {
     }
     #[inline]
     pub unsafe fn try_lock(&self) -> bool {
-        let locked_prev = self.locked_raw().compare_and_swap(false, true, Ordering::Acquire);
-        !locked_prev
+        self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_ok()
     }
     #[inline]
     pub unsafe fn lock(&self) {
+        if self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_err() {
+            self.lock_contended(false);
+        }
+    }
+    #[inline]
+    pub unsafe fn lock_shared(&self) {
+        if self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed).is_err() {
+            self.lock_contended(true);
+        }
+    }
+    #[cold]
+    fn lock_contended(&self, shared: bool) {
+        // Spin first to speed things up if the lock is released quickly.
+        let mut state = self.spin();
+
+        // If it's unlocked now, attempt to take the lock without marking it
+        // as contended.
+        if state == UNLOCKED {
+            match self.futex.compare_exchange(UNLOCKED, LOCKED, Acquire, Relaxed) {
+                Ok(_) => return,
+                Err(s) => state = s,
+            }
+        }
+
+        loop {
+            // Put the lock in contended state. We avoid an unnecessary write
+            // if it's already set to CONTENDED. If it was UNLOCKED before the
+            // swap, we took the lock; it stays marked as contended because we
+            // can't know whether anybody else is still sleeping on it.
+            if state != CONTENDED && self.futex.swap(CONTENDED, Acquire) == UNLOCKED {
+                return;
+            }
+
+            // Wait for the futex to change state, assuming it is still
+            // CONTENDED.
+            if shared {
+                shared_futex_wait(&self.futex, CONTENDED, None);
+            } else {
+                futex_wait(&self.futex, CONTENDED, None);
+            }
+
+            // Spin again after waking up.
+            state = self.spin();
+        }
+    }
+    fn spin(&self) -> u32 {
+        let mut spin = 100;
         loop {
-            let locked_prev = self.locked_raw().compare_and_swap(false, true, Ordering::Acquire);
-            if !locked_prev {
-                break;
+            // We only use `load` (and not `swap` or `compare_exchange`) while
+            // spinning, to be easier on the caches.
+            let state = self.futex.load(Relaxed);
+
+            // We stop spinning when the mutex is UNLOCKED, but also when it's
+            // CONTENDED: there are already sleeping threads, so spinning
+            // wouldn't get us ahead of them.
+            if state != LOCKED || spin == 0 {
+                return state;
             }
 
-            self.waiters.fetch_add(1, Ordering::Relaxed);
-            let futex: *const bool = self.locked_raw().get_mut();
-            syscall!(FUTEX, futex, linux::FUTEX_WAIT_PRIVATE, 0, 0, 0, 0);
-            self.waiters.fetch_sub(1, Ordering::Relaxed);
+            atomic::hint_core_should_pause();
+            spin -= 1;
         }
     }
     #[inline]
     pub unsafe fn unlock(&self) {
-        let locked_prev = self.locked_raw().compare_and_swap(true, false, Ordering::Release);
-        if locked_prev {
-            if self.waiters.load(Ordering::Relaxed) != 0 {
-                // As an optimization we can do some small amount of spins and check if the lock gets
-                // unlocked. And only if spin does not work then go to sleep.
-
-                let futex: *const bool = self.locked_raw().get_mut();
-                syscall!(FUTEX, futex, linux::FUTEX_WAKE_PRIVATE, 1, 0, 0, 0);
-            }
-        } else {
-            panic!("mutex is not locked");
+        if self.futex.swap(UNLOCKED, Release) == CONTENDED {
+            // We only wake up one thread. When that thread locks the mutex,
+            // it will mark the mutex as CONTENDED (see `lock_contended`
+            // above), which makes sure that any other waiting threads will
+            // also be woken up eventually.
+            futex_wake(&self.futex);
+        }
+    }
+    #[inline]
+    pub unsafe fn unlock_shared(&self) {
+        if self.futex.swap(UNLOCKED, Release) == CONTENDED {
+            shared_futex_wake(&self.futex);
         }
     }
     #[inline]
@@ -80,15 +140,22 @@ impl Mutex {
     }
 }
 
+// Identifies the current thread by the address of a thread local, which is
+// unique among the live threads and never 0.
+#[inline]
+fn current_thread_id() -> usize {
+    #[thread_local]
+    static KEY: u8 = 0;
+    &KEY as *const u8 as usize
+}
+
 pub struct ReentrantMutex {
-    locked: UnsafeCell<AtomicBool>,
-    waiters: AtomicUsize,
-    owner: AtomicPtr<libc::pthread_t>,
-    // Number of times we reentered to this lock, the counter starts from 0.
-    // To help reduce memory traffic for common case (mutex is taken only once) this couter
-    // start with 0. If the mutex is locked and reentrance_count is zero then it means
-    // current thread locked it only once.
-    reentrance_count: usize,
+    mutex: Mutex,
+    // The thread holding the lock, or 0. Only the owner stores its own id
+    // here, so reading our own id back means we hold the lock.
+    owner: AtomicUsize,
+    // How many times the owner has locked the mutex, only accessed by it
+    lock_count: UnsafeCell<u32>,
 }
 
 unsafe impl Send for ReentrantMutex {}
@@ -97,75 +164,291 @@ unsafe impl Sync for ReentrantMutex {}
 impl ReentrantMutex {
     pub const fn new() -> ReentrantMutex {
         ReentrantMutex {
-            locked: UnsafeCell::new(AtomicBool::new(false)),
-            waiters: AtomicUsize::new(0),
-            owner: AtomicPtr::new(ptr::null_mut()),
-            reentrance_count: 0,
+            mutex: Mutex::new(),
+            owner: AtomicUsize::new(0),
+            lock_count: UnsafeCell::new(0),
         }
     }
     #[inline]
-    pub unsafe fn locked_raw(&self) -> &mut AtomicBool {
-        &mut *self.locked.get()
-    }
-    #[inline]
     pub unsafe fn init(&self) {
     }
     #[inline]
     pub unsafe fn try_lock(&self) -> bool {
-        let locked_prev = self.locked_raw().compare_and_swap(false, true, Ordering::Acquire);
-        if !locked_prev {
-            return true
+        let this_thread = current_thread_id();
+        if self.owner.load(Relaxed) == this_thread {
+            self.increment_lock_count();
+            true
+        } else if self.mutex.try_lock() {
+            self.owner.store(this_thread, Relaxed);
+            *self.lock_count.get() = 1;
+            true
         } else {
-            // check who owns the lock, is it us?
-            if self.owner.load(Ordering::Relaxed) == thread::current().thread {
-                self.reentrance_count += 1;
-                return true;
-            }
-            return false;
+            false
         }
     }
     #[inline]
     pub unsafe fn lock(&self) {
-        loop {
-            let locked_prev = self.locked_raw().compare_and_swap(false, true, Ordering::Acquire);
-            if !locked_prev {
-                self.owner.store(thread::current().thread, Ordering::Relaxed);
-                break;
-            } else if self.owner.load(Ordering::Relaxed) == thread::current().thread {
-                self.reentrance_count += 1;
-                break;
-            }
-
-            self.waiters.fetch_add(1, Ordering::Relaxed);
-            let futex: *const bool = self.locked_raw().get_mut();
-            syscall!(FUTEX, futex, linux::FUTEX_WAIT_PRIVATE, 0, 0, 0, 0);
-            self.waiters.fetch_sub(1, Ordering::Relaxed);
+        let this_thread = current_thread_id();
+        if self.owner.load(Relaxed) == this_thread {
+            self.increment_lock_count();
+        } else {
+            self.mutex.lock();
+            self.owner.store(this_thread, Relaxed);
+            *self.lock_count.get() = 1;
         }
     }
+    unsafe fn increment_lock_count(&self) {
+        let count = &mut *self.lock_count.get();
+        *count = count.checked_add(1).expect("lock count overflow in reentrant mutex");
+    }
     #[inline]
     pub unsafe fn unlock(&self) {
-        if self.owner.load(Ordering::Relaxed) != thread::current() {
-            panic!("unlocking mutex owned by other thread");
+        let count = &mut *self.lock_count.get();
+        *count -= 1;
+        if *count == 0 {
+            self.owner.store(0, Relaxed);
+            self.mutex.unlock();
         }
+    }
+    #[inline]
+    pub unsafe fn destroy(&self) {
+    }
+}
+
+// A priority inheritance mutex, see `FUTEX_LOCK_PI` in futex(2). The futex
+// word holds the kernel thread id of the owner (or 0 if unlocked), plus the
+// `FUTEX_WAITERS` bit, set by the kernel when threads are blocked on it, and the
+// `FUTEX_OWNER_DIED` bit. Uncontended locking and unlocking don't enter the
+// kernel; a thread that finds the mutex locked lets the kernel queue it and
+// boost the priority of the owner.
+//
+// While locked, the mutex is linked into the robust list of its owner (see
+// `set_robust_list(2)`), so that the kernel sets `FUTEX_OWNER_DIED` and hands
+// the mutex to the next waiter if the owner exits without unlocking it. This
+// doesn't work for detached threads, which unmap their TLS area (where the
+// list head lives) before exiting.
+#[repr(C)]
+pub struct PiMutex {
+    futex: AtomicU32,
+    entry: UnsafeCell<RobustEntry>,
+}
 
-        if self.reentrance_count == 0 {
-            // common case, we are the only lock in this thread
-            self.owner.store(ptr::null_mut(), Ordering::Relaxed);
+#[repr(C)]
+struct RobustEntry {
+    // The part the kernel knows about, the next entry in the list. The
+    // pointers in the list have their lowest bit set to tell the kernel that
+    // the entries are PI futexes.
+    link: robust_list,
+    // The previous entry, only used by us to unlink the entry in constant time
+    prev: *mut robust_list,
+}
 
-            let locked_prev = self.locked_raw().compare_and_swap(true, false, Ordering::Release);
-            if locked_prev {
-                if self.waiters.load(Ordering::Relaxed) != 0 {
-                    // As an optimization we can do some small amount of spins and check if the lock gets
-                    // unlocked. And only if spin does not work then go to sleep.
+unsafe impl Send for PiMutex {}
+unsafe impl Sync for PiMutex {}
 
-                    let futex: *const bool = self.locked_raw().get_mut();
-                    syscall!(FUTEX, futex, linux::FUTEX_WAKE_PRIVATE, 1, 0, 0, 0);
+const PI: usize = 1;
+
+#[thread_local]
+static mut ROBUST_LIST: robust_list_head = robust_list_head {
+    list: robust_list { next: 0 as *mut robust_list },
+    futex_offset: 0,
+    list_op_pending: 0 as *mut robust_list,
+};
+
+#[thread_local]
+static mut ROBUST_LIST_REGISTERED: bool = false;
+
+// The kernel thread id of the current thread, or 0 if it hasn't been asked for
+// yet. Cached because uncontended locking must not make any system calls.
+#[thread_local]
+static mut TID: u32 = 0;
+
+// Called in the child after a `fork`: the cached thread id is the one of the
+// parent's thread, and the child starts without a robust list registered
+pub unsafe fn after_fork() {
+    TID = linux::gettid() as u32;
+    ROBUST_LIST_REGISTERED = false;
+}
+
+// Returns the robust list of the current thread, registering it with the
+// kernel the first time
+unsafe fn robust_list() -> *mut robust_list_head {
+    let head = &mut ROBUST_LIST as *mut robust_list_head;
+    if !ROBUST_LIST_REGISTERED {
+        // The list is circular, an empty one points back to the head
+        (*head).list.next = &mut (*head).list;
+        // Where the futex word is, relative to an entry
+        let mutex = PiMutex::new();
+        (*head).futex_offset = (&mutex.futex as *const AtomicU32 as isize -
+                                mutex.entry.get() as isize) as libc::c_long;
+        // Can only fail if the size is wrong
+        linux::set_robust_list(head, mem::size_of::<robust_list_head>());
+        ROBUST_LIST_REGISTERED = true;
+    }
+    head
+}
+
+#[inline]
+fn unmark(entry: *mut robust_list) -> *mut robust_list {
+    (entry as usize & !PI) as *mut robust_list
+}
+
+// A wrong id in the futex word would make the kernel consider the lock owned
+// by another task, hence `after_fork`
+#[inline]
+fn current_tid() -> u32 {
+    unsafe {
+        if TID == 0 {
+            TID = linux::gettid() as u32;
+        }
+        TID
+    }
+}
+
+impl PiMutex {
+    pub const fn new() -> PiMutex {
+        PiMutex {
+            futex: AtomicU32::new(0),
+            entry: UnsafeCell::new(RobustEntry {
+                link: robust_list { next: 0 as *mut robust_list },
+                prev: 0 as *mut robust_list,
+            }),
+        }
+    }
+    #[inline]
+    fn futex_ptr(&self) -> *mut u32 {
+        &self.futex as *const AtomicU32 as *mut u32
+    }
+    #[inline]
+    fn marked_entry(&self) -> *mut robust_list {
+        (self.entry.get() as usize | PI) as *mut robust_list
+    }
+    // Links the mutex at the front of the robust list
+    unsafe fn enqueue(&self, head: *mut robust_list_head) {
+        let entry = self.entry.get();
+        let first = (*head).list.next;
+        (*entry).link.next = first;
+        (*entry).prev = &mut (*head).list;
+        if unmark(first) != &mut (*head).list as *mut robust_list {
+            (*(unmark(first) as *mut RobustEntry)).prev = entry as *mut robust_list;
+        }
+        (*head).list.next = self.marked_entry();
+    }
+    unsafe fn dequeue(&self, head: *mut robust_list_head) {
+        let entry = self.entry.get();
+        let (prev, next) = ((*entry).prev, (*entry).link.next);
+        (*prev).next = next;
+        if unmark(next) != &mut (*head).list as *mut robust_list {
+            (*(unmark(next) as *mut RobustEntry)).prev = prev;
+        }
+    }
+    // Clears `FUTEX_OWNER_DIED`, returning whether it was set. Only called by
+    // the owner.
+    fn clear_owner_died(&self) -> bool {
+        self.futex.fetch_and(!linux::FUTEX_OWNER_DIED, Relaxed) & linux::FUTEX_OWNER_DIED != 0
+    }
+    #[inline]
+    pub unsafe fn init(&self) {
+    }
+    // Returns `true` if the previous owner exited without unlocking the mutex
+    #[inline]
+    pub unsafe fn lock(&self) -> bool {
+        let tid = current_tid();
+        let head = robust_list();
+        // Tell the kernel about the mutex in case we exit halfway through
+        (*head).list_op_pending = self.marked_entry();
+        if self.futex.compare_exchange(0, tid, Acquire, Relaxed).is_err() {
+            self.lock_contended(tid);
+        }
+        self.enqueue(head);
+        (*head).list_op_pending = ptr::null_mut();
+        self.clear_owner_died()
+    }
+    #[cold]
+    unsafe fn lock_contended(&self, tid: u32) {
+        loop {
+            let r = linux::futex(self.futex_ptr(),
+                                 linux::FUTEX_LOCK_PI_PRIVATE,
+                                 0,
+                                 ptr::null(),
+                                 ptr::null_mut(),
+                                 0);
+            match -r {
+                0 => return,
+                // The owner is exiting, or we got interrupted
+                libc::EAGAIN | libc::EINTR => {}
+                // The owner is gone without the kernel cleaning up after it
+                // (e.g. a detached thread), take the mutex over ourselves
+                libc::ESRCH => {
+                    let state = self.futex.load(Relaxed);
+                    let dead = (state & linux::FUTEX_WAITERS) | linux::FUTEX_OWNER_DIED | tid;
+                    if self.futex.compare_exchange(state, dead, Acquire, Relaxed).is_ok() {
+                        return;
+                    }
                 }
-            } else {
-                panic!("mutex is not locked");
+                libc::EDEADLK => {
+                    (*robust_list()).list_op_pending = ptr::null_mut();
+                    panic!("deadlock: the mutex is already locked by the current thread");
+                }
+                e => panic!("FUTEX_LOCK_PI failed: {}", io::Error::from_raw_os_error(e)),
+            }
+        }
+    }
+    // Returns `None` if the mutex is locked, or whether the previous owner
+    // exited without unlocking the mutex otherwise
+    #[inline]
+    pub unsafe fn try_lock(&self) -> Option<bool> {
+        let tid = current_tid();
+        let head = robust_list();
+        (*head).list_op_pending = self.marked_entry();
+        let locked = match self.futex.compare_exchange(0, tid, Acquire, Relaxed) {
+            Ok(_) => true,
+            // Only the kernel can take over from a dead owner, as there may
+            // be waiters to deal with
+            Err(state) if state & linux::FUTEX_TID_MASK == 0 => {
+                linux::futex(self.futex_ptr(),
+                             linux::FUTEX_TRYLOCK_PI_PRIVATE,
+                             0,
+                             ptr::null(),
+                             ptr::null_mut(),
+                             0) == 0
             }
+            Err(_) => false,
+        };
+        if locked {
+            self.enqueue(head);
+        }
+        (*head).list_op_pending = ptr::null_mut();
+        if locked {
+            Some(self.clear_owner_died())
         } else {
-            self.reentrance_count -= 1;
+            None
+        }
+    }
+    #[inline]
+    pub unsafe fn unlock(&self) {
+        let tid = current_tid();
+        let head = robust_list();
+        (*head).list_op_pending = self.marked_entry();
+        self.dequeue(head);
+        if self.futex.compare_exchange(tid, 0, Release, Relaxed).is_err() {
+            // There are waiters, let the kernel hand the mutex to the one
+            // with the highest priority
+            linux::futex(self.futex_ptr(),
+                         linux::FUTEX_UNLOCK_PI_PRIVATE,
+                         0,
+                         ptr::null(),
+                         ptr::null_mut(),
+                         0);
+        }
+        (*head).list_op_pending = ptr::null_mut();
+    }
+    #[inline]
+    pub unsafe fn destroy(&self) {
+        // A mutex whose guard was leaked is still in our robust list, where
+        // it must not stay after being freed
+        if self.futex.load(Relaxed) & linux::FUTEX_TID_MASK == current_tid() {
+            self.dequeue(robust_list());
         }
     }
 }
@@ -11,15 +11,10 @@ use path::{self, PathBuf};
 use slice;
 use super::cvt;
 use sys::ext::prelude::*;
+use sys_common::mutex::Mutex;
 use vec;
 
-static ENV_LOCK: () = ();
-// TODO(steed, #143): Synchronize environment access once we have mutexes.
-trait MutexExt {
-    fn lock(&self) { }
-    fn unlock(&self) { }
-}
-impl MutexExt for () { }
+static ENV_LOCK: Mutex = Mutex::new();
 
 pub fn errno() -> i32 {
     panic!("no C-compatible errno variable");
@@ -61,8 +56,8 @@ pub fn getcwd() -> io::Result<PathBuf> {
 }
 
 pub fn page_size() -> usize {
-    // TODO(steed, #133): Implement me.
-    unimplemented!();
+    // The kernel always passes `AT_PAGESZ`
+    unsafe { libc::getauxval(linux::AT_PAGESZ).unwrap() as usize }
 }
 
 pub fn chdir(p: &path::Path) -> io::Result<()> {
@@ -127,7 +122,27 @@ impl StdError for JoinPathsError {
 }
 
 pub fn current_exe() -> io::Result<PathBuf> {
-    ::fs::read_link("/proc/self/exe")
+    match ::fs::read_link("/proc/self/exe") {
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
+            // /proc isn't mounted, fall back to the path we were started with
+            let execfn = match unsafe { libc::getauxval(linux::AT_EXECFN) } {
+                Some(ptr) => unsafe { CStr::from_ptr(ptr as *const c_char) },
+                None => {
+                    return Err(io::Error::new(io::ErrorKind::Other,
+                                              "no /proc/self/exe available. Is /proc mounted?"))
+                }
+            };
+            let path = PathBuf::from(OsStr::from_bytes(execfn.to_bytes()));
+            if path.is_absolute() {
+                Ok(path)
+            } else {
+                // Relative to the working directory at the time of `execve`,
+                // which we can only hope hasn't changed since
+                getcwd().map(|cwd| cwd.join(path))
+            }
+        }
+        other => other,
+    }
 }
 
 pub unsafe fn environ() -> *const *const c_char {
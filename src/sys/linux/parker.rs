@@ -0,0 +1,55 @@
+use sync::atomic::AtomicU32;
+use sync::atomic::Ordering::{Acquire, Release};
+use sys::futex::{futex_wait, futex_wake};
+use time::Duration;
+
+const PARKED: u32 = !0;
+const EMPTY: u32 = 0;
+const NOTIFIED: u32 = 1;
+
+// A parker for a single thread, built on a futex word that is `EMPTY`,
+// `NOTIFIED` (a token is available) or `PARKED` (the owner is sleeping).
+pub struct Parker {
+    state: AtomicU32,
+}
+
+impl Parker {
+    pub const fn new() -> Parker {
+        Parker { state: AtomicU32::new(EMPTY) }
+    }
+
+    // Must only be called by the thread that owns the parker.
+    pub unsafe fn park(&self) {
+        // Consume the token if there is one (NOTIFIED => EMPTY), otherwise
+        // announce that we're going to sleep (EMPTY => PARKED).
+        if self.state.fetch_sub(1, Acquire) == NOTIFIED {
+            return;
+        }
+        loop {
+            futex_wait(&self.state, PARKED, None);
+            if self.state.compare_exchange(NOTIFIED, EMPTY, Acquire, Acquire).is_ok() {
+                return;
+            }
+            // Spurious wakeup, go back to sleep.
+        }
+    }
+
+    // Must only be called by the thread that owns the parker.
+    pub unsafe fn park_timeout(&self, timeout: Duration) {
+        if self.state.fetch_sub(1, Acquire) == NOTIFIED {
+            return;
+        }
+        futex_wait(&self.state, PARKED, Some(timeout));
+        // Whether we were notified, timed out or woke up spuriously, the
+        // state goes back to EMPTY, consuming a token if there was one.
+        self.state.swap(EMPTY, Acquire);
+    }
+
+    pub fn unpark(&self) {
+        // Only wake up the owner if it's actually sleeping; the token is left
+        // behind for the next `park` otherwise.
+        if self.state.swap(NOTIFIED, Release) == PARKED {
+            futex_wake(&self.state);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+//! Access to the program headers of the running executable.
+//!
+//! We are always statically linked, so the program headers of our own image
+//! describe everything that is mapped into memory: the unwind tables, the
+//! thread-local storage template, the dynamic section and so on. The linker
+//! defines `__ehdr_start` to point at the ELF header that starts the first
+//! loaded segment, which lets us find them without asking the kernel.
+
+use linux::elf::{elf_phdr, elfhdr, PT_LOAD};
+use slice;
+
+extern "C" {
+    static __ehdr_start: elfhdr;
+}
+
+/// Returns the program headers of the executable.
+pub fn program_headers() -> &'static [elf_phdr] {
+    unsafe {
+        let ehdr = &__ehdr_start;
+        let phdrs = (ehdr as *const elfhdr as *const u8)
+            .offset(ehdr.e_phoff as isize) as *const elf_phdr;
+        slice::from_raw_parts(phdrs, ehdr.e_phnum as usize)
+    }
+}
+
+/// Returns the difference between the addresses the executable was linked at
+/// and the addresses it was loaded at.
+///
+/// This is zero unless the executable is position independent.
+pub fn load_bias() -> usize {
+    unsafe {
+        let ehdr = &__ehdr_start as *const elfhdr as usize;
+        program_headers()
+            .iter()
+            .find(|phdr| phdr.p_type == PT_LOAD && phdr.p_offset == 0)
+            .map(|phdr| ehdr.wrapping_sub(phdr.p_vaddr as usize))
+            .unwrap_or(0)
+    }
+}
+
+/// Returns the in-memory contents of the first segment of type `p_type`.
+pub fn segment(p_type: u32) -> Option<&'static [u8]> {
+    program_headers()
+        .iter()
+        .find(|phdr| phdr.p_type == p_type)
+        .map(|phdr| unsafe {
+            let start = load_bias().wrapping_add(phdr.p_vaddr as usize);
+            slice::from_raw_parts(start as *const u8, phdr.p_memsz as usize)
+        })
+}
@@ -247,6 +247,7 @@ impl Command {
         let pid = unsafe {
             match cvt(linux::fork())? {
                 0 => {
+                    sys::mutex::after_fork();
                     drop(input);
                     let err = self.do_exec(theirs);
                     let errno = err.raw_os_error().unwrap_or(errno::EINVAL) as u32;
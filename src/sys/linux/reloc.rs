@@ -0,0 +1,294 @@
+//! Self-relocation of static position independent executables
+//!
+//! A static-pie executable can be loaded anywhere, which is what gives it
+//! ASLR, but there's no dynamic linker to apply its relocations: we have to
+//! do it ourselves, before anything reads an address the linker stored in
+//! memory. Until then every vtable, `&'static str` in a static, GOT entry and
+//! so on holds the address it would have if we had been loaded at the
+//! addresses we were linked at.
+//!
+//! The entry point computes the address of our dynamic section, `_DYNAMIC`,
+//! with position independent code and hands it to `relocate`. The symbol is
+//! weak, so it's null when we were linked as a regular static executable,
+//! which has nothing to relocate.
+//!
+//! All the relocations of an executable that doesn't import any symbol
+//! should be relative ones, i.e. "add the load address to this word".
+//! Anything else is reported as a fatal error.
+//!
+//! Nothing in here may touch a global, not even indirectly: no panics (their
+//! locations are statics), no bounds checks, no calls that aren't inlined on
+//! architectures that call through the GOT (MIPS). That rules out iterators
+//! and the methods of slices, pointers and integers (`offset`,
+//! `wrapping_add`, ...) too, which are calls in unoptimized builds: we walk
+//! the tables with addresses as plain integers, and do arithmetic with the
+//! `#[inline(always)]` helpers at the bottom, which use intrinsics.
+
+use intrinsics;
+use linux;
+use linux::elf::{DT_JMPREL, DT_NULL, DT_PLTREL, DT_PLTRELSZ, DT_REL, DT_RELA, DT_RELASZ};
+use linux::elf::{DT_RELSZ, PT_DYNAMIC, elf_dyn, elf_phdr, elf_rel, elf_rela};
+use linux::{AT_NULL, AT_PHDR, AT_PHNUM};
+use mem;
+
+#[cfg(target_arch = "aarch64")]
+use linux::{R_AARCH64_NONE as R_NONE, R_AARCH64_RELATIVE as R_RELATIVE};
+#[cfg(target_arch = "arm")]
+use linux::{R_ARM_NONE as R_NONE, R_ARM_RELATIVE as R_RELATIVE};
+#[cfg(target_arch = "mips")]
+use linux::{R_MIPS_NONE as R_NONE, R_MIPS_REL32 as R_RELATIVE};
+#[cfg(target_arch = "powerpc")]
+use linux::{R_PPC_NONE as R_NONE, R_PPC_RELATIVE as R_RELATIVE};
+#[cfg(target_arch = "powerpc64")]
+use linux::{R_PPC64_NONE as R_NONE, R_PPC64_RELATIVE as R_RELATIVE};
+#[cfg(target_arch = "x86")]
+use linux::{R_386_NONE as R_NONE, R_386_RELATIVE as R_RELATIVE};
+#[cfg(target_arch = "x86_64")]
+use linux::{R_X86_64_NONE as R_NONE, R_X86_64_RELATIVE as R_RELATIVE};
+
+#[cfg(target_arch = "mips64")]
+use linux::R_MIPS_NONE as R_NONE;
+// A relative relocation is an `R_MIPS_REL32` followed by an `R_MIPS_64`
+#[cfg(target_arch = "mips64")]
+const R_RELATIVE: u32 = linux::R_MIPS_REL32 | linux::R_MIPS_64 << 8;
+
+/// Applies the relocations of the executable whose dynamic section is at
+/// `dynamic`, if any.
+///
+/// `sp` is the initial stack pointer, below which the kernel left the
+/// arguments, the environment and the auxiliary vector.
+#[inline(always)]
+pub unsafe fn relocate(sp: *const usize, dynamic: *const elf_dyn) {
+    if dynamic as usize == 0 {
+        return;
+    }
+
+    let base = load_base(sp as usize, dynamic as usize);
+
+    let mut rel = 0;
+    let mut relsz = 0;
+    let mut rela = 0;
+    let mut relasz = 0;
+    let mut jmprel = 0;
+    let mut pltrelsz = 0;
+    let mut pltrel = 0;
+    let mut entry = dynamic as usize;
+    while (*(entry as *const elf_dyn)).d_tag as i64 != DT_NULL {
+        let value = (*(entry as *const elf_dyn)).d_val as usize;
+        match (*(entry as *const elf_dyn)).d_tag as i64 {
+            DT_REL => rel = add(base, value),
+            DT_RELSZ => relsz = value,
+            DT_RELA => rela = add(base, value),
+            DT_RELASZ => relasz = value,
+            DT_JMPREL => jmprel = add(base, value),
+            DT_PLTRELSZ => pltrelsz = value,
+            DT_PLTREL => pltrel = value as i64,
+            _ => {}
+        }
+        entry = add(entry, size_of::<elf_dyn>());
+    }
+
+    apply_rel(base, rel, relsz);
+    apply_rela(base, rela, relasz);
+    // There shouldn't be any, these would be relocations for PLT entries of
+    // imported functions
+    if pltrel == DT_REL {
+        apply_rel(base, jmprel, pltrelsz);
+    } else {
+        apply_rela(base, jmprel, pltrelsz);
+    }
+
+    relocate_got(base, dynamic as usize);
+}
+
+// Returns the address we were loaded at, i.e. the difference between the
+// actual address of `_DYNAMIC` and the one it was linked at, which we get
+// from the program headers
+//
+// We can't use `libc::getauxval` because it needs a global that `_init` sets.
+#[inline(always)]
+unsafe fn load_base(sp: usize, dynamic: usize) -> usize {
+    // Skip argc, the arguments and the environment
+    let argc = *(sp as *const usize);
+    let mut envp = add(sp, mul(add(argc, 2), size_of::<usize>()));
+    while *(envp as *const usize) != 0 {
+        envp = add(envp, size_of::<usize>());
+    }
+
+    let mut auxv = add(envp, size_of::<usize>());
+    let mut phdr = 0;
+    let mut phnum = 0;
+    while *(auxv as *const usize) != AT_NULL as usize {
+        let value = *(add(auxv, size_of::<usize>()) as *const usize);
+        if *(auxv as *const usize) == AT_PHDR as usize {
+            phdr = value;
+        } else if *(auxv as *const usize) == AT_PHNUM as usize {
+            phnum = value;
+        }
+        auxv = add(auxv, mul(2, size_of::<usize>()));
+    }
+
+    let end = add(phdr, mul(phnum, size_of::<elf_phdr>()));
+    while phdr < end {
+        if (*(phdr as *const elf_phdr)).p_type == PT_DYNAMIC {
+            return sub(dynamic, (*(phdr as *const elf_phdr)).p_vaddr as usize);
+        }
+        phdr = add(phdr, size_of::<elf_phdr>());
+    }
+    fatal(b"fatal runtime error: no PT_DYNAMIC program header\n")
+}
+
+// `rel` is the address of the table and `size` its size in bytes; a missing
+// table has both set to 0
+#[inline(always)]
+unsafe fn apply_rel(base: usize, mut rel: usize, size: usize) {
+    let end = add(rel, size);
+    while rel < end {
+        let info = (*(rel as *const elf_rel)).r_info as usize;
+        let type_ = r_type(info);
+        if type_ != R_NONE {
+            if type_ != R_RELATIVE || r_sym(info) != 0 {
+                unsupported();
+            }
+            // The addend is the word being relocated
+            let word = add(base, (*(rel as *const elf_rel)).r_offset as usize) as *mut usize;
+            *word = add(*word, base);
+        }
+        rel = add(rel, size_of::<elf_rel>());
+    }
+}
+
+#[inline(always)]
+unsafe fn apply_rela(base: usize, mut rela: usize, size: usize) {
+    let end = add(rela, size);
+    while rela < end {
+        let info = (*(rela as *const elf_rela)).r_info as usize;
+        let type_ = r_type(info);
+        if type_ != R_NONE {
+            if type_ != R_RELATIVE || r_sym(info) != 0 {
+                unsupported();
+            }
+            let word = add(base, (*(rela as *const elf_rela)).r_offset as usize) as *mut usize;
+            *word = add(base, (*(rela as *const elf_rela)).r_addend as usize);
+        }
+        rela = add(rela, size_of::<elf_rela>());
+    }
+}
+
+// The local entries of the MIPS GOT hold link-time addresses but have no
+// relocations, the dynamic linker is expected to relocate them by itself
+#[cfg(any(target_arch = "mips", target_arch = "mips64"))]
+#[inline(always)]
+unsafe fn relocate_got(base: usize, mut dynamic: usize) {
+    use linux::DT_MIPS_LOCAL_GOTNO;
+    use linux::elf::DT_PLTGOT;
+
+    let mut got = 0;
+    let mut local_gotno = 0;
+    while (*(dynamic as *const elf_dyn)).d_tag as i64 != DT_NULL {
+        let value = (*(dynamic as *const elf_dyn)).d_val as usize;
+        match (*(dynamic as *const elf_dyn)).d_tag as i64 {
+            DT_PLTGOT => got = add(base, value),
+            DT_MIPS_LOCAL_GOTNO => local_gotno = value,
+            _ => {}
+        }
+        dynamic = add(dynamic, size_of::<elf_dyn>());
+    }
+
+    let end = add(got, mul(local_gotno, size_of::<usize>()));
+    while got < end {
+        *(got as *mut usize) = add(*(got as *const usize), base);
+        got = add(got, size_of::<usize>());
+    }
+}
+
+#[cfg(not(any(target_arch = "mips", target_arch = "mips64")))]
+#[inline(always)]
+unsafe fn relocate_got(_base: usize, _dynamic: usize) {}
+
+// `ELF32_R_TYPE` and friends, which aren't `#[inline(always)]`
+
+#[cfg(target_pointer_width = "32")]
+#[inline(always)]
+fn r_type(info: usize) -> u32 {
+    (info & 0xff) as u32
+}
+
+#[cfg(target_pointer_width = "32")]
+#[inline(always)]
+fn r_sym(info: usize) -> u32 {
+    (info >> 8) as u32
+}
+
+#[cfg(all(target_pointer_width = "64", not(target_arch = "mips64")))]
+#[inline(always)]
+fn r_type(info: usize) -> u32 {
+    (info & 0xffff_ffff) as u32
+}
+
+#[cfg(all(target_pointer_width = "64", not(target_arch = "mips64")))]
+#[inline(always)]
+fn r_sym(info: usize) -> u32 {
+    (info >> 32) as u32
+}
+
+// MIPS64 `r_info` is a 32-bit symbol followed by a special symbol and three
+// types, one byte each, stored in big endian order whatever the endianness
+#[cfg(target_arch = "mips64")]
+#[inline(always)]
+fn r_type(info: usize) -> u32 {
+    (from_be(info as u64) & 0x7fff_ffff) as u32
+}
+
+#[cfg(target_arch = "mips64")]
+#[inline(always)]
+fn r_sym(info: usize) -> u32 {
+    let sym = (from_be(info as u64) >> 32) as u32;
+    if cfg!(target_endian = "big") {
+        sym
+    } else {
+        unsafe { intrinsics::bswap(sym) }
+    }
+}
+
+#[cfg(target_arch = "mips64")]
+#[inline(always)]
+fn from_be(x: u64) -> u64 {
+    if cfg!(target_endian = "big") {
+        x
+    } else {
+        unsafe { intrinsics::bswap(x) }
+    }
+}
+
+#[inline(always)]
+fn add(a: usize, b: usize) -> usize {
+    unsafe { intrinsics::overflowing_add(a, b) }
+}
+
+#[inline(always)]
+fn sub(a: usize, b: usize) -> usize {
+    unsafe { intrinsics::overflowing_sub(a, b) }
+}
+
+#[inline(always)]
+fn mul(a: usize, b: usize) -> usize {
+    unsafe { intrinsics::overflowing_mul(a, b) }
+}
+
+#[inline(always)]
+fn size_of<T>() -> usize {
+    unsafe { intrinsics::size_of::<T>() }
+}
+
+#[inline(always)]
+unsafe fn unsupported() -> ! {
+    fatal(b"fatal runtime error: unsupported relocation type\n")
+}
+
+#[inline(always)]
+unsafe fn fatal(msg: &'static [u8]) -> ! {
+    let (ptr, len): (*const u8, usize) = mem::transmute(msg);
+    linux::write(2, ptr as *const _, len);
+    intrinsics::abort()
+}
@@ -1,30 +1,82 @@
-// Copyright 2014 The Rust Project Developers. See the COPYRIGHT
-// file at the top-level directory of this distribution and at
-// http://rust-lang.org/COPYRIGHT.
+// Rust 1.62.0: library/std/src/sys/unix/locks/futex_rwlock.rs
 //
-// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
-// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
-// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
-// option. This file may not be copied, modified, or distributed
-// except according to those terms.
-
-use linux;
-use cell::UnsafeCell;
-use sync::atomic::{AtomicUsize, Ordering};
-use {isize, usize};
+// Deviations from upstream, to build with the nightly steed targets and to
+// provide the Rust 1.16.0 `sys::rwlock` interface:
+//
+// - The type is called `RWLock`, all its locking methods are `unsafe` and it
+//   has a no-op `destroy`.
+// - `try_read` and `try_write` use `compare_exchange_weak` loops instead of
+//   `fetch_update` and `bool::then`.
+// - `spin_until` takes a generic closure instead of `impl Fn`.
+// - Spinning uses `atomic::hint_core_should_pause` instead of
+//   `hint::spin_loop`; `AtomicU32` needs the `integer_atomics` feature.
+// - The comment on `state` explains why the lock prefers writers.
 
-// value that signals rwlock is locked with a writer
-const RWLOCK_WRITER: usize = usize::MAX;
+use sync::atomic::{self, AtomicU32};
+use sync::atomic::Ordering::{Acquire, Relaxed, Release};
+use sys::futex::{futex_wait, futex_wake, futex_wake_all};
 
 pub struct RWLock {
-    // Number of users for this rwlock
-    // Zero means no users
-    // Value equal to `RWLOCK_WRITER` means it is locked by a writer
-    // Any other value - number of readears currently holding the lock
-    users: UnsafeCell<AtomicUsize>,
-
-    // Number of blocked threads that wait when the lock becomes available
-    waiters: AtomicUsize,
+    // The state consists of a 30-bit reader counter, a 'readers waiting' flag,
+    // and a 'writers waiting' flag. Bits 0..30:
+    //   0: Unlocked
+    //   1..=0x3FFF_FFFE: Locked by N readers
+    //   0x3FFF_FFFF: Write locked
+    // Bit 30: Readers are waiting on this futex.
+    // Bit 31: Writers are waiting on the writer_notify futex.
+    //
+    // New readers don't take the lock while a writer is waiting, so a steady
+    // stream of readers can't starve the writers.
+    state: AtomicU32,
+    // The 'condition variable' to notify writers through. Incremented on every
+    // signal.
+    writer_notify: AtomicU32,
+}
+
+const READ_LOCKED: u32 = 1;
+const MASK: u32 = (1 << 30) - 1;
+const WRITE_LOCKED: u32 = MASK;
+const MAX_READERS: u32 = MASK - 1;
+const READERS_WAITING: u32 = 1 << 30;
+const WRITERS_WAITING: u32 = 1 << 31;
+
+#[inline]
+fn is_unlocked(state: u32) -> bool {
+    state & MASK == 0
+}
+
+#[inline]
+fn is_write_locked(state: u32) -> bool {
+    state & MASK == WRITE_LOCKED
+}
+
+#[inline]
+fn has_readers_waiting(state: u32) -> bool {
+    state & READERS_WAITING != 0
+}
+
+#[inline]
+fn has_writers_waiting(state: u32) -> bool {
+    state & WRITERS_WAITING != 0
+}
+
+#[inline]
+fn is_read_lockable(state: u32) -> bool {
+    // This also returns false if the counter could overflow if we tried to
+    // read lock it.
+    //
+    // We don't allow read-locking if there's readers waiting, even if the lock
+    // is unlocked and there's no writers waiting. The only situation when this
+    // happens is after unlocking, at which point the unlocking thread might be
+    // waking up writers, which have priority over readers. The unlocking
+    // thread will clear the readers waiting bit and wake up readers, if
+    // necessary.
+    state & MASK < MAX_READERS && !has_readers_waiting(state) && !has_writers_waiting(state)
+}
+
+#[inline]
+fn has_reached_max_readers(state: u32) -> bool {
+    state & MASK == MAX_READERS
 }
 
 unsafe impl Send for RWLock {}
@@ -33,121 +85,249 @@ unsafe impl Sync for RWLock {}
 impl RWLock {
     pub const fn new() -> RWLock {
         RWLock {
-            // We use UnsafeCell because we need address of the pointer for futex() syscall
-            users: UnsafeCell::new(AtomicUsize::new(0)),
-            waiters: AtomicUsize::new(0),
+            state: AtomicU32::new(0),
+            writer_notify: AtomicU32::new(0),
         }
     }
     #[inline]
-    pub unsafe fn users_raw(&self) -> &mut AtomicUsize {
-        &mut *self.users.get()
+    pub unsafe fn try_read(&self) -> bool {
+        let mut state = self.state.load(Relaxed);
+        while is_read_lockable(state) {
+            match self.state.compare_exchange_weak(state, state + READ_LOCKED, Acquire, Relaxed) {
+                Ok(_) => return true,
+                Err(s) => state = s,
+            }
+        }
+        false
     }
     #[inline]
     pub unsafe fn read(&self) {
-        let mut users = self.users_raw().load(Ordering::Acquire);
-
-        loop {
-            if users == RWLOCK_WRITER {
-                self.waiters.fetch_add(1, Ordering::Relaxed);
-                let futex: *const usize = self.users_raw().get_mut();
-                syscall!(FUTEX, futex, linux::FUTEX_WAIT_PRIVATE, users, 0, 0, 0);
-                self.waiters.fetch_sub(1, Ordering::Relaxed);
-
-                users = self.users_raw().load(Ordering::Acquire);
-            } else if users == RWLOCK_WRITER - 1 {
-                panic!("rwlock maximum reader count exceeded");
-            } else {
-                let users_prev = self.users_raw().compare_and_swap(users, users + 1, Ordering::Acquire);
-                if users == users_prev {
-                    // atomic swap was successfull, we are good
-                    break;
-                }
-                users = users_prev;
-            }
+        let state = self.state.load(Relaxed);
+        if !is_read_lockable(state) ||
+           self.state.compare_exchange_weak(state, state + READ_LOCKED, Acquire, Relaxed)
+               .is_err() {
+            self.read_contended();
         }
     }
     #[inline]
-    pub unsafe fn try_read(&self) -> bool {
-        let mut users = self.users_raw().load(Ordering::Acquire);
+    pub unsafe fn read_unlock(&self) {
+        let state = self.state.fetch_sub(READ_LOCKED, Release) - READ_LOCKED;
+
+        // It's impossible for a reader to be waiting on a read-locked RWLock,
+        // except if there is also a writer waiting.
+        debug_assert!(!has_readers_waiting(state) || has_writers_waiting(state));
+
+        // Wake up a writer if we were the last reader and there's a writer
+        // waiting.
+        if is_unlocked(state) && has_writers_waiting(state) {
+            self.wake_writer_or_readers(state);
+        }
+    }
+    #[cold]
+    fn read_contended(&self) {
+        let mut state = self.spin_read();
 
         loop {
-            if users == RWLOCK_WRITER {
-                return false;
-            } else if users == RWLOCK_WRITER - 1 {
+            // If we can lock it, lock it.
+            if is_read_lockable(state) {
+                match self.state.compare_exchange_weak(state, state + READ_LOCKED,
+                                                       Acquire, Relaxed) {
+                    Ok(_) => return, // Locked!
+                    Err(s) => {
+                        state = s;
+                        continue;
+                    }
+                }
+            }
+
+            // Check for overflow.
+            if has_reached_max_readers(state) {
                 panic!("rwlock maximum reader count exceeded");
-            } else {
-                let users_prev = self.users_raw().compare_and_swap(users, users + 1, Ordering::Acquire);
-                if users == users_prev {
-                    // atomic swap was successfull, we are good
-                    return true;
+            }
+
+            // Make sure the readers waiting bit is set before we go to sleep.
+            if !has_readers_waiting(state) {
+                if let Err(s) = self.state.compare_exchange(state, state | READERS_WAITING,
+                                                            Relaxed, Relaxed) {
+                    state = s;
+                    continue;
                 }
-                users = users_prev;
             }
+
+            // Wait for the state to change.
+            futex_wait(&self.state, state | READERS_WAITING, None);
+
+            // Spin again after waking up.
+            state = self.spin_read();
         }
     }
     #[inline]
-    pub unsafe fn write(&self) {
-        loop {
-            let users_prev = self.users_raw().compare_and_swap(0, RWLOCK_WRITER, Ordering::Acquire);
-            if users_prev == 0 {
-                break;
+    pub unsafe fn try_write(&self) -> bool {
+        let mut state = self.state.load(Relaxed);
+        while is_unlocked(state) {
+            match self.state.compare_exchange_weak(state, state + WRITE_LOCKED, Acquire, Relaxed) {
+                Ok(_) => return true,
+                Err(s) => state = s,
             }
-
-            self.waiters.fetch_add(1, Ordering::Relaxed);
-            let futex: *const usize = self.users_raw().get_mut();
-            syscall!(FUTEX, futex, linux::FUTEX_WAIT_PRIVATE, users_prev, 0, 0, 0);
-            self.waiters.fetch_sub(1, Ordering::Relaxed);
         }
+        false
     }
     #[inline]
-    pub unsafe fn try_write(&self) -> bool {
-        let users_prev = self.users_raw().compare_and_swap(0, RWLOCK_WRITER, Ordering::Acquire);
-        users_prev == 0
+    pub unsafe fn write(&self) {
+        if self.state.compare_exchange_weak(0, WRITE_LOCKED, Acquire, Relaxed).is_err() {
+            self.write_contended();
+        }
     }
     #[inline]
-    pub unsafe fn read_unlock(&self) {
-        let mut users = self.users_raw().load(Ordering::Release);
+    pub unsafe fn write_unlock(&self) {
+        let state = self.state.fetch_sub(WRITE_LOCKED, Release) - WRITE_LOCKED;
+
+        debug_assert!(is_unlocked(state));
+
+        if has_writers_waiting(state) || has_readers_waiting(state) {
+            self.wake_writer_or_readers(state);
+        }
+    }
+    #[cold]
+    fn write_contended(&self) {
+        let mut state = self.spin_write();
+
+        let mut other_writers_waiting = 0;
 
         loop {
-            if users == RWLOCK_WRITER {
-                panic!("rwlock is locked by a writer");
+            // If it's unlocked, we try to lock it.
+            if is_unlocked(state) {
+                match self.state.compare_exchange_weak(state,
+                                                       state | WRITE_LOCKED | other_writers_waiting,
+                                                       Acquire,
+                                                       Relaxed) {
+                    Ok(_) => return, // Locked!
+                    Err(s) => {
+                        state = s;
+                        continue;
+                    }
+                }
             }
-            if users == 0 {
-                panic!("rwlock is not locked by a reader");
+
+            // Set the waiting bit indicating that we're waiting on it.
+            if !has_writers_waiting(state) {
+                if let Err(s) = self.state.compare_exchange(state, state | WRITERS_WAITING,
+                                                            Relaxed, Relaxed) {
+                    state = s;
+                    continue;
+                }
             }
-            let users_prev = self.users_raw().compare_and_swap(users, users - 1, Ordering::Release);
-            if users == users_prev {
-                if users - 1 == 0 && self.waiters.load(Ordering::Relaxed) != 0 {
-                    // As an optimization we can do some small amount of spins and check if the lock gets
-                    // unlocked. And only if spin does not work then go to sleep.
-
-                    let futex: *const usize = self.users_raw().get_mut();
-                    // We just dropped a read lock. If we had waiters then they all must be writers
-                    // (readers would not block). In this case no need to wake more than 1 waiter.
-                    syscall!(FUTEX, futex, linux::FUTEX_WAKE_PRIVATE, 1, 0, 0, 0);
+
+            // Other writers might be waiting now too, so we should make sure
+            // we keep that bit on once we manage lock it.
+            other_writers_waiting = WRITERS_WAITING;
+
+            // Examine the notification counter before we check if `state` has
+            // changed, to make sure we don't miss any notifications.
+            let seq = self.writer_notify.load(Acquire);
+
+            // Don't go to sleep if the lock has become available, or if the
+            // writers waiting bit is no longer set.
+            state = self.state.load(Relaxed);
+            if is_unlocked(state) || !has_writers_waiting(state) {
+                continue;
+            }
+
+            // Wait for the state to change.
+            futex_wait(&self.writer_notify, seq, None);
+
+            // Spin again after waking up.
+            state = self.spin_write();
+        }
+    }
+    // Wakes up waiting threads after unlocking.
+    //
+    // If both are waiting, this will wake up only one writer, but will fall
+    // back to waking up readers if there was no writer to wake up.
+    #[cold]
+    fn wake_writer_or_readers(&self, mut state: u32) {
+        assert!(is_unlocked(state));
+
+        // The readers waiting bit might be turned on at any point now, since
+        // readers will block when there's anything waiting. Writers will just
+        // lock the lock though, regardless of the waiting bits, so we don't
+        // have to worry about the writer waiting bit.
+        //
+        // If the lock gets locked in the meantime, we don't have to do
+        // anything, because then the thread that locked the lock will take
+        // care of waking up waiters when it unlocks.
+
+        // If only writers are waiting, wake one of them up.
+        if state == WRITERS_WAITING {
+            match self.state.compare_exchange(state, 0, Relaxed, Relaxed) {
+                Ok(_) => {
+                    self.wake_writer();
+                    return;
                 }
-                break;
+                Err(s) => {
+                    // Maybe some readers are now waiting too. So, continue to
+                    // the next `if`.
+                    state = s;
+                }
+            }
+        }
+
+        // If both writers and readers are waiting, leave the readers waiting
+        // and only wake up one writer.
+        if state == READERS_WAITING + WRITERS_WAITING {
+            if self.state.compare_exchange(state, READERS_WAITING, Relaxed, Relaxed).is_err() {
+                // The lock got locked. Not our problem anymore.
+                return;
+            }
+            if self.wake_writer() {
+                return;
+            }
+            // No writers were actually blocked on futex_wait, so we continue
+            // to wake up readers instead, since we can't be sure if we
+            // notified a writer.
+            state = READERS_WAITING;
+        }
+
+        // If readers are waiting, wake them all up.
+        if state == READERS_WAITING {
+            if self.state.compare_exchange(state, 0, Relaxed, Relaxed).is_ok() {
+                futex_wake_all(&self.state);
             }
-            users = users_prev;
         }
     }
-    #[inline]
-    pub unsafe fn write_unlock(&self) {
-        let users_prev = self.users_raw().compare_and_swap(RWLOCK_WRITER, 0, Ordering::Release);
-        if users_prev == RWLOCK_WRITER {
-            if self.waiters.load(Ordering::Relaxed) != 0 {
-                // As an optimization we can do some small amount of spins and check if the lock gets
-                // unlocked. And only if spin does not work then go to sleep.
-
-                let futex: *const usize = self.users_raw().get_mut();
-                // There can be both reader and writer waiters. Wake all of them and let's the fight
-                // begin.
-                syscall!(FUTEX, futex, linux::FUTEX_WAKE_PRIVATE, isize::MAX, 0, 0, 0);
+    // This wakes one writer and returns true if we woke up a writer that was
+    // blocked on futex_wait.
+    //
+    // If this returns false, it might still be the case that we notified a
+    // writer that was about to go to sleep.
+    fn wake_writer(&self) -> bool {
+        self.writer_notify.fetch_add(1, Release);
+        futex_wake(&self.writer_notify)
+    }
+    // Spin for a while, but stop directly at the given condition.
+    fn spin_until<F: Fn(u32) -> bool>(&self, f: F) -> u32 {
+        let mut spin = 100; // Chosen by fair dice roll.
+        loop {
+            let state = self.state.load(Relaxed);
+            if f(state) || spin == 0 {
+                return state;
             }
-        } else {
-            panic!("rwlock is not locked by a writer");
+            atomic::hint_core_should_pause();
+            spin -= 1;
         }
     }
+    fn spin_write(&self) -> u32 {
+        // Stop spinning when it's unlocked or when there's waiting writers, to
+        // keep things somewhat fair.
+        self.spin_until(|state| is_unlocked(state) || has_writers_waiting(state))
+    }
+    fn spin_read(&self) -> u32 {
+        // Stop spinning when it's unlocked or read locked, or when there's
+        // waiting threads.
+        self.spin_until(|state| {
+            !is_write_locked(state) || has_readers_waiting(state) || has_writers_waiting(state)
+        })
+    }
     #[inline]
     pub unsafe fn destroy(&self) {
     }
@@ -1,8 +1,133 @@
-pub struct Handler(());
+// Copyright 2014-2015 The Rust Project Developers. See the COPYRIGHT
+// file at the top-level directory of this distribution and at
+// http://rust-lang.org/COPYRIGHT.
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use libc;
+use mem;
+use ptr;
+use sys;
+use sys::cvt_ptr;
+use sys::os;
+use sys_common::thread_info;
+use sys_common::util::report_overflow;
+
+pub struct Handler {
+    _data: *mut libc::c_void
+}
 
 impl Handler {
     pub unsafe fn new() -> Handler {
-        // TODO(steed, #132): Implement a stack overflow handler.
-        Handler(())
+        make_handler()
+    }
+}
+
+impl Drop for Handler {
+    fn drop(&mut self) {
+        unsafe {
+            drop_handler(self);
+        }
+    }
+}
+
+// Signal handler for the SIGSEGV and SIGBUS handlers. We've got guard pages
+// (unmapped pages) at the end of every thread's stack, so if a thread ends
+// up running into the guard page it'll trigger this handler. We want to
+// detect these cases and print out a helpful error saying that the stack
+// has overflowed. All other signals, however, should go back to what they
+// were originally supposed to do.
+//
+// When the faulting address is within the guard page, the handler prints a
+// message saying that the thread has overflowed its stack and aborts the
+// process. Otherwise it un-registers itself and returns, so that the original
+// signal is delivered again with its default action. By returning we're
+// ensuring that other segfaults do indeed look like segfaults.
+//
+// Returning from this kind of signal handler is technically not defined to
+// work when reading the POSIX spec strictly, but in practice it turns out
+// many large systems and all implementations allow returning from a signal
+// handler to work. For a more detailed explanation see the comments on
+// rust-lang/rust#26458.
+unsafe extern fn signal_handler(signum: libc::c_int,
+                                info: *mut libc::siginfo_t,
+                                _data: *mut libc::c_void) {
+    let guard = thread_info::stack_guard().unwrap_or(0);
+    let addr = (*info).si_addr() as usize;
+
+    if guard != 0 && guard - os::page_size() <= addr && addr < guard {
+        report_overflow();
+        sys::abort_internal();
+    }
+
+    // Unregister ourselves by reverting back to the default behavior.
+    let mut action: libc::sigaction = mem::zeroed();
+    action.sa_handler = libc::SIG_DFL;
+    libc::sigaction(signum, &action, ptr::null_mut());
+
+    // See comment above for why this function returns.
+}
+
+static mut MAIN_ALTSTACK: *mut libc::c_void = 0 as *mut _;
+
+pub unsafe fn init() {
+    let mut action: libc::sigaction = mem::zeroed();
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_ONSTACK;
+    action.sa_handler = signal_handler as libc::sighandler_t;
+    libc::sigaction(libc::SIGSEGV, &action, ptr::null_mut());
+    libc::sigaction(libc::SIGBUS, &action, ptr::null_mut());
+
+    let handler = make_handler();
+    MAIN_ALTSTACK = handler._data;
+    mem::forget(handler);
+}
+
+pub unsafe fn cleanup() {
+    Handler { _data: MAIN_ALTSTACK };
+}
+
+unsafe fn get_stackp() -> *mut libc::c_void {
+    let stackp = libc::mmap(ptr::null_mut(),
+                            libc::SIGSTKSZ,
+                            libc::PROT_READ | libc::PROT_WRITE,
+                            libc::MAP_PRIVATE | libc::MAP_ANON,
+                            -1,
+                            0);
+    match cvt_ptr(stackp) {
+        Ok(stackp) => stackp,
+        Err(_) => panic!("failed to allocate an alternative stack"),
+    }
+}
+
+unsafe fn get_stack() -> libc::stack_t {
+    libc::stack_t { ss_sp: get_stackp(), ss_flags: 0, ss_size: libc::SIGSTKSZ }
+}
+
+unsafe fn make_handler() -> Handler {
+    let mut stack = mem::zeroed();
+    libc::sigaltstack(ptr::null(), &mut stack);
+    // Configure alternate signal stack, if one is not already set.
+    if stack.ss_flags & libc::SS_DISABLE != 0 {
+        stack = get_stack();
+        libc::sigaltstack(&stack, ptr::null_mut());
+        Handler { _data: stack.ss_sp as *mut libc::c_void }
+    } else {
+        Handler { _data: ptr::null_mut() }
+    }
+}
+
+unsafe fn drop_handler(handler: &mut Handler) {
+    if !handler._data.is_null() {
+        let stack = libc::stack_t {
+            ss_sp: ptr::null_mut(),
+            ss_flags: libc::SS_DISABLE,
+            ss_size: libc::SIGSTKSZ,
+        };
+        libc::sigaltstack(&stack, ptr::null_mut());
+        libc::munmap(handler._data, libc::SIGSTKSZ);
     }
 }
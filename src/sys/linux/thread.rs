@@ -172,9 +172,9 @@ impl Thread {
         }
     }
 
-    /*
     pub fn id(&self) -> libc::pthread_t { self.id }
 
+    /*
     pub fn into_id(self) -> libc::pthread_t {
         let id = self.id;
         mem::forget(self);
@@ -183,175 +183,89 @@ impl Thread {
     */
 }
 
-// TODO(steed, #134): Implement `Drop` for `Thread`.
-/*
 impl Drop for Thread {
     fn drop(&mut self) {
         let ret = unsafe { libc::pthread_detach(self.id) };
         debug_assert_eq!(ret, 0);
     }
 }
-*/
-
-#[cfg(all(not(all(target_os = "linux", not(target_env = "musl"))),
-          not(target_os = "freebsd"),
-          not(target_os = "macos"),
-          not(target_os = "bitrig"),
-          not(all(target_os = "netbsd", not(target_vendor = "rumprun"))),
-          not(target_os = "openbsd"),
-          not(target_os = "solaris")))]
-#[cfg_attr(test, allow(dead_code))]
-pub mod guard {
-    pub unsafe fn current() -> Option<usize> { None }
-    pub unsafe fn init() -> Option<usize> { None }
-}
 
-
-/*
-#[cfg(any(all(target_os = "linux", not(target_env = "musl")),
-          target_os = "freebsd",
-          target_os = "macos",
-          target_os = "bitrig",
-          all(target_os = "netbsd", not(target_vendor = "rumprun")),
-          target_os = "openbsd",
-          target_os = "solaris"))]
+// Rust 1.15.0 (`current` only, `init` is our own)
 #[cfg_attr(test, allow(dead_code))]
 pub mod guard {
+    use fs::File;
+    use io::Read;
     use libc;
-    use libc::mmap;
-    use libc::{PROT_NONE, MAP_PRIVATE, MAP_ANON, MAP_FAILED, MAP_FIXED};
+    use mem;
+    use ptr;
     use sys::os;
 
-    #[cfg(any(target_os = "macos",
-              target_os = "bitrig",
-              target_os = "openbsd",
-              target_os = "solaris"))]
-    unsafe fn get_stack_start() -> Option<*mut libc::c_void> {
-        current().map(|s| s as *mut libc::c_void)
-    }
-
-    #[cfg(any(target_os = "android", target_os = "freebsd",
-              target_os = "linux", target_os = "netbsd"))]
-    unsafe fn get_stack_start() -> Option<*mut libc::c_void> {
-        let mut ret = None;
-        let mut attr: libc::pthread_attr_t = ::mem::zeroed();
-        assert_eq!(libc::pthread_attr_init(&mut attr), 0);
-        #[cfg(target_os = "freebsd")]
-            let e = libc::pthread_attr_get_np(libc::pthread_self(), &mut attr);
-        #[cfg(not(target_os = "freebsd"))]
-            let e = libc::pthread_getattr_np(libc::pthread_self(), &mut attr);
-        if e == 0 {
-            let mut stackaddr = ::ptr::null_mut();
-            let mut stacksize = 0;
-            assert_eq!(libc::pthread_attr_getstack(&attr, &mut stackaddr,
-                                                   &mut stacksize), 0);
-            ret = Some(stackaddr);
-        }
-        assert_eq!(libc::pthread_attr_destroy(&mut attr), 0);
-        ret
-    }
-
+    // The kernel maps the main thread's stack itself and grows it on demand,
+    // down to `RLIMIT_STACK` bytes below its top. We find the top in
+    // `/proc/self/maps`; the kernel won't map anything right below the lowest
+    // address the stack can grow to, so that's where a stack overflow faults.
     pub unsafe fn init() -> Option<usize> {
-        let psize = os::page_size();
-        let mut stackaddr = match get_stack_start() {
-            Some(addr) => addr,
-            None => return None,
-        };
-
-        // Ensure stackaddr is page aligned! A parent process might
-        // have reset RLIMIT_STACK to be non-page aligned. The
-        // pthread_attr_getstack() reports the usable stack area
-        // stackaddr < stackaddr + stacksize, so if stackaddr is not
-        // page-aligned, calculate the fix such that stackaddr <
-        // new_page_aligned_stackaddr < stackaddr + stacksize
-        let remainder = (stackaddr as usize) % psize;
-        if remainder != 0 {
-            stackaddr = ((stackaddr as usize) + psize - remainder)
-                as *mut libc::c_void;
-        }
-
-        // Rellocate the last page of the stack.
-        // This ensures SIGBUS will be raised on
-        // stack overflow.
-        let result = mmap(stackaddr, psize, PROT_NONE,
-                          MAP_PRIVATE | MAP_ANON | MAP_FIXED, -1, 0);
-
-        if result != stackaddr || result == MAP_FAILED {
-            panic!("failed to allocate a guard page");
+        let mut limit: libc::rlimit64 = mem::zeroed();
+        if libc::prlimit64(0, libc::RLIMIT_STACK, ptr::null(), &mut limit) < 0 ||
+           limit.rlim_cur == libc::RLIM64_INFINITY {
+            return None;
         }
 
-        let offset = if cfg!(any(target_os = "linux", target_os = "freebsd")) {
-            2
-        } else {
-            1
-        };
-
-        Some(stackaddr as usize + offset * psize)
+        let psize = os::page_size();
+        let stackaddr = try_opt!(stack_top()).checked_sub(limit.rlim_cur as usize);
+        // Round up, the guard is the page below the stack
+        stackaddr.map(|addr| (addr + psize - 1) & !(psize - 1))
     }
 
-    #[cfg(target_os = "solaris")]
-    pub unsafe fn current() -> Option<usize> {
-        let mut current_stack: libc::stack_t = ::mem::zeroed();
-        assert_eq!(libc::stack_getbounds(&mut current_stack), 0);
-        Some(current_stack.ss_sp as usize)
-    }
+    // Returns the end of the mapping that contains the current stack
+    fn stack_top() -> Option<usize> {
+        // Somewhere on the stack
+        let local = 0u8;
+        let sp = &local as *const u8 as usize;
 
-    #[cfg(target_os = "macos")]
-    pub unsafe fn current() -> Option<usize> {
-        Some((libc::pthread_get_stackaddr_np(libc::pthread_self()) as usize -
-              libc::pthread_get_stacksize_np(libc::pthread_self())))
+        let mut maps = String::new();
+        if File::open("/proc/self/maps").and_then(|mut f| f.read_to_string(&mut maps)).is_err() {
+            return None;
+        }
+        for line in maps.lines() {
+            // start-end perms offset dev inode path
+            let mut range = try_opt!(line.split(' ').next()).split('-');
+            let start = try_opt!(range.next().and_then(parse_hex));
+            let end = try_opt!(range.next().and_then(parse_hex));
+            if start <= sp && sp < end {
+                return Some(end);
+            }
+        }
+        None
     }
 
-    #[cfg(any(target_os = "openbsd", target_os = "bitrig"))]
-    pub unsafe fn current() -> Option<usize> {
-        let mut current_stack: libc::stack_t = ::mem::zeroed();
-        assert_eq!(libc::pthread_stackseg_np(libc::pthread_self(),
-                                             &mut current_stack), 0);
-
-        let extra = if cfg!(target_os = "bitrig") {3} else {1} * os::page_size();
-        Some(if libc::pthread_main_np() == 1 {
-            // main thread
-            current_stack.ss_sp as usize - current_stack.ss_size + extra
-        } else {
-            // new thread
-            current_stack.ss_sp as usize - current_stack.ss_size
-        })
+    fn parse_hex(s: &str) -> Option<usize> {
+        usize::from_str_radix(s, 16).ok()
     }
 
-    #[cfg(any(target_os = "android", target_os = "freebsd",
-              target_os = "linux", target_os = "netbsd"))]
     pub unsafe fn current() -> Option<usize> {
         let mut ret = None;
-        let mut attr: libc::pthread_attr_t = ::mem::zeroed();
+        let mut attr: libc::pthread_attr_t = mem::zeroed();
         assert_eq!(libc::pthread_attr_init(&mut attr), 0);
-        #[cfg(target_os = "freebsd")]
-            let e = libc::pthread_attr_get_np(libc::pthread_self(), &mut attr);
-        #[cfg(not(target_os = "freebsd"))]
-            let e = libc::pthread_getattr_np(libc::pthread_self(), &mut attr);
+        let e = libc::pthread_getattr_np(libc::pthread_self(), &mut attr);
         if e == 0 {
             let mut guardsize = 0;
             assert_eq!(libc::pthread_attr_getguardsize(&attr, &mut guardsize), 0);
-            if guardsize == 0 {
-                panic!("there is no guard page");
+            // steed: The main thread has no guard page we know of here, its
+            // guard comes from `init`
+            if guardsize != 0 {
+                let mut stackaddr = ptr::null_mut();
+                let mut size = 0;
+                assert_eq!(libc::pthread_attr_getstack(&attr, &mut stackaddr,
+                                                       &mut size), 0);
+                // steed: `stackaddr` is right above the guard page
+                ret = Some(stackaddr as usize);
             }
-            let mut stackaddr = ::ptr::null_mut();
-            let mut size = 0;
-            assert_eq!(libc::pthread_attr_getstack(&attr, &mut stackaddr,
-                                                   &mut size), 0);
-
-            ret = if cfg!(target_os = "freebsd") {
-                Some(stackaddr as usize - guardsize)
-            } else if cfg!(target_os = "netbsd") {
-                Some(stackaddr as usize)
-            } else {
-                Some(stackaddr as usize + guardsize)
-            };
         }
         assert_eq!(libc::pthread_attr_destroy(&mut attr), 0);
         ret
     }
 }
-*/
 
 // glibc >= 2.15 has a __pthread_get_minstack() function that returns
 // PTHREAD_STACK_MIN plus however many bytes are needed for thread-local
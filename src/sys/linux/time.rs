@@ -11,7 +11,7 @@
 use cmp::Ordering;
 use time::Duration;
 use linux;
-use ctypes::c_long;
+use ctypes::{c_int, c_long};
 
 pub use self::inner::{Instant, SystemTime, UNIX_EPOCH};
 
@@ -42,23 +42,32 @@ impl Timespec {
     }
 
     fn add_duration(&self, other: &Duration) -> Timespec {
-        let secs = (self.t.tv_sec as i64).checked_add(other.as_secs() as i64);
-        let mut secs = secs.expect("overflow when adding duration to time");
+        self.checked_add_duration(other).expect("overflow when adding duration to time")
+    }
+
+    fn checked_add_duration(&self, other: &Duration) -> Option<Timespec> {
+        if other.as_secs() > i64::max_value() as u64 {
+            return None;
+        }
+        let mut secs = try_opt!((self.t.tv_sec as i64).checked_add(other.as_secs() as i64));
 
         // Nano calculations can't overflow because nanos are <1B which fit
         // in a u32.
         let mut nsec = other.subsec_nanos() + self.t.tv_nsec as u32;
         if nsec >= NSEC_PER_SEC as u32 {
             nsec -= NSEC_PER_SEC as u32;
-            secs = secs.checked_add(1).expect("overflow when adding \
-                                               duration to time");
+            secs = try_opt!(secs.checked_add(1));
         }
-        Timespec {
+        // `time_t` is 32 bits wide on some platforms
+        if secs > linux::time_t::max_value() as i64 {
+            return None;
+        }
+        Some(Timespec {
             t: linux::timespec {
                 tv_sec: secs as linux::time_t,
                 tv_nsec: nsec as c_long,
             },
-        }
+        })
     }
 
     fn sub_duration(&self, other: &Duration) -> Timespec {
@@ -107,9 +116,10 @@ impl Ord for Timespec {
 mod inner {
     use fmt;
     use linux;
+    use sys::cvt;
     use time::Duration;
 
-    use super::Timespec;
+    use super::{Timespec, clock_gettime};
 
     #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
     pub struct Instant {
@@ -145,6 +155,14 @@ mod inner {
             Instant { t: self.t.add_duration(other) }
         }
 
+        pub fn checked_add_duration(&self, other: &Duration) -> Option<Instant> {
+            self.t.checked_add_duration(other).map(|t| Instant { t: t })
+        }
+
+        pub fn as_timespec(&self) -> &linux::timespec {
+            &self.t.t
+        }
+
         pub fn sub_duration(&self, other: &Duration) -> Instant {
             Instant { t: self.t.sub_duration(other) }
         }
@@ -200,11 +218,54 @@ mod inner {
                 tv_nsec: 0,
             }
         };
-        // TODO issue #10
-        // cvt(clock_gettime.unwrap())
-        unsafe {
-            assert_eq!(linux::clock_gettime(clock, &mut t.t), 0)
-        }
+        cvt(unsafe { clock_gettime(clock, &mut t.t) }).unwrap();
         t
     }
 }
+
+#[cfg(any(target_arch = "aarch64",
+          target_arch = "arm",
+          target_arch = "x86",
+          target_arch = "x86_64"))]
+unsafe fn clock_gettime(clock: linux::clockid_t, tp: *mut linux::timespec) -> c_int {
+    use mem;
+    use sync::atomic::{AtomicUsize, Ordering};
+    use sys::vdso;
+
+    #[cfg(target_arch = "aarch64")]
+    const SYMBOL: (&'static str, &'static str) = ("__kernel_clock_gettime", "LINUX_2.6.39");
+    #[cfg(not(target_arch = "aarch64"))]
+    const SYMBOL: (&'static str, &'static str) = ("__vdso_clock_gettime", "LINUX_2.6");
+
+    // The address of the vDSO function; `UNRESOLVED` until the first call and
+    // `UNAVAILABLE` if there's no such function
+    const UNRESOLVED: usize = 0;
+    const UNAVAILABLE: usize = 1;
+    static VDSO_CLOCK_GETTIME: AtomicUsize = AtomicUsize::new(UNRESOLVED);
+
+    let mut f = VDSO_CLOCK_GETTIME.load(Ordering::Relaxed);
+    if f == UNRESOLVED {
+        f = vdso::lookup(SYMBOL.0, SYMBOL.1).unwrap_or(UNAVAILABLE);
+        VDSO_CLOCK_GETTIME.store(f, Ordering::Relaxed);
+    }
+
+    if f == UNAVAILABLE {
+        linux::clock_gettime(clock, tp)
+    } else {
+        // Like the system call, the vDSO function returns a negated errno on
+        // failure
+        let f: extern "C" fn(linux::clockid_t, *mut linux::timespec) -> c_int =
+            mem::transmute(f);
+        f(clock, tp)
+    }
+}
+
+// TODO(steed): The vDSO of the other architectures either doesn't implement
+// `clock_gettime` or doesn't use the C calling convention for it
+#[cfg(not(any(target_arch = "aarch64",
+              target_arch = "arm",
+              target_arch = "x86",
+              target_arch = "x86_64")))]
+unsafe fn clock_gettime(clock: linux::clockid_t, tp: *mut linux::timespec) -> c_int {
+    linux::clock_gettime(clock, tp)
+}
@@ -0,0 +1,94 @@
+use super::{Registers, _Unwind_Exception, _Unwind_Reason_Code};
+
+// DWARF register numbers 0 - 30 are x0 - x30, 31 is sp and 72 - 79 are the
+// callee-saved floating point registers d8 - d15. Registers[0..32] hold the
+// general purpose registers and sp, Registers[32] the program counter and
+// Registers[33..41] d8 - d15.
+pub const NREGS: usize = 41;
+pub const SP: usize = 31;
+pub const IP: usize = 32;
+
+pub fn slot(reg: u16) -> Option<usize> {
+    match reg {
+        0...31 => Some(reg as usize),
+        72...79 => Some(reg as usize - 72 + 33),
+        _ => None,
+    }
+}
+
+extern "C" {
+    pub fn __steed_unwind_save(f: extern "C" fn(&mut Registers,
+                                                *mut _Unwind_Exception)
+                                                -> _Unwind_Reason_Code,
+                               exception: *mut _Unwind_Exception)
+                               -> _Unwind_Reason_Code;
+    pub fn __steed_unwind_restore(regs: &Registers) -> !;
+}
+
+// Only the callee-saved registers, the stack pointer and the return address
+// are meaningful in the saved state; they describe the caller as it will be
+// once we return.
+global_asm!("
+    .globl __steed_unwind_save
+    .hidden __steed_unwind_save
+    __steed_unwind_save:
+
+    stp x29, x30, [sp, #-16]!
+    sub sp, sp, #0x150 // Registers, rounded up to keep sp 16 byte aligned
+
+    stp x19, x20, [sp, #0x98]
+    stp x21, x22, [sp, #0xa8]
+    stp x23, x24, [sp, #0xb8]
+    stp x25, x26, [sp, #0xc8]
+    stp x27, x28, [sp, #0xd8]
+    stp x29, x30, [sp, #0xe8]
+    add x9, sp, #0x160 // sp after we return
+    str x9, [sp, #0xf8]
+    str x30, [sp, #0x100] // return address
+    stp d8, d9, [sp, #0x108]
+    stp d10, d11, [sp, #0x118]
+    stp d12, d13, [sp, #0x128]
+    stp d14, d15, [sp, #0x138]
+
+    mov x9, x0 // f
+    mov x0, sp // regs
+               // exception is already in x1
+    blr x9
+
+    add sp, sp, #0x150
+    ldp x29, x30, [sp], #16
+    ret
+
+    .globl __steed_unwind_restore
+    .hidden __steed_unwind_restore
+    __steed_unwind_restore:
+
+    ldp d8, d9, [x0, #0x108]
+    ldp d10, d11, [x0, #0x118]
+    ldp d12, d13, [x0, #0x128]
+    ldp d14, d15, [x0, #0x138]
+    ldr x1, [x0, #0xf8]
+    mov sp, x1
+
+    // x17 (ip1) is a scratch register nobody expects to survive a call, we use
+    // it to hold the program counter
+    ldr x17, [x0, #0x100]
+    ldr x16, [x0, #0x80]
+    ldp x2, x3, [x0, #0x10]
+    ldp x4, x5, [x0, #0x20]
+    ldp x6, x7, [x0, #0x30]
+    ldp x8, x9, [x0, #0x40]
+    ldp x10, x11, [x0, #0x50]
+    ldp x12, x13, [x0, #0x60]
+    ldp x14, x15, [x0, #0x70]
+    ldp x18, x19, [x0, #0x90]
+    ldp x20, x21, [x0, #0xa0]
+    ldp x22, x23, [x0, #0xb0]
+    ldp x24, x25, [x0, #0xc0]
+    ldp x26, x27, [x0, #0xd0]
+    ldp x28, x29, [x0, #0xe0]
+    ldr x30, [x0, #0xf0]
+    ldp x0, x1, [x0]
+
+    br x17
+");
@@ -0,0 +1,113 @@
+use super::{Registers, _Unwind_Exception, _Unwind_Reason_Code};
+
+// Registers[0..16] hold the core registers r0 - r15 (r13 is sp, r14 lr and
+// r15 pc), Registers[16..32] the callee-saved VFP registers d8 - d15, two
+// slots each. Only the core registers can be accessed by their (DWARF / EHABI)
+// register number, the VFP registers are only touched by the EHABI unwinder.
+pub const NREGS: usize = 32;
+pub const SP: usize = 13;
+pub const LR: usize = 14;
+pub const IP: usize = 15;
+pub const VFP: usize = 16;
+
+pub fn slot(reg: u16) -> Option<usize> {
+    if reg < 16 {
+        Some(reg as usize)
+    } else {
+        None
+    }
+}
+
+extern "C" {
+    pub fn __steed_unwind_save(f: extern "C" fn(&mut Registers,
+                                                *mut _Unwind_Exception)
+                                                -> _Unwind_Reason_Code,
+                               exception: *mut _Unwind_Exception)
+                               -> _Unwind_Reason_Code;
+    pub fn __steed_unwind_restore(regs: &Registers) -> !;
+}
+
+// Only the callee-saved registers, the stack pointer and the return address
+// are meaningful in the saved state; they describe the caller as it will be
+// once we return.
+#[cfg(not(target_feature = "vfp2"))]
+global_asm!("
+    .globl __steed_unwind_save
+    .hidden __steed_unwind_save
+    .type __steed_unwind_save,%function
+    __steed_unwind_save:
+
+    push {r4, lr}
+    sub sp, sp, #128 @ Registers
+
+    add r3, sp, #16
+    stmia r3, {r4-r11}
+    add r3, sp, #136 @ sp after we return
+    str r3, [sp, #52]
+    str lr, [sp, #56]
+    str lr, [sp, #60] @ return address
+
+    mov r3, r0 @ f
+    mov r0, sp @ regs
+               @ exception is already in r1
+    blx r3
+
+    add sp, sp, #128
+    pop {r4, pc}
+
+    .globl __steed_unwind_restore
+    .hidden __steed_unwind_restore
+    .type __steed_unwind_restore,%function
+    __steed_unwind_restore:
+
+    ldr sp, [r0, #52]
+    ldr lr, [r0, #56]
+    @ r12 (ip) is a scratch register nobody expects to survive a call, we use
+    @ it to hold the program counter
+    ldr r12, [r0, #60]
+    ldmia r0, {r0-r11}
+    bx r12
+");
+
+#[cfg(target_feature = "vfp2")]
+global_asm!("
+    .globl __steed_unwind_save
+    .hidden __steed_unwind_save
+    .type __steed_unwind_save,%function
+    __steed_unwind_save:
+
+    push {r4, lr}
+    sub sp, sp, #128 @ Registers
+
+    add r3, sp, #16
+    stmia r3, {r4-r11}
+    add r3, sp, #136 @ sp after we return
+    str r3, [sp, #52]
+    str lr, [sp, #56]
+    str lr, [sp, #60] @ return address
+    add r3, sp, #64
+    vstmia r3, {d8-d15}
+
+    mov r3, r0 @ f
+    mov r0, sp @ regs
+               @ exception is already in r1
+    blx r3
+
+    add sp, sp, #128
+    pop {r4, pc}
+
+    .globl __steed_unwind_restore
+    .hidden __steed_unwind_restore
+    .type __steed_unwind_restore,%function
+    __steed_unwind_restore:
+
+    add r1, r0, #64
+    vldmia r1, {d8-d15}
+    ldr sp, [r0, #52]
+    ldr lr, [r0, #56]
+    @ r12 (ip) is a scratch register nobody expects to survive a call, we use
+    @ it to hold the program counter
+    ldr r12, [r0, #60]
+    ldmia r0, {r0-r11}
+    bx r12
+");
@@ -0,0 +1,781 @@
+//! Unwinding using DWARF call frame information
+//!
+//! Every function is described by a Frame Description Entry (FDE) in the
+//! `.eh_frame` section, which refers to a Common Information Entry (CIE) that
+//! holds what's shared between FDEs. Together they describe, for every
+//! instruction of the function, how to compute the Canonical Frame Address
+//! (CFA) and where the caller's registers were saved. See DWARF-4 Section 6.4,
+//! "Call Frame Information", and the LSB for the `.eh_frame` specifics:
+//! http://refspecs.linuxfoundation.org/LSB_3.0.0/LSB-PDA/LSB-PDA/ehframechpt.html
+//!
+//! The FDE for an address is found with the binary search table in the
+//! `.eh_frame_hdr` section, which the linker creates when passed
+//! `--eh-frame-hdr`.
+
+use ctypes::{c_int, c_void};
+use linux::elf::PT_GNU_EH_FRAME;
+use mem;
+use panic_unwind::dwarf::DwarfReader;
+use panic_unwind::dwarf::eh::{self, EHContext, DW_EH_PE_absptr, DW_EH_PE_datarel,
+                              DW_EH_PE_omit, DW_EH_PE_sdata4};
+use sys::phdr;
+
+use super::*;
+use super::arch::NREGS;
+use super::expr;
+
+pub type _Unwind_Action = c_int;
+
+pub const _UA_SEARCH_PHASE: c_int = 1;
+pub const _UA_CLEANUP_PHASE: c_int = 2;
+pub const _UA_HANDLER_FRAME: c_int = 4;
+pub const _UA_FORCE_UNWIND: c_int = 8;
+pub const _UA_END_OF_STACK: c_int = 16;
+
+pub type _Unwind_Personality_Fn = extern "C" fn(version: c_int,
+                                                actions: _Unwind_Action,
+                                                exception_class: _Unwind_Exception_Class,
+                                                exception_object: *mut _Unwind_Exception,
+                                                context: *mut _Unwind_Context)
+                                                -> _Unwind_Reason_Code;
+
+pub struct _Unwind_Context {
+    regs: Registers,
+    lsda: usize,
+    func_start: usize,
+}
+
+const DW_CFA_advance_loc: u8 = 0x1 << 6;
+const DW_CFA_offset: u8 = 0x2 << 6;
+const DW_CFA_restore: u8 = 0x3 << 6;
+const DW_CFA_nop: u8 = 0x00;
+const DW_CFA_set_loc: u8 = 0x01;
+const DW_CFA_advance_loc1: u8 = 0x02;
+const DW_CFA_advance_loc2: u8 = 0x03;
+const DW_CFA_advance_loc4: u8 = 0x04;
+const DW_CFA_offset_extended: u8 = 0x05;
+const DW_CFA_restore_extended: u8 = 0x06;
+const DW_CFA_undefined: u8 = 0x07;
+const DW_CFA_same_value: u8 = 0x08;
+const DW_CFA_register: u8 = 0x09;
+const DW_CFA_remember_state: u8 = 0x0a;
+const DW_CFA_restore_state: u8 = 0x0b;
+const DW_CFA_def_cfa: u8 = 0x0c;
+const DW_CFA_def_cfa_register: u8 = 0x0d;
+const DW_CFA_def_cfa_offset: u8 = 0x0e;
+const DW_CFA_def_cfa_expression: u8 = 0x0f;
+const DW_CFA_expression: u8 = 0x10;
+const DW_CFA_offset_extended_sf: u8 = 0x11;
+const DW_CFA_def_cfa_sf: u8 = 0x12;
+const DW_CFA_def_cfa_offset_sf: u8 = 0x13;
+const DW_CFA_val_offset: u8 = 0x14;
+const DW_CFA_val_offset_sf: u8 = 0x15;
+const DW_CFA_val_expression: u8 = 0x16;
+const DW_CFA_GNU_window_save: u8 = 0x2d;
+const DW_CFA_GNU_args_size: u8 = 0x2e;
+const DW_CFA_GNU_negative_offset_extended: u8 = 0x2f;
+
+// How deep DW_CFA_remember_state may nest
+const STATE_STACK_SIZE: usize = 4;
+
+struct Cie {
+    // Whether FDEs have augmentation data
+    augmentation_data: bool,
+    code_alignment: u64,
+    data_alignment: i64,
+    return_address_register: u16,
+    fde_encoding: u8,
+    lsda_encoding: u8,
+    personality: usize,
+    instructions: *const u8,
+    end: *const u8,
+}
+
+struct Fde {
+    cie: Cie,
+    pc_begin: usize,
+    pc_range: usize,
+    lsda: usize,
+    instructions: *const u8,
+    end: *const u8,
+}
+
+#[derive(Clone, Copy)]
+enum Rule {
+    SameValue,
+    Undefined,
+    Offset(i64),
+    ValOffset(i64),
+    Register(u16),
+    // Pointers to expression blocks
+    Expression(*const u8),
+    ValExpression(*const u8),
+}
+
+#[derive(Clone, Copy)]
+enum CfaRule {
+    RegisterOffset(u16, i64),
+    // Pointer to an expression block
+    Expression(*const u8),
+}
+
+// A row of the table DWARF-4 Section 6.4.1 describes
+#[derive(Clone, Copy)]
+struct Row {
+    cfa: CfaRule,
+    rules: [Rule; NREGS],
+}
+
+// Encoded pointers in `.eh_frame` are never relative to anything but
+// themselves (or `.eh_frame_hdr`, see `Fde::find`)
+fn context() -> EHContext<'static> {
+    static NONE: fn() -> usize = none;
+    fn none() -> usize {
+        0
+    }
+
+    EHContext {
+        ip: 0,
+        func_start: 0,
+        get_text_start: &NONE,
+        get_data_start: &NONE,
+    }
+}
+
+unsafe fn read_length(reader: &mut DwarfReader) -> Option<usize> {
+    match reader.read::<u32>() {
+        0 => None,
+        0xffffffff => Some(reader.read::<u64>() as usize),
+        length => Some(length as usize),
+    }
+}
+
+impl Cie {
+    unsafe fn parse(ptr: *const u8) -> Option<Cie> {
+        let mut reader = DwarfReader::new(ptr);
+        let length = try_opt!(read_length(&mut reader));
+        let end = reader.ptr.offset(length as isize);
+
+        if reader.read::<u32>() != 0 {
+            // Not a CIE
+            return None;
+        }
+
+        let version = reader.read::<u8>();
+        if version != 1 && version != 3 {
+            return None;
+        }
+
+        let augmentation = reader.ptr;
+        while reader.read::<u8>() != 0 {}
+
+        let mut cie = Cie {
+            augmentation_data: *augmentation == b'z',
+            code_alignment: reader.read_uleb128(),
+            data_alignment: reader.read_sleb128(),
+            return_address_register: if version == 1 {
+                reader.read::<u8>() as u16
+            } else {
+                reader.read_uleb128() as u16
+            },
+            fde_encoding: DW_EH_PE_absptr,
+            lsda_encoding: DW_EH_PE_omit,
+            personality: 0,
+            instructions: end,
+            end: end,
+        };
+
+        if cie.augmentation_data {
+            let length = reader.read_uleb128();
+            let data_end = reader.ptr.offset(length as isize);
+
+            let mut augmentation = augmentation.offset(1);
+            loop {
+                match *augmentation {
+                    b'L' => cie.lsda_encoding = reader.read::<u8>(),
+                    b'R' => cie.fde_encoding = reader.read::<u8>(),
+                    b'P' => {
+                        let encoding = reader.read::<u8>();
+                        cie.personality =
+                            eh::read_encoded_pointer(&mut reader, &context(), encoding);
+                    }
+                    // Signal frame, we don't unwind through signal handlers
+                    b'S' => {}
+                    // End of the string, or something we don't know about but
+                    // can skip thanks to the length of the data
+                    _ => break,
+                }
+                augmentation = augmentation.offset(1);
+            }
+
+            reader.ptr = data_end;
+        } else if *augmentation != 0 {
+            return None;
+        }
+
+        cie.instructions = reader.ptr;
+        Some(cie)
+    }
+}
+
+impl Fde {
+    unsafe fn parse(ptr: *const u8) -> Option<Fde> {
+        let mut reader = DwarfReader::new(ptr);
+        let length = try_opt!(read_length(&mut reader));
+        let end = reader.ptr.offset(length as isize);
+
+        // The CIE pointer is relative to its own position
+        let id = reader.ptr;
+        let cie_pointer = reader.read::<u32>();
+        if cie_pointer == 0 {
+            // Not an FDE
+            return None;
+        }
+        let cie = try_opt!(Cie::parse(id.offset(-(cie_pointer as isize))));
+
+        let pc_begin = eh::read_encoded_pointer(&mut reader, &context(), cie.fde_encoding);
+        let pc_range = eh::read_encoded_pointer(&mut reader,
+                                                &context(),
+                                                cie.fde_encoding & 0x0f);
+
+        let mut lsda = 0;
+        if cie.augmentation_data {
+            let length = reader.read_uleb128();
+            let data_end = reader.ptr.offset(length as isize);
+            if cie.lsda_encoding != DW_EH_PE_omit {
+                lsda = eh::read_encoded_pointer(&mut reader, &context(), cie.lsda_encoding);
+            }
+            reader.ptr = data_end;
+        }
+
+        Some(Fde {
+            cie: cie,
+            pc_begin: pc_begin,
+            pc_range: pc_range,
+            lsda: lsda,
+            instructions: reader.ptr,
+            end: end,
+        })
+    }
+
+    /// Finds the FDE that covers `pc`
+    unsafe fn find(pc: usize) -> Option<Fde> {
+        let hdr = try_opt!(phdr::segment(PT_GNU_EH_FRAME)).as_ptr();
+        let mut reader = DwarfReader::new(hdr);
+
+        let version = reader.read::<u8>();
+        let eh_frame_ptr_enc = reader.read::<u8>();
+        let fde_count_enc = reader.read::<u8>();
+        let table_enc = reader.read::<u8>();
+        if version != 1 || eh_frame_ptr_enc == DW_EH_PE_omit ||
+           fde_count_enc == DW_EH_PE_omit {
+            return None;
+        }
+
+        eh::read_encoded_pointer(&mut reader, &context(), eh_frame_ptr_enc);
+        let fde_count = eh::read_encoded_pointer(&mut reader, &context(), fde_count_enc);
+
+        // The table is sorted by initial location. Each entry is the initial
+        // location and the address of an FDE, both relative to the start of
+        // `.eh_frame_hdr`. Every linker we care about uses 32-bit entries.
+        if table_enc != DW_EH_PE_datarel | DW_EH_PE_sdata4 {
+            return None;
+        }
+        let table = reader.ptr as *const [i32; 2];
+        let address = |offset: i32| (hdr as usize).wrapping_add(offset as usize);
+
+        // Find the last entry whose initial location is not past `pc`
+        let (mut lo, mut hi) = (0, fde_count);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if address((*table.offset(mid as isize))[0]) <= pc {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        if lo == 0 {
+            return None;
+        }
+
+        let fde = try_opt!(Fde::parse(address((*table.offset(lo as isize - 1))[1]) as
+                                      *const u8));
+        if pc < fde.pc_begin || pc - fde.pc_begin >= fde.pc_range {
+            return None;
+        }
+        Some(fde)
+    }
+
+    /// Returns the state of the caller of the frame `regs` describes, or
+    /// `None` if the frame has no caller or its CFI can't be understood.
+    unsafe fn unwind(&self, regs: &Registers) -> Option<Registers> {
+        let row = try_opt!(self.row(regs.ip() - 1));
+
+        let cfa = match row.cfa {
+            CfaRule::RegisterOffset(reg, offset) => {
+                try_opt!(regs.get(reg)).wrapping_add(offset as usize)
+            }
+            CfaRule::Expression(expr) => try_opt!(expr::evaluate(expr, regs, None)),
+        };
+
+        let mut caller = *regs;
+        for (slot, rule) in row.rules.iter().enumerate() {
+            let value = match *rule {
+                Rule::SameValue | Rule::Undefined => continue,
+                Rule::Offset(offset) => *(cfa.wrapping_add(offset as usize) as *const usize),
+                Rule::ValOffset(offset) => cfa.wrapping_add(offset as usize),
+                Rule::Register(reg) => try_opt!(regs.get(reg)),
+                Rule::Expression(expr) => {
+                    *(try_opt!(expr::evaluate(expr, regs, Some(cfa))) as *const usize)
+                }
+                Rule::ValExpression(expr) => try_opt!(expr::evaluate(expr, regs, Some(cfa))),
+            };
+            caller.regs[slot] = value;
+        }
+
+        // By definition the CFA is the value of the stack pointer at the call
+        // site in the caller
+        caller.set_sp(cfa);
+
+        let ra = try_opt!(super::arch::slot(self.cie.return_address_register));
+        if let Rule::Undefined = row.rules[ra] {
+            // Outermost frame
+            return None;
+        }
+        let ip = caller.regs[ra];
+        if ip == 0 {
+            return None;
+        }
+        caller.set_ip(ip);
+
+        Some(caller)
+    }
+
+    /// Runs the call frame instructions up to `pc`
+    unsafe fn row(&self, pc: usize) -> Option<Row> {
+        let mut row = Row {
+            cfa: CfaRule::RegisterOffset(0, 0),
+            rules: [Rule::SameValue; NREGS],
+        };
+        let mut stack = [row; STATE_STACK_SIZE];
+        let mut depth = 0;
+
+        let cie = &self.cie;
+        try_opt!(execute(cie, cie.instructions, cie.end, None, &mut row, &mut stack,
+                         &mut depth, self.pc_begin, !0));
+        let initial = row;
+        try_opt!(execute(cie, self.instructions, self.end, Some(&initial), &mut row,
+                         &mut stack, &mut depth, self.pc_begin, pc));
+
+        Some(row)
+    }
+}
+
+// Executes the instructions in `ptr..end`, stopping once the location passes
+// `pc`. `initial` is the row after the CIE's instructions ran, which
+// DW_CFA_restore goes back to.
+unsafe fn execute(cie: &Cie,
+                  ptr: *const u8,
+                  end: *const u8,
+                  initial: Option<&Row>,
+                  row: &mut Row,
+                  stack: &mut [Row; STATE_STACK_SIZE],
+                  depth: &mut usize,
+                  mut location: usize,
+                  pc: usize)
+                  -> Option<()> {
+    let mut reader = DwarfReader::new(ptr);
+
+    // Register numbers we don't track (e.g. vector registers, which are
+    // caller-saved anyway) are ignored
+    fn set(row: &mut Row, reg: u64, rule: Rule) {
+        if let Some(slot) = super::arch::slot(reg as u16) {
+            row.rules[slot] = rule;
+        }
+    }
+
+    fn restore(row: &mut Row, initial: Option<&Row>, reg: u64) {
+        if let Some(slot) = super::arch::slot(reg as u16) {
+            row.rules[slot] = initial.map(|initial| initial.rules[slot])
+                                     .unwrap_or(Rule::SameValue);
+        }
+    }
+
+    let data_alignment = cie.data_alignment;
+    let code_alignment = cie.code_alignment as usize;
+
+    while reader.ptr < end {
+        let op = reader.read::<u8>();
+
+        let advance = match op & 0xc0 {
+            DW_CFA_advance_loc => Some((op & 0x3f) as usize * code_alignment),
+            DW_CFA_offset => {
+                let offset = reader.read_uleb128() as i64 * data_alignment;
+                set(row, (op & 0x3f) as u64, Rule::Offset(offset));
+                None
+            }
+            DW_CFA_restore => {
+                restore(row, initial, (op & 0x3f) as u64);
+                None
+            }
+            _ => {
+                match op {
+                    DW_CFA_nop => None,
+                    DW_CFA_set_loc => {
+                        let address = eh::read_encoded_pointer(&mut reader,
+                                                               &context(),
+                                                               cie.fde_encoding);
+                        location = address;
+                        if location > pc {
+                            return Some(());
+                        }
+                        None
+                    }
+                    DW_CFA_advance_loc1 => {
+                        Some(reader.read::<u8>() as usize * code_alignment)
+                    }
+                    DW_CFA_advance_loc2 => {
+                        Some(reader.read::<u16>() as usize * code_alignment)
+                    }
+                    DW_CFA_advance_loc4 => {
+                        Some(reader.read::<u32>() as usize * code_alignment)
+                    }
+                    DW_CFA_offset_extended => {
+                        let reg = reader.read_uleb128();
+                        let offset = reader.read_uleb128() as i64 * data_alignment;
+                        set(row, reg, Rule::Offset(offset));
+                        None
+                    }
+                    DW_CFA_offset_extended_sf => {
+                        let reg = reader.read_uleb128();
+                        let offset = reader.read_sleb128() * data_alignment;
+                        set(row, reg, Rule::Offset(offset));
+                        None
+                    }
+                    DW_CFA_GNU_negative_offset_extended => {
+                        let reg = reader.read_uleb128();
+                        let offset = -(reader.read_uleb128() as i64) * data_alignment;
+                        set(row, reg, Rule::Offset(offset));
+                        None
+                    }
+                    DW_CFA_val_offset => {
+                        let reg = reader.read_uleb128();
+                        let offset = reader.read_uleb128() as i64 * data_alignment;
+                        set(row, reg, Rule::ValOffset(offset));
+                        None
+                    }
+                    DW_CFA_val_offset_sf => {
+                        let reg = reader.read_uleb128();
+                        let offset = reader.read_sleb128() * data_alignment;
+                        set(row, reg, Rule::ValOffset(offset));
+                        None
+                    }
+                    DW_CFA_restore_extended => {
+                        let reg = reader.read_uleb128();
+                        restore(row, initial, reg);
+                        None
+                    }
+                    DW_CFA_undefined => {
+                        let reg = reader.read_uleb128();
+                        set(row, reg, Rule::Undefined);
+                        None
+                    }
+                    DW_CFA_same_value => {
+                        let reg = reader.read_uleb128();
+                        set(row, reg, Rule::SameValue);
+                        None
+                    }
+                    DW_CFA_register => {
+                        let reg = reader.read_uleb128();
+                        let other = reader.read_uleb128();
+                        set(row, reg, Rule::Register(other as u16));
+                        None
+                    }
+                    DW_CFA_remember_state => {
+                        if *depth == STATE_STACK_SIZE {
+                            return None;
+                        }
+                        stack[*depth] = *row;
+                        *depth += 1;
+                        None
+                    }
+                    DW_CFA_restore_state => {
+                        if *depth == 0 {
+                            return None;
+                        }
+                        *depth -= 1;
+                        // The CFA isn't part of the remembered state
+                        let cfa = row.cfa;
+                        *row = stack[*depth];
+                        row.cfa = cfa;
+                        None
+                    }
+                    DW_CFA_def_cfa => {
+                        let reg = reader.read_uleb128() as u16;
+                        let offset = reader.read_uleb128() as i64;
+                        row.cfa = CfaRule::RegisterOffset(reg, offset);
+                        None
+                    }
+                    DW_CFA_def_cfa_sf => {
+                        let reg = reader.read_uleb128() as u16;
+                        let offset = reader.read_sleb128() * data_alignment;
+                        row.cfa = CfaRule::RegisterOffset(reg, offset);
+                        None
+                    }
+                    DW_CFA_def_cfa_register => {
+                        let reg = reader.read_uleb128() as u16;
+                        match row.cfa {
+                            CfaRule::RegisterOffset(_, offset) => {
+                                row.cfa = CfaRule::RegisterOffset(reg, offset)
+                            }
+                            CfaRule::Expression(_) => return None,
+                        }
+                        None
+                    }
+                    DW_CFA_def_cfa_offset => {
+                        let offset = reader.read_uleb128() as i64;
+                        match row.cfa {
+                            CfaRule::RegisterOffset(reg, _) => {
+                                row.cfa = CfaRule::RegisterOffset(reg, offset)
+                            }
+                            CfaRule::Expression(_) => return None,
+                        }
+                        None
+                    }
+                    DW_CFA_def_cfa_offset_sf => {
+                        let offset = reader.read_sleb128() * data_alignment;
+                        match row.cfa {
+                            CfaRule::RegisterOffset(reg, _) => {
+                                row.cfa = CfaRule::RegisterOffset(reg, offset)
+                            }
+                            CfaRule::Expression(_) => return None,
+                        }
+                        None
+                    }
+                    DW_CFA_def_cfa_expression => {
+                        row.cfa = CfaRule::Expression(reader.ptr);
+                        skip_block(&mut reader);
+                        None
+                    }
+                    DW_CFA_expression => {
+                        let reg = reader.read_uleb128();
+                        set(row, reg, Rule::Expression(reader.ptr));
+                        skip_block(&mut reader);
+                        None
+                    }
+                    DW_CFA_val_expression => {
+                        let reg = reader.read_uleb128();
+                        set(row, reg, Rule::ValExpression(reader.ptr));
+                        skip_block(&mut reader);
+                        None
+                    }
+                    DW_CFA_GNU_args_size => {
+                        reader.read_uleb128();
+                        None
+                    }
+                    // Only meaningful on SPARC and for AArch64 pointer
+                    // authentication, neither of which we support
+                    DW_CFA_GNU_window_save => None,
+                    _ => return None,
+                }
+            }
+        };
+
+        if let Some(delta) = advance {
+            location = location.wrapping_add(delta);
+            if location > pc {
+                return Some(());
+            }
+        }
+    }
+
+    Some(())
+}
+
+unsafe fn skip_block(reader: &mut DwarfReader) {
+    let length = reader.read_uleb128();
+    reader.ptr = reader.ptr.offset(length as isize);
+}
+
+impl _Unwind_Context {
+    unsafe fn new(regs: &Registers) -> _Unwind_Context {
+        _Unwind_Context {
+            regs: *regs,
+            lsda: 0,
+            func_start: 0,
+        }
+    }
+
+    // Finds the FDE of the current frame, and makes its LSDA available to
+    // the personality routine.
+    unsafe fn fde(&mut self) -> Option<Fde> {
+        // The instruction pointer is a return address which may point past
+        // the end of the calling function, if the call was its last
+        // instruction.
+        let fde = try_opt!(Fde::find(self.regs.ip() - 1));
+        self.lsda = fde.lsda;
+        self.func_start = fde.pc_begin;
+        Some(fde)
+    }
+}
+
+#[inline(never)]
+pub unsafe fn _Unwind_RaiseException(exception: *mut _Unwind_Exception)
+                                     -> _Unwind_Reason_Code {
+    return save(raise, exception);
+
+    extern "C" fn raise(regs: &mut Registers,
+                        exception: *mut _Unwind_Exception)
+                        -> _Unwind_Reason_Code {
+        unsafe {
+            // Phase 1: look for a frame that catches the exception
+            let mut context = _Unwind_Context::new(regs);
+            loop {
+                let fde = match context.fde() {
+                    Some(fde) => fde,
+                    None => return _URC_END_OF_STACK,
+                };
+
+                if fde.cie.personality != 0 {
+                    let personality: _Unwind_Personality_Fn =
+                        mem::transmute(fde.cie.personality);
+                    match personality(1,
+                                      _UA_SEARCH_PHASE,
+                                      (*exception).exception_class,
+                                      exception,
+                                      &mut context) {
+                        _URC_CONTINUE_UNWIND => {}
+                        _URC_HANDLER_FOUND => break,
+                        _ => return _URC_FATAL_PHASE1_ERROR,
+                    }
+                }
+
+                context.regs = match fde.unwind(&context.regs) {
+                    Some(regs) => regs,
+                    None => return _URC_END_OF_STACK,
+                };
+            }
+
+            // Phase 2 stops at this frame, which we identify by its stack
+            // pointer
+            (*exception).private[0] = 0;
+            (*exception).private[1] = context.regs.sp();
+
+            unwind_phase2(regs, exception)
+        }
+    }
+}
+
+// Runs the landing pads of the frames between `regs` and the handler frame
+// phase 1 found; only returns if something went wrong.
+unsafe fn unwind_phase2(regs: &Registers,
+                        exception: *mut _Unwind_Exception)
+                        -> _Unwind_Reason_Code {
+    let mut context = _Unwind_Context::new(regs);
+    loop {
+        let fde = match context.fde() {
+            Some(fde) => fde,
+            None => return _URC_FATAL_PHASE2_ERROR,
+        };
+
+        let handler_frame = context.regs.sp() == (*exception).private[1];
+        if fde.cie.personality != 0 {
+            let personality: _Unwind_Personality_Fn = mem::transmute(fde.cie.personality);
+            let actions = if handler_frame {
+                _UA_CLEANUP_PHASE | _UA_HANDLER_FRAME
+            } else {
+                _UA_CLEANUP_PHASE
+            };
+            match personality(1,
+                              actions,
+                              (*exception).exception_class,
+                              exception,
+                              &mut context) {
+                _URC_CONTINUE_UNWIND => {}
+                _URC_INSTALL_CONTEXT => restore(&context.regs),
+                _ => return _URC_FATAL_PHASE2_ERROR,
+            }
+        }
+
+        // The handler frame must not let the exception through
+        if handler_frame {
+            return _URC_FATAL_PHASE2_ERROR;
+        }
+
+        context.regs = match fde.unwind(&context.regs) {
+            Some(regs) => regs,
+            None => return _URC_FATAL_PHASE2_ERROR,
+        };
+    }
+}
+
+// Called by the compiler generated landing pads once they are done with the
+// cleanup, to continue unwinding
+#[cfg(not(test))]
+#[inline(never)]
+#[no_mangle]
+pub unsafe extern "C" fn _Unwind_Resume(exception: *mut _Unwind_Exception) -> ! {
+    save(resume, exception);
+    ::intrinsics::abort();
+
+    extern "C" fn resume(regs: &mut Registers,
+                         exception: *mut _Unwind_Exception)
+                         -> _Unwind_Reason_Code {
+        unsafe { unwind_phase2(regs, exception) }
+    }
+}
+
+#[inline(never)]
+pub unsafe fn _Unwind_Backtrace(trace: _Unwind_Trace_Fn,
+                                trace_argument: *mut c_void)
+                                -> _Unwind_Reason_Code {
+    let mut tracer = Tracer {
+        trace: trace,
+        argument: trace_argument,
+    };
+    return save(backtrace, &mut tracer as *mut Tracer as *mut _Unwind_Exception);
+
+    extern "C" fn backtrace(regs: &mut Registers,
+                            tracer: *mut _Unwind_Exception)
+                            -> _Unwind_Reason_Code {
+        unsafe {
+            let tracer = &*(tracer as *const Tracer);
+            let mut context = _Unwind_Context::new(regs);
+            loop {
+                let fde = context.fde();
+                if (tracer.trace)(&mut context, tracer.argument) != _URC_NO_REASON {
+                    return _URC_FATAL_PHASE1_ERROR;
+                }
+
+                context.regs = match fde.and_then(|fde| fde.unwind(&context.regs)) {
+                    Some(regs) => regs,
+                    None => return _URC_END_OF_STACK,
+                };
+            }
+        }
+    }
+}
+
+pub unsafe fn _Unwind_GetGR(ctx: *mut _Unwind_Context, reg_index: c_int) -> _Unwind_Word {
+    (*ctx).regs.get(reg_index as u16).unwrap_or(0)
+}
+
+pub unsafe fn _Unwind_SetGR(ctx: *mut _Unwind_Context, reg_index: c_int, value: _Unwind_Word) {
+    (*ctx).regs.set(reg_index as u16, value);
+}
+
+pub unsafe fn _Unwind_GetIP(ctx: *mut _Unwind_Context) -> _Unwind_Word {
+    (*ctx).regs.ip()
+}
+
+pub unsafe fn _Unwind_SetIP(ctx: *mut _Unwind_Context, value: _Unwind_Word) {
+    (*ctx).regs.set_ip(value)
+}
+
+pub unsafe fn _Unwind_GetLanguageSpecificData(ctx: *mut _Unwind_Context) -> *mut u8 {
+    (*ctx).lsda as *mut u8
+}
+
+pub unsafe fn _Unwind_GetRegionStart(ctx: *mut _Unwind_Context) -> _Unwind_Ptr {
+    (*ctx).func_start
+}
@@ -0,0 +1,564 @@
+//! Unwinding using the ARM Exception Handling ABI
+//!
+//! On ARM, frames are described by the `.ARM.exidx` index table, a sorted
+//! list of (function start, unwind entry) pairs, and the `.ARM.extab` section
+//! that holds the entries that don't fit in the index table. Unwinding a
+//! frame is done by interpreting a small byte code that undoes the function's
+//! prologue. See "Exception Handling ABI for the ARM Architecture":
+//! http://infocenter.arm.com/help/topic/com.arm.doc.ihi0038b/IHI0038B_ehabi.pdf
+//!
+//! Unlike with DWARF unwinding, the personality routine is responsible for
+//! unwinding its frame, and finds everything it needs in the exception
+//! object. This module also provides the personality routines for frames
+//! without landing pads, `__aeabi_unwind_cpp_pr{0,1,2}`.
+
+use ctypes::{c_int, c_void};
+use linux::elf::PT_ARM_EXIDX;
+use mem;
+use sys::phdr;
+
+use super::*;
+use super::arch::{IP, LR, SP, VFP};
+
+pub type _Unwind_State = c_int;
+
+pub const _US_VIRTUAL_UNWIND_FRAME: c_int = 0;
+pub const _US_UNWIND_FRAME_STARTING: c_int = 1;
+pub const _US_UNWIND_FRAME_RESUME: c_int = 2;
+pub const _US_ACTION_MASK: c_int = 3;
+pub const _US_FORCE_UNWIND: c_int = 8;
+pub const _US_END_OF_STACK: c_int = 16;
+
+// The personality routines stash a pointer to the exception object in r12,
+// see `rust_eh_personality`
+pub const UNWIND_POINTER_REG: c_int = 12;
+
+pub type _Unwind_Personality_Fn = extern "C" fn(state: _Unwind_State,
+                                                exception_object: *mut _Unwind_Exception,
+                                                context: *mut _Unwind_Context)
+                                                -> _Unwind_Reason_Code;
+
+pub struct _Unwind_Context {
+    regs: Registers,
+}
+
+const EXIDX_CANTUNWIND: u32 = 1;
+
+// The unwinding control block (`_Unwind_Control_Block`), which is what
+// `_Unwind_Exception` is on ARM
+#[repr(C)]
+struct ControlBlock {
+    exception_class: u64,
+    exception_cleanup: usize,
+    unwinder_cache: [u32; 5],
+    barrier_cache: [u32; 6],
+    cleanup_cache: [u32; 4],
+    pr_cache: PrCache,
+}
+
+#[repr(C)]
+struct PrCache {
+    // Start of the function being unwound
+    fnstart: usize,
+    // Its unwind entry, either in `.ARM.exidx` or `.ARM.extab`
+    ehtp: *const u32,
+    // Bit 0 is set if `ehtp` points into `.ARM.exidx`
+    additional: u32,
+    reserved1: u32,
+}
+
+unsafe fn control_block(exception: *mut _Unwind_Exception) -> *mut ControlBlock {
+    debug_assert!(mem::size_of::<ControlBlock>() <= mem::size_of::<_Unwind_Exception>());
+    exception as *mut ControlBlock
+}
+
+// Decodes a 31-bit place-relative offset
+unsafe fn prel31(ptr: *const u32) -> usize {
+    let offset = ((*ptr << 1) as i32 >> 1) as isize;
+    (ptr as isize).wrapping_add(offset) as usize
+}
+
+// Finds the index table entry that covers `pc`, caches it in the control
+// block and returns the personality routine of the frame.
+unsafe fn find_entry(pc: usize,
+                     exception: *mut _Unwind_Exception)
+                     -> Option<_Unwind_Personality_Fn> {
+    let exidx = try_opt!(phdr::segment(PT_ARM_EXIDX));
+    let table = exidx.as_ptr() as *const [u32; 2];
+    let entries = exidx.len() / mem::size_of::<[u32; 2]>();
+
+    // Find the last entry whose function doesn't start after `pc`
+    let (mut lo, mut hi) = (0, entries);
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if prel31(&(*table.offset(mid as isize))[0]) <= pc {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo == 0 {
+        return None;
+    }
+    let entry = &*table.offset(lo as isize - 1);
+
+    let ucb = control_block(exception);
+    (*ucb).pr_cache.fnstart = prel31(&entry[0]);
+    if entry[1] == EXIDX_CANTUNWIND {
+        return None;
+    }
+    if entry[1] & (1 << 31) == 0 {
+        (*ucb).pr_cache.ehtp = prel31(&entry[1]) as *const u32;
+        (*ucb).pr_cache.additional = 0;
+    } else {
+        // Compact entry inlined into the index table
+        (*ucb).pr_cache.ehtp = &entry[1];
+        (*ucb).pr_cache.additional = 1;
+    }
+
+    let ehtp = (*ucb).pr_cache.ehtp;
+    if *ehtp & (1 << 31) == 0 {
+        // Generic model: the first word is the personality routine
+        Some(mem::transmute(prel31(ehtp)))
+    } else {
+        // Compact model: one of the routines the EHABI defines
+        match (*ehtp >> 24) & 0xf {
+            0 => Some(__aeabi_unwind_cpp_pr0),
+            1 => Some(__aeabi_unwind_cpp_pr1),
+            2 => Some(__aeabi_unwind_cpp_pr2),
+            _ => None,
+        }
+    }
+}
+
+// The return address (the saved pc) points *after* the call, which may be
+// past the end of the calling function if the call was its last instruction
+fn lookup_pc(regs: &Registers) -> usize {
+    (regs.ip() & !1) - 1
+}
+
+#[inline(never)]
+pub unsafe fn _Unwind_RaiseException(exception: *mut _Unwind_Exception)
+                                     -> _Unwind_Reason_Code {
+    return save(raise, exception);
+
+    extern "C" fn raise(regs: &mut Registers,
+                        exception: *mut _Unwind_Exception)
+                        -> _Unwind_Reason_Code {
+        unsafe {
+            // Phase 1: look for a frame that catches the exception. The
+            // personality routines unwind the frames they don't handle.
+            let mut context = _Unwind_Context { regs: *regs };
+            loop {
+                let personality = match find_entry(lookup_pc(&context.regs), exception) {
+                    Some(personality) => personality,
+                    None => return _URC_END_OF_STACK,
+                };
+
+                match personality(_US_VIRTUAL_UNWIND_FRAME, exception, &mut context) {
+                    _URC_CONTINUE_UNWIND => {}
+                    _URC_HANDLER_FOUND => break,
+                    _ => return _URC_FAILURE,
+                }
+            }
+
+            unwind_phase2(regs, exception, _US_UNWIND_FRAME_STARTING)
+        }
+    }
+}
+
+// Runs the landing pads of the frames starting at `regs` until one of them
+// catches the exception; only returns if something went wrong. `state` is the
+// state the personality routine of the first frame is called with.
+unsafe fn unwind_phase2(regs: &Registers,
+                        exception: *mut _Unwind_Exception,
+                        mut state: _Unwind_State)
+                        -> _Unwind_Reason_Code {
+    let mut context = _Unwind_Context { regs: *regs };
+    loop {
+        let personality = match find_entry(lookup_pc(&context.regs), exception) {
+            Some(personality) => personality,
+            None => return _URC_FAILURE,
+        };
+
+        match personality(state, exception, &mut context) {
+            _URC_CONTINUE_UNWIND => {}
+            _URC_INSTALL_CONTEXT => restore(&context.regs),
+            _ => return _URC_FAILURE,
+        }
+
+        state = _US_UNWIND_FRAME_STARTING;
+    }
+}
+
+// Called by the compiler generated landing pads once they are done with the
+// cleanup, to continue unwinding
+#[cfg(not(test))]
+#[inline(never)]
+#[no_mangle]
+pub unsafe extern "C" fn _Unwind_Resume(exception: *mut _Unwind_Exception) -> ! {
+    save(resume, exception);
+    ::intrinsics::abort();
+
+    extern "C" fn resume(regs: &mut Registers,
+                         exception: *mut _Unwind_Exception)
+                         -> _Unwind_Reason_Code {
+        // The frame that called us is the one whose landing pad just ran, its
+        // personality routine picks up where it left off
+        unsafe { unwind_phase2(regs, exception, _US_UNWIND_FRAME_RESUME) }
+    }
+}
+
+#[inline(never)]
+pub unsafe fn _Unwind_Backtrace(trace: _Unwind_Trace_Fn,
+                                trace_argument: *mut c_void)
+                                -> _Unwind_Reason_Code {
+    let mut tracer = Tracer {
+        trace: trace,
+        argument: trace_argument,
+    };
+    return save(backtrace, &mut tracer as *mut Tracer as *mut _Unwind_Exception);
+
+    extern "C" fn backtrace(regs: &mut Registers,
+                            tracer: *mut _Unwind_Exception)
+                            -> _Unwind_Reason_Code {
+        unsafe {
+            let tracer = &*(tracer as *const Tracer);
+            // The personality routines keep their state in the control block
+            // so we need one, even though there's no exception
+            let mut exception = _Unwind_Exception {
+                exception_class: 0,
+                exception_cleanup: no_cleanup,
+                private: [0; unwinder_private_data_size],
+            };
+            let mut context = _Unwind_Context { regs: *regs };
+            loop {
+                if (tracer.trace)(&mut context, tracer.argument) != _URC_NO_REASON {
+                    return _URC_FAILURE;
+                }
+
+                if context.regs.ip() & !1 == 0 {
+                    return _URC_END_OF_STACK;
+                }
+                let personality = match find_entry(lookup_pc(&context.regs), &mut exception) {
+                    Some(personality) => personality,
+                    None => return _URC_END_OF_STACK,
+                };
+
+                match personality(_US_VIRTUAL_UNWIND_FRAME | _US_FORCE_UNWIND,
+                                  &mut exception,
+                                  &mut context) {
+                    _URC_CONTINUE_UNWIND => {}
+                    _ => return _URC_END_OF_STACK,
+                }
+            }
+        }
+    }
+
+    extern "C" fn no_cleanup(_unwind_code: _Unwind_Reason_Code,
+                             _exception: *mut _Unwind_Exception) {
+    }
+}
+
+/// Unwinds the frame of a generic model entry, whose unwind instructions
+/// follow the personality routine
+pub unsafe fn __gnu_unwind_frame(exception: *mut _Unwind_Exception,
+                                 context: *mut _Unwind_Context)
+                                 -> _Unwind_Reason_Code {
+    let ptr = (*control_block(exception)).pr_cache.ehtp.offset(1);
+    let mut opcodes = Opcodes {
+        data: *ptr << 8,
+        next: ptr.offset(1),
+        bytes_left: 3,
+        words_left: (*ptr >> 24) & 0xff,
+    };
+    execute(&mut (*context).regs, &mut opcodes)
+}
+
+// Personality routines of the compact model. Frames that use them have no
+// landing pads (we don't support the descriptors that may follow the unwind
+// instructions) so all they do is unwind the frame.
+
+#[cfg_attr(not(test), no_mangle)]
+pub extern "C" fn __aeabi_unwind_cpp_pr0(_state: _Unwind_State,
+                                         exception: *mut _Unwind_Exception,
+                                         context: *mut _Unwind_Context)
+                                         -> _Unwind_Reason_Code {
+    unsafe {
+        let ptr = (*control_block(exception)).pr_cache.ehtp;
+        let mut opcodes = Opcodes {
+            data: *ptr << 8,
+            next: ptr.offset(1),
+            bytes_left: 3,
+            words_left: 0,
+        };
+        compact(&mut (*context).regs, &mut opcodes)
+    }
+}
+
+#[cfg_attr(not(test), no_mangle)]
+pub extern "C" fn __aeabi_unwind_cpp_pr1(_state: _Unwind_State,
+                                         exception: *mut _Unwind_Exception,
+                                         context: *mut _Unwind_Context)
+                                         -> _Unwind_Reason_Code {
+    unsafe { compact_long(exception, context) }
+}
+
+#[cfg_attr(not(test), no_mangle)]
+pub extern "C" fn __aeabi_unwind_cpp_pr2(_state: _Unwind_State,
+                                         exception: *mut _Unwind_Exception,
+                                         context: *mut _Unwind_Context)
+                                         -> _Unwind_Reason_Code {
+    unsafe { compact_long(exception, context) }
+}
+
+// pr1 and pr2 only differ in the descriptors, which we don't support
+unsafe fn compact_long(exception: *mut _Unwind_Exception,
+                       context: *mut _Unwind_Context)
+                       -> _Unwind_Reason_Code {
+    let ptr = (*control_block(exception)).pr_cache.ehtp;
+    let mut opcodes = Opcodes {
+        data: *ptr << 16,
+        next: ptr.offset(1),
+        bytes_left: 2,
+        words_left: (*ptr >> 16) & 0xff,
+    };
+    compact(&mut (*context).regs, &mut opcodes)
+}
+
+unsafe fn compact(regs: &mut Registers, opcodes: &mut Opcodes) -> _Unwind_Reason_Code {
+    match execute(regs, opcodes) {
+        _URC_NO_REASON => _URC_CONTINUE_UNWIND,
+        _ => _URC_FAILURE,
+    }
+}
+
+// A stream of unwind instructions: the remaining bytes of the current word
+// (most significant first) followed by whole words
+struct Opcodes {
+    data: u32,
+    next: *const u32,
+    bytes_left: u32,
+    words_left: u32,
+}
+
+const FINISH: u8 = 0xb0;
+
+impl Opcodes {
+    unsafe fn next(&mut self) -> u8 {
+        if self.bytes_left == 0 {
+            if self.words_left == 0 {
+                return FINISH;
+            }
+            self.words_left -= 1;
+            self.data = *self.next;
+            self.next = self.next.offset(1);
+            self.bytes_left = 3;
+        } else {
+            self.bytes_left -= 1;
+        }
+
+        let byte = (self.data >> 24) as u8;
+        self.data <<= 8;
+        byte
+    }
+}
+
+// Pops the core registers in `mask` (bit n is rn)
+unsafe fn pop_core(regs: &mut Registers, mask: u32) {
+    let mut vsp = regs.regs[SP] as *const usize;
+    for reg in 0..16 {
+        if mask & (1 << reg) != 0 {
+            regs.regs[reg] = *vsp;
+            vsp = vsp.offset(1);
+        }
+    }
+    // Popping sp sets it, rather than the popped values being skipped
+    if mask & (1 << SP) == 0 {
+        regs.regs[SP] = vsp as usize;
+    }
+}
+
+// Pops `count` VFP registers starting at d`first`. Only d8 - d15 are
+// callee-saved, the rest are skipped. `fstmx` registers were saved with
+// FSTMFDX, which stores an additional word.
+unsafe fn pop_vfp(regs: &mut Registers, first: u32, count: u32, fstmx: bool) {
+    let vsp = regs.regs[SP];
+    for i in 0..count {
+        let reg = first + i;
+        if reg >= 8 && reg < 16 {
+            let slot = VFP + 2 * (reg as usize - 8);
+            let value = (vsp + 8 * i as usize) as *const usize;
+            regs.regs[slot] = *value;
+            regs.regs[slot + 1] = *value.offset(1);
+        }
+    }
+    regs.regs[SP] = vsp + 8 * count as usize + if fstmx { 4 } else { 0 };
+}
+
+// Executes unwind instructions (EHABI Section 10.3) until the "finish"
+// instruction
+unsafe fn execute(regs: &mut Registers, opcodes: &mut Opcodes) -> _Unwind_Reason_Code {
+    let mut set_pc = false;
+
+    loop {
+        let op = opcodes.next();
+
+        if op & 0x80 == 0 {
+            // 00xxxxxx: vsp += (xxxxxx << 2) + 4
+            // 01xxxxxx: vsp -= (xxxxxx << 2) + 4
+            let offset = (((op & 0x3f) as usize) << 2) + 4;
+            if op & 0x40 == 0 {
+                regs.regs[SP] += offset;
+            } else {
+                regs.regs[SP] -= offset;
+            }
+            continue;
+        }
+
+        match op & 0xf0 {
+            0x80 => {
+                // 1000iiii iiiiiiii: pop r4 - r15 under mask
+                let mask = ((op as u32 & 0xf) << 8) | opcodes.next() as u32;
+                if mask == 0 {
+                    // Refuse to unwind
+                    return _URC_FAILURE;
+                }
+                pop_core(regs, mask << 4);
+                if mask & (1 << (IP - 4)) != 0 {
+                    set_pc = true;
+                }
+            }
+            0x90 => {
+                // 1001nnnn: vsp = r[nnnn]
+                let reg = (op & 0xf) as usize;
+                if reg == SP || reg == IP {
+                    return _URC_FAILURE;
+                }
+                regs.regs[SP] = regs.regs[reg];
+            }
+            0xa0 => {
+                // 10100nnn: pop r4 - r[4 + nnn]
+                // 10101nnn: pop r4 - r[4 + nnn], r14
+                let mut mask = (0xff0 >> (7 - (op & 7))) & 0xff0;
+                if op & 0x8 != 0 {
+                    mask |= 1 << LR;
+                }
+                pop_core(regs, mask);
+            }
+            0xb0 => {
+                match op {
+                    FINISH => {
+                        if !set_pc {
+                            regs.regs[IP] = regs.regs[LR];
+                        }
+                        return _URC_NO_REASON;
+                    }
+                    0xb1 => {
+                        // 10110001 0000iiii: pop r0 - r3 under mask
+                        let mask = opcodes.next() as u32;
+                        if mask == 0 || mask & 0xf0 != 0 {
+                            return _URC_FAILURE;
+                        }
+                        pop_core(regs, mask);
+                    }
+                    0xb2 => {
+                        // 10110010 uleb128: vsp += 0x204 + (uleb128 << 2)
+                        let mut value = 0;
+                        let mut shift = 0;
+                        loop {
+                            let byte = opcodes.next();
+                            value |= ((byte & 0x7f) as usize) << shift;
+                            shift += 7;
+                            if byte & 0x80 == 0 {
+                                break;
+                            }
+                        }
+                        regs.regs[SP] += 0x204 + (value << 2);
+                    }
+                    0xb3 => {
+                        // 10110011 sssscccc: pop d[ssss] - d[ssss + cccc],
+                        // saved with FSTMFDX
+                        let byte = opcodes.next() as u32;
+                        pop_vfp(regs, byte >> 4, (byte & 0xf) + 1, true);
+                    }
+                    0xb8...0xbf => {
+                        // 10111nnn: pop d8 - d[8 + nnn], saved with FSTMFDX
+                        pop_vfp(regs, 8, (op as u32 & 0x7) + 1, true);
+                    }
+                    _ => return _URC_FAILURE,
+                }
+            }
+            0xc0 => {
+                match op {
+                    0xc0...0xc5 => {
+                        // 11000nnn: pop wR10 - wR[10 + nnn] (iWMMXt)
+                        regs.regs[SP] += 8 * ((op & 0x7) as usize + 1);
+                    }
+                    0xc6 => {
+                        // 11000110 sssscccc: pop wR[ssss] - wR[ssss + cccc]
+                        let byte = opcodes.next();
+                        regs.regs[SP] += 8 * ((byte & 0xf) as usize + 1);
+                    }
+                    0xc7 => {
+                        // 11000111 0000iiii: pop wCGR0 - wCGR3 under mask
+                        let mask = opcodes.next();
+                        if mask == 0 || mask & 0xf0 != 0 {
+                            return _URC_FAILURE;
+                        }
+                        regs.regs[SP] += 4 * mask.count_ones() as usize;
+                    }
+                    0xc8 => {
+                        // 11001000 sssscccc: pop d[16 + ssss] - d[16 + ssss +
+                        // cccc], saved with VPUSH
+                        let byte = opcodes.next() as u32;
+                        pop_vfp(regs, 16 + (byte >> 4), (byte & 0xf) + 1, false);
+                    }
+                    0xc9 => {
+                        // 11001001 sssscccc: pop d[ssss] - d[ssss + cccc],
+                        // saved with VPUSH
+                        let byte = opcodes.next() as u32;
+                        pop_vfp(regs, byte >> 4, (byte & 0xf) + 1, false);
+                    }
+                    _ => return _URC_FAILURE,
+                }
+            }
+            0xd0 if op & 0x8 == 0 => {
+                // 11010nnn: pop d8 - d[8 + nnn], saved with VPUSH
+                pop_vfp(regs, 8, (op as u32 & 0x7) + 1, false);
+            }
+            _ => return _URC_FAILURE,
+        }
+    }
+}
+
+pub unsafe fn _Unwind_GetGR(ctx: *mut _Unwind_Context, reg_index: c_int) -> _Unwind_Word {
+    (*ctx).regs.get(reg_index as u16).unwrap_or(0)
+}
+
+pub unsafe fn _Unwind_SetGR(ctx: *mut _Unwind_Context, reg_index: c_int, value: _Unwind_Word) {
+    (*ctx).regs.set(reg_index as u16, value);
+}
+
+// The least significant bit of the pc is the Thumb state
+pub unsafe fn _Unwind_GetIP(ctx: *mut _Unwind_Context) -> _Unwind_Word {
+    (*ctx).regs.ip() & !1
+}
+
+pub unsafe fn _Unwind_SetIP(ctx: *mut _Unwind_Context, value: _Unwind_Word) {
+    let thumb_state = (*ctx).regs.ip() & 1;
+    (*ctx).regs.set_ip(value | thumb_state)
+}
+
+pub unsafe fn _Unwind_GetLanguageSpecificData(ctx: *mut _Unwind_Context) -> *mut u8 {
+    let exception = _Unwind_GetGR(ctx, UNWIND_POINTER_REG) as *mut _Unwind_Exception;
+    let ptr = (*control_block(exception)).pr_cache.ehtp;
+    // Skip the personality routine and the unwind instructions
+    let ptr = ptr.offset(1);
+    ptr.offset(((*ptr >> 24) & 0xff) as isize + 1) as *mut u8
+}
+
+pub unsafe fn _Unwind_GetRegionStart(ctx: *mut _Unwind_Context) -> _Unwind_Ptr {
+    let exception = _Unwind_GetGR(ctx, UNWIND_POINTER_REG) as *mut _Unwind_Exception;
+    (*control_block(exception)).pr_cache.fnstart
+}
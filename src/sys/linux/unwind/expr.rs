@@ -0,0 +1,262 @@
+//! Evaluation of DWARF expressions
+//!
+//! Call frame information may describe the CFA or the location of a saved
+//! register with a DWARF expression (DWARF-4: Section 2.5, "DWARF
+//! Expressions"). Only the operations that make sense in that context are
+//! supported.
+
+use panic_unwind::dwarf::DwarfReader;
+
+use super::Registers;
+
+const DW_OP_addr: u8 = 0x03;
+const DW_OP_deref: u8 = 0x06;
+const DW_OP_const1u: u8 = 0x08;
+const DW_OP_const1s: u8 = 0x09;
+const DW_OP_const2u: u8 = 0x0a;
+const DW_OP_const2s: u8 = 0x0b;
+const DW_OP_const4u: u8 = 0x0c;
+const DW_OP_const4s: u8 = 0x0d;
+const DW_OP_const8u: u8 = 0x0e;
+const DW_OP_const8s: u8 = 0x0f;
+const DW_OP_constu: u8 = 0x10;
+const DW_OP_consts: u8 = 0x11;
+const DW_OP_dup: u8 = 0x12;
+const DW_OP_drop: u8 = 0x13;
+const DW_OP_over: u8 = 0x14;
+const DW_OP_pick: u8 = 0x15;
+const DW_OP_swap: u8 = 0x16;
+const DW_OP_rot: u8 = 0x17;
+const DW_OP_abs: u8 = 0x19;
+const DW_OP_and: u8 = 0x1a;
+const DW_OP_div: u8 = 0x1b;
+const DW_OP_minus: u8 = 0x1c;
+const DW_OP_mod: u8 = 0x1d;
+const DW_OP_mul: u8 = 0x1e;
+const DW_OP_neg: u8 = 0x1f;
+const DW_OP_not: u8 = 0x20;
+const DW_OP_or: u8 = 0x21;
+const DW_OP_plus: u8 = 0x22;
+const DW_OP_plus_uconst: u8 = 0x23;
+const DW_OP_shl: u8 = 0x24;
+const DW_OP_shr: u8 = 0x25;
+const DW_OP_shra: u8 = 0x26;
+const DW_OP_xor: u8 = 0x27;
+const DW_OP_bra: u8 = 0x28;
+const DW_OP_eq: u8 = 0x29;
+const DW_OP_ge: u8 = 0x2a;
+const DW_OP_gt: u8 = 0x2b;
+const DW_OP_le: u8 = 0x2c;
+const DW_OP_lt: u8 = 0x2d;
+const DW_OP_ne: u8 = 0x2e;
+const DW_OP_skip: u8 = 0x2f;
+const DW_OP_lit0: u8 = 0x30;
+const DW_OP_lit31: u8 = 0x4f;
+const DW_OP_breg0: u8 = 0x70;
+const DW_OP_breg31: u8 = 0x8f;
+const DW_OP_bregx: u8 = 0x92;
+const DW_OP_deref_size: u8 = 0x94;
+const DW_OP_nop: u8 = 0x96;
+
+const STACK_SIZE: usize = 64;
+
+struct Stack {
+    values: [usize; STACK_SIZE],
+    len: usize,
+}
+
+impl Stack {
+    fn push(&mut self, value: usize) -> Option<()> {
+        if self.len == STACK_SIZE {
+            return None;
+        }
+        self.values[self.len] = value;
+        self.len += 1;
+        Some(())
+    }
+
+    fn pop(&mut self) -> Option<usize> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.values[self.len])
+    }
+
+    // `n`th entry from the top, 0 being the top
+    fn peek(&self, n: usize) -> Option<usize> {
+        if n >= self.len {
+            return None;
+        }
+        Some(self.values[self.len - 1 - n])
+    }
+}
+
+/// Evaluates the expression block (a ULEB128 length followed by that many
+/// bytes of operations) at `block`.
+///
+/// `initial` is pushed onto the stack before evaluation starts; it's the CFA
+/// when evaluating register rules. Returns `None` if the expression is
+/// malformed or uses an unsupported operation.
+pub unsafe fn evaluate(block: *const u8,
+                       regs: &Registers,
+                       initial: Option<usize>)
+                       -> Option<usize> {
+    let mut reader = DwarfReader::new(block);
+    let len = reader.read_uleb128() as usize;
+    let start = reader.ptr;
+    let end = start.offset(len as isize);
+
+    let mut stack = Stack {
+        values: [0; STACK_SIZE],
+        len: 0,
+    };
+    if let Some(value) = initial {
+        try_opt!(stack.push(value));
+    }
+
+    while reader.ptr < end {
+        let op = reader.read::<u8>();
+        match op {
+            DW_OP_addr => try_opt!(stack.push(reader.read::<usize>())),
+            DW_OP_deref => {
+                let address = try_opt!(stack.pop());
+                try_opt!(stack.push(*(address as *const usize)))
+            }
+            DW_OP_deref_size => {
+                let size = reader.read::<u8>();
+                let address = try_opt!(stack.pop());
+                let value = match size {
+                    1 => *(address as *const u8) as usize,
+                    2 => *(address as *const u16) as usize,
+                    4 => *(address as *const u32) as usize,
+                    8 => *(address as *const u64) as usize,
+                    _ => return None,
+                };
+                try_opt!(stack.push(value))
+            }
+            DW_OP_const1u => try_opt!(stack.push(reader.read::<u8>() as usize)),
+            DW_OP_const1s => try_opt!(stack.push(reader.read::<i8>() as usize)),
+            DW_OP_const2u => try_opt!(stack.push(reader.read::<u16>() as usize)),
+            DW_OP_const2s => try_opt!(stack.push(reader.read::<i16>() as usize)),
+            DW_OP_const4u => try_opt!(stack.push(reader.read::<u32>() as usize)),
+            DW_OP_const4s => try_opt!(stack.push(reader.read::<i32>() as usize)),
+            DW_OP_const8u => try_opt!(stack.push(reader.read::<u64>() as usize)),
+            DW_OP_const8s => try_opt!(stack.push(reader.read::<i64>() as usize)),
+            DW_OP_constu => try_opt!(stack.push(reader.read_uleb128() as usize)),
+            DW_OP_consts => try_opt!(stack.push(reader.read_sleb128() as usize)),
+            DW_OP_lit0...DW_OP_lit31 => try_opt!(stack.push((op - DW_OP_lit0) as usize)),
+            DW_OP_breg0...DW_OP_breg31 => {
+                let reg = try_opt!(regs.get((op - DW_OP_breg0) as u16));
+                let offset = reader.read_sleb128() as usize;
+                try_opt!(stack.push(reg.wrapping_add(offset)))
+            }
+            DW_OP_bregx => {
+                let reg = try_opt!(regs.get(reader.read_uleb128() as u16));
+                let offset = reader.read_sleb128() as usize;
+                try_opt!(stack.push(reg.wrapping_add(offset)))
+            }
+            DW_OP_dup => {
+                let value = try_opt!(stack.peek(0));
+                try_opt!(stack.push(value))
+            }
+            DW_OP_drop => {
+                try_opt!(stack.pop());
+            }
+            DW_OP_over => {
+                let value = try_opt!(stack.peek(1));
+                try_opt!(stack.push(value))
+            }
+            DW_OP_pick => {
+                let value = try_opt!(stack.peek(reader.read::<u8>() as usize));
+                try_opt!(stack.push(value))
+            }
+            DW_OP_swap => {
+                let a = try_opt!(stack.pop());
+                let b = try_opt!(stack.pop());
+                try_opt!(stack.push(a));
+                try_opt!(stack.push(b))
+            }
+            DW_OP_rot => {
+                let a = try_opt!(stack.pop());
+                let b = try_opt!(stack.pop());
+                let c = try_opt!(stack.pop());
+                try_opt!(stack.push(a));
+                try_opt!(stack.push(c));
+                try_opt!(stack.push(b))
+            }
+            DW_OP_abs => {
+                let a = try_opt!(stack.pop()) as isize;
+                try_opt!(stack.push(a.wrapping_abs() as usize))
+            }
+            DW_OP_neg => {
+                let a = try_opt!(stack.pop()) as isize;
+                try_opt!(stack.push(a.wrapping_neg() as usize))
+            }
+            DW_OP_not => {
+                let a = try_opt!(stack.pop());
+                try_opt!(stack.push(!a))
+            }
+            DW_OP_plus_uconst => {
+                let a = try_opt!(stack.pop());
+                try_opt!(stack.push(a.wrapping_add(reader.read_uleb128() as usize)))
+            }
+            DW_OP_and | DW_OP_div | DW_OP_minus | DW_OP_mod | DW_OP_mul |
+            DW_OP_or | DW_OP_plus | DW_OP_shl | DW_OP_shr | DW_OP_shra |
+            DW_OP_xor | DW_OP_eq | DW_OP_ge | DW_OP_gt | DW_OP_le |
+            DW_OP_lt | DW_OP_ne => {
+                let b = try_opt!(stack.pop());
+                let a = try_opt!(stack.pop());
+                try_opt!(stack.push(try_opt!(binary(op, a, b))))
+            }
+            DW_OP_skip => {
+                let offset = reader.read::<i16>();
+                reader.ptr = reader.ptr.offset(offset as isize);
+            }
+            DW_OP_bra => {
+                let offset = reader.read::<i16>();
+                if try_opt!(stack.pop()) != 0 {
+                    reader.ptr = reader.ptr.offset(offset as isize);
+                }
+            }
+            DW_OP_nop => {}
+            _ => return None,
+        }
+    }
+
+    stack.pop()
+}
+
+fn binary(op: u8, a: usize, b: usize) -> Option<usize> {
+    let (sa, sb) = (a as isize, b as isize);
+    Some(match op {
+        DW_OP_and => a & b,
+        DW_OP_div => {
+            if sb == 0 {
+                return None;
+            }
+            sa.wrapping_div(sb) as usize
+        }
+        DW_OP_minus => a.wrapping_sub(b),
+        DW_OP_mod => {
+            if b == 0 {
+                return None;
+            }
+            a % b
+        }
+        DW_OP_mul => a.wrapping_mul(b),
+        DW_OP_or => a | b,
+        DW_OP_plus => a.wrapping_add(b),
+        DW_OP_shl => a.wrapping_shl(b as u32),
+        DW_OP_shr => a.wrapping_shr(b as u32),
+        DW_OP_shra => sa.wrapping_shr(b as u32) as usize,
+        DW_OP_xor => a ^ b,
+        DW_OP_eq => (sa == sb) as usize,
+        DW_OP_ge => (sa >= sb) as usize,
+        DW_OP_gt => (sa > sb) as usize,
+        DW_OP_le => (sa <= sb) as usize,
+        DW_OP_lt => (sa < sb) as usize,
+        DW_OP_ne => (sa != sb) as usize,
+        _ => return None,
+    })
+}
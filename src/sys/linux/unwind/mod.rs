@@ -0,0 +1,188 @@
+//! A stack unwinder written in Rust
+//!
+//! This replaces the libgcc_s / libunwind dependency that upstream `std` has.
+//! The interface mirrors the Itanium C++ ABI unwinder (and, on ARM, the ARM
+//! EHABI one) closely enough that the personality routine in `panic_unwind`
+//! could be ported unchanged.
+//!
+//! Frames are described by the unwind tables of our own (statically linked)
+//! executable, which we find through its program headers:
+//!
+//! - `.eh_frame` and its `.eh_frame_hdr` lookup table, on every architecture
+//!   but ARM. See the `dwarf` module.
+//! - `.ARM.exidx` and `.ARM.extab` on ARM. See the `ehabi` module.
+//!
+//! The register state of the frame that starts unwinding is captured, and the
+//! state of a landing pad is installed, by a pair of small assembly routines
+//! per architecture (see the `arch` module).
+
+#![allow(non_camel_case_types)]
+#![allow(non_snake_case)]
+#![allow(non_upper_case_globals)]
+#![allow(dead_code)]
+
+use ctypes::{c_int, c_void};
+
+#[cfg(target_arch = "aarch64")]
+#[path = "aarch64.rs"]
+mod arch;
+
+#[cfg(target_arch = "arm")]
+#[path = "arm.rs"]
+mod arch;
+
+#[cfg(target_arch = "x86")]
+#[path = "x86.rs"]
+mod arch;
+
+#[cfg(target_arch = "x86_64")]
+#[path = "x86_64.rs"]
+mod arch;
+
+#[cfg(not(target_arch = "arm"))]
+mod dwarf;
+#[cfg(not(target_arch = "arm"))]
+mod expr;
+
+#[cfg(target_arch = "arm")]
+mod ehabi;
+
+#[cfg(not(target_arch = "arm"))]
+pub use self::dwarf::*;
+#[cfg(target_arch = "arm")]
+pub use self::ehabi::*;
+
+pub type _Unwind_Word = usize;
+pub type _Unwind_Ptr = usize;
+pub type _Unwind_Exception_Class = u64;
+
+#[repr(C)]
+#[derive(Copy, Clone, PartialEq)]
+pub enum _Unwind_Reason_Code {
+    _URC_NO_REASON = 0,
+    _URC_FOREIGN_EXCEPTION_CAUGHT = 1,
+    _URC_FATAL_PHASE2_ERROR = 2,
+    _URC_FATAL_PHASE1_ERROR = 3,
+    _URC_NORMAL_STOP = 4,
+    _URC_END_OF_STACK = 5,
+    _URC_HANDLER_FOUND = 6,
+    _URC_INSTALL_CONTEXT = 7,
+    _URC_CONTINUE_UNWIND = 8,
+    _URC_FAILURE = 9, // used only by ARM EHABI
+}
+pub use self::_Unwind_Reason_Code::*;
+
+// NOTE The unwinder uses the private words of the exception object to keep
+// state between the two phases of unwinding. On ARM they hold the rest of the
+// EHABI unwinding control block.
+#[cfg(target_arch = "x86")]
+pub const unwinder_private_data_size: usize = 5;
+
+#[cfg(target_arch = "x86_64")]
+pub const unwinder_private_data_size: usize = 6;
+
+#[cfg(target_arch = "arm")]
+pub const unwinder_private_data_size: usize = 20;
+
+#[cfg(target_arch = "aarch64")]
+pub const unwinder_private_data_size: usize = 2;
+
+pub type _Unwind_Exception_Cleanup_Fn = extern "C" fn(unwind_code: _Unwind_Reason_Code,
+                                                      exception: *mut _Unwind_Exception);
+
+#[repr(C)]
+pub struct _Unwind_Exception {
+    pub exception_class: _Unwind_Exception_Class,
+    pub exception_cleanup: _Unwind_Exception_Cleanup_Fn,
+    pub private: [_Unwind_Word; unwinder_private_data_size],
+}
+
+pub type _Unwind_Trace_Fn = extern "C" fn(ctx: *mut _Unwind_Context,
+                                          arg: *mut c_void)
+                                          -> _Unwind_Reason_Code;
+
+// What `_Unwind_Backtrace` passes through `save`, in place of an exception
+struct Tracer {
+    trace: _Unwind_Trace_Fn,
+    argument: *mut c_void,
+}
+
+/// The register state of a stack frame
+///
+/// Registers are stored in the slots `arch::slot` maps their DWARF register
+/// numbers to. The layout is shared with the assembly in the `arch` module.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct Registers {
+    regs: [usize; arch::NREGS],
+}
+
+impl Registers {
+    pub fn get(&self, reg: u16) -> Option<usize> {
+        arch::slot(reg).map(|i| self.regs[i])
+    }
+
+    pub fn set(&mut self, reg: u16, value: usize) -> bool {
+        match arch::slot(reg) {
+            Some(i) => {
+                self.regs[i] = value;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn ip(&self) -> usize {
+        self.regs[arch::IP]
+    }
+
+    pub fn set_ip(&mut self, ip: usize) {
+        self.regs[arch::IP] = ip;
+    }
+
+    pub fn sp(&self) -> usize {
+        self.regs[arch::SP]
+    }
+
+    pub fn set_sp(&mut self, sp: usize) {
+        self.regs[arch::SP] = sp;
+    }
+}
+
+/// Calls `f` with the register state of the caller of `save`, as it will be
+/// once `save` returns.
+///
+/// `f`'s return value is passed through.
+unsafe fn save(f: extern "C" fn(&mut Registers, *mut _Unwind_Exception)
+                                -> _Unwind_Reason_Code,
+               exception: *mut _Unwind_Exception)
+               -> _Unwind_Reason_Code {
+    arch::__steed_unwind_save(f, exception)
+}
+
+/// Installs the register state `regs`, transferring control to its
+/// instruction pointer.
+unsafe fn restore(regs: &Registers) -> ! {
+    arch::__steed_unwind_restore(regs)
+}
+
+pub unsafe fn _Unwind_DeleteException(exception: *mut _Unwind_Exception) {
+    ((*exception).exception_cleanup)(_URC_FOREIGN_EXCEPTION_CAUGHT, exception)
+}
+
+pub unsafe fn _Unwind_GetIPInfo(ctx: *mut _Unwind_Context,
+                                ip_before_insn: *mut c_int)
+                                -> _Unwind_Word {
+    // All our frames are call sites: the instruction pointer is a return
+    // address that points *after* the call.
+    *ip_before_insn = 0;
+    _Unwind_GetIP(ctx)
+}
+
+pub unsafe fn _Unwind_GetTextRelBase(_ctx: *mut _Unwind_Context) -> _Unwind_Ptr {
+    0
+}
+
+pub unsafe fn _Unwind_GetDataRelBase(_ctx: *mut _Unwind_Context) -> _Unwind_Ptr {
+    0
+}
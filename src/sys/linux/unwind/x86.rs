@@ -0,0 +1,75 @@
+use super::{Registers, _Unwind_Exception, _Unwind_Reason_Code};
+
+// DWARF register numbers 0 - 7 are %eax, %ecx, %edx, %ebx, %esp, %ebp, %esi
+// and %edi, 8 is the return address (%eip). Registers[n] is at offset 4 * n.
+pub const NREGS: usize = 9;
+pub const SP: usize = 4;
+pub const IP: usize = 8;
+
+pub fn slot(reg: u16) -> Option<usize> {
+    if (reg as usize) < NREGS {
+        Some(reg as usize)
+    } else {
+        None
+    }
+}
+
+extern "C" {
+    pub fn __steed_unwind_save(f: extern "C" fn(&mut Registers,
+                                                *mut _Unwind_Exception)
+                                                -> _Unwind_Reason_Code,
+                               exception: *mut _Unwind_Exception)
+                               -> _Unwind_Reason_Code;
+    pub fn __steed_unwind_restore(regs: &Registers) -> !;
+}
+
+// Only the callee-saved registers, the stack pointer and the return address
+// are meaningful in the saved state; they describe the caller as it will be
+// once we return.
+global_asm!("
+    .globl __steed_unwind_save
+    .hidden __steed_unwind_save
+    __steed_unwind_save:
+
+    push %ebp
+    mov %esp,%ebp
+    sub $0x38,%esp # Arguments to f at (%esp), Registers at 0x10(%esp)
+
+    mov %ebx,0x1c(%esp)
+    mov %esi,0x28(%esp)
+    mov %edi,0x2c(%esp)
+    mov (%ebp),%eax # caller's %ebp
+    mov %eax,0x24(%esp)
+    lea 8(%ebp),%eax # %esp after we return
+    mov %eax,0x20(%esp)
+    mov 4(%ebp),%eax # return address
+    mov %eax,0x30(%esp)
+
+    lea 0x10(%esp),%eax
+    mov %eax,(%esp) # regs
+    mov 12(%ebp),%eax
+    mov %eax,4(%esp) # exception
+    call *8(%ebp) # f
+
+    leave
+    ret
+
+    .globl __steed_unwind_restore
+    .hidden __steed_unwind_restore
+    __steed_unwind_restore:
+
+    mov 4(%esp),%eax
+    mov 16(%eax),%esp
+    pushl 32(%eax) # popped by the final `ret`
+    pushl 0(%eax)
+
+    mov 4(%eax),%ecx
+    mov 8(%eax),%edx
+    mov 12(%eax),%ebx
+    mov 20(%eax),%ebp
+    mov 24(%eax),%esi
+    mov 28(%eax),%edi
+    pop %eax
+
+    ret
+");
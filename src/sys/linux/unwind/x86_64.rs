@@ -0,0 +1,80 @@
+use super::{Registers, _Unwind_Exception, _Unwind_Reason_Code};
+
+// DWARF register numbers 0 - 15 are %rax, %rdx, %rcx, %rbx, %rsi, %rdi, %rbp,
+// %rsp and %r8 - %r15, 16 is the return address (%rip). Registers[n] is at
+// offset 8 * n.
+pub const NREGS: usize = 17;
+pub const SP: usize = 7;
+pub const IP: usize = 16;
+
+pub fn slot(reg: u16) -> Option<usize> {
+    if (reg as usize) < NREGS {
+        Some(reg as usize)
+    } else {
+        None
+    }
+}
+
+extern "C" {
+    pub fn __steed_unwind_save(f: extern "C" fn(&mut Registers,
+                                                *mut _Unwind_Exception)
+                                                -> _Unwind_Reason_Code,
+                               exception: *mut _Unwind_Exception)
+                               -> _Unwind_Reason_Code;
+    pub fn __steed_unwind_restore(regs: &Registers) -> !;
+}
+
+// Only the callee-saved registers, the stack pointer and the return address
+// are meaningful in the saved state; they describe the caller as it will be
+// once we return.
+global_asm!("
+    .globl __steed_unwind_save
+    .hidden __steed_unwind_save
+    __steed_unwind_save:
+
+    sub $0x98,%rsp # Registers, keeping %rsp 16 byte aligned for the call
+
+    mov %rbx,0x18(%rsp)
+    mov %rbp,0x30(%rsp)
+    mov %r12,0x60(%rsp)
+    mov %r13,0x68(%rsp)
+    mov %r14,0x70(%rsp)
+    mov %r15,0x78(%rsp)
+    lea 0xa0(%rsp),%rax # %rsp after we return
+    mov %rax,0x38(%rsp)
+    mov 0x98(%rsp),%rax # return address
+    mov %rax,0x80(%rsp)
+
+    mov %rdi,%rax # f
+    mov %rsp,%rdi # regs
+                  # exception is already in %rsi
+    call *%rax
+
+    add $0x98,%rsp
+    ret
+
+    .globl __steed_unwind_restore
+    .hidden __steed_unwind_restore
+    __steed_unwind_restore:
+
+    mov 0x38(%rdi),%rsp
+    pushq 0x80(%rdi) # popped by the final `ret`
+
+    mov 0x00(%rdi),%rax
+    mov 0x08(%rdi),%rdx
+    mov 0x10(%rdi),%rcx
+    mov 0x18(%rdi),%rbx
+    mov 0x20(%rdi),%rsi
+    mov 0x30(%rdi),%rbp
+    mov 0x40(%rdi),%r8
+    mov 0x48(%rdi),%r9
+    mov 0x50(%rdi),%r10
+    mov 0x58(%rdi),%r11
+    mov 0x60(%rdi),%r12
+    mov 0x68(%rdi),%r13
+    mov 0x70(%rdi),%r14
+    mov 0x78(%rdi),%r15
+    mov 0x28(%rdi),%rdi
+
+    ret
+");
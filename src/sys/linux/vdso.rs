@@ -0,0 +1,134 @@
+//! Symbol lookup in the vDSO
+//!
+//! The kernel maps a small shared object, the vDSO, into every process. It
+//! implements some system calls, like `clock_gettime`, in user space. We find
+//! its ELF header through `AT_SYSINFO_EHDR` and its symbols through its
+//! dynamic section, much like the kernel's own example does:
+//! Documentation/vDSO/parse_vdso.c
+
+use libc;
+use linux;
+use linux::elf::{ELF_ST_BIND, ELF_ST_TYPE, SHN_UNDEF, STB_GLOBAL, STB_WEAK, STT_FUNC};
+use linux::elf::{PT_DYNAMIC, PT_LOAD, VER_FLG_BASE, VERSYM_VERSION};
+use linux::elf::{DT_HASH, DT_NULL, DT_STRTAB, DT_SYMTAB, DT_VERDEF, DT_VERSYM};
+use linux::elf::{elf_dyn, elf_phdr, elf_sym, elf_verdaux, elf_verdef, elfhdr};
+use ffi::CStr;
+use slice;
+
+/// Returns the address of the function `name`, of version `version`, or
+/// `None` if the vDSO doesn't have it (or there's no vDSO at all).
+pub fn lookup(name: &str, version: &str) -> Option<usize> {
+    unsafe {
+        let vdso = try_opt!(Vdso::new());
+        vdso.lookup(name.as_bytes(), version.as_bytes())
+    }
+}
+
+struct Vdso {
+    // Difference between the addresses the vDSO was linked at and the
+    // addresses it was mapped at
+    load_offset: usize,
+    strtab: *const u8,
+    symtab: &'static [elf_sym],
+    versym: *const u16,
+    verdef: *const elf_verdef,
+}
+
+impl Vdso {
+    unsafe fn new() -> Option<Vdso> {
+        let base = try_opt!(libc::getauxval(linux::AT_SYSINFO_EHDR)) as usize;
+        let ehdr = &*(base as *const elfhdr);
+        let phdrs = slice::from_raw_parts((base + ehdr.e_phoff as usize) as *const elf_phdr,
+                                          ehdr.e_phnum as usize);
+
+        let mut load_offset = None;
+        let mut dynamic = None;
+        for phdr in phdrs {
+            if phdr.p_type == PT_LOAD && load_offset.is_none() {
+                load_offset = Some((base + phdr.p_offset as usize)
+                    .wrapping_sub(phdr.p_vaddr as usize));
+            } else if phdr.p_type == PT_DYNAMIC {
+                dynamic = Some((base + phdr.p_offset as usize) as *const elf_dyn);
+            }
+        }
+        let load_offset = try_opt!(load_offset);
+        let mut dynamic = try_opt!(dynamic);
+
+        let mut strtab = None;
+        let mut symtab = None;
+        let mut hash = None;
+        let mut versym = 0 as *const u16;
+        let mut verdef = 0 as *const elf_verdef;
+        while (*dynamic).d_tag as i64 != DT_NULL {
+            let address = load_offset.wrapping_add((*dynamic).d_val as usize);
+            match (*dynamic).d_tag as i64 {
+                DT_STRTAB => strtab = Some(address as *const u8),
+                DT_SYMTAB => symtab = Some(address as *const elf_sym),
+                DT_HASH => hash = Some(address as *const u32),
+                DT_VERSYM => versym = address as *const u16,
+                DT_VERDEF => verdef = address as *const elf_verdef,
+                _ => {}
+            }
+            dynamic = dynamic.offset(1);
+        }
+        if versym.is_null() {
+            verdef = 0 as *const _;
+        }
+
+        // The number of symbols is the number of entries in the hash table's
+        // chain
+        let nchain = *try_opt!(hash).offset(1) as usize;
+        Some(Vdso {
+            load_offset: load_offset,
+            strtab: try_opt!(strtab),
+            symtab: slice::from_raw_parts(try_opt!(symtab), nchain),
+            versym: versym,
+            verdef: verdef,
+        })
+    }
+
+    unsafe fn lookup(&self, name: &[u8], version: &[u8]) -> Option<usize> {
+        for (i, sym) in self.symtab.iter().enumerate() {
+            let bind = ELF_ST_BIND(sym.st_info);
+            if ELF_ST_TYPE(sym.st_info) != STT_FUNC ||
+               (bind != STB_GLOBAL && bind != STB_WEAK) ||
+               sym.st_shndx == SHN_UNDEF {
+                continue;
+            }
+            if self.string(sym.st_name) != name || !self.match_version(i, version) {
+                continue;
+            }
+            return Some(self.load_offset.wrapping_add(sym.st_value as usize));
+        }
+        None
+    }
+
+    // Checks that the version of symbol number `i` is `version`
+    unsafe fn match_version(&self, i: usize, version: &[u8]) -> bool {
+        // Without versioning information, anything goes
+        if self.verdef.is_null() {
+            return true;
+        }
+
+        let index = *self.versym.offset(i as isize) & VERSYM_VERSION;
+        let mut def = self.verdef;
+        loop {
+            if (*def).vd_flags & VER_FLG_BASE == 0 &&
+               (*def).vd_ndx & VERSYM_VERSION == index {
+                break;
+            }
+            if (*def).vd_next == 0 {
+                // No definition of that version
+                return false;
+            }
+            def = (def as usize + (*def).vd_next as usize) as *const elf_verdef;
+        }
+
+        let aux = (def as usize + (*def).vd_aux as usize) as *const elf_verdaux;
+        self.string((*aux).vda_name) == version
+    }
+
+    unsafe fn string(&self, offset: u32) -> &'static [u8] {
+        CStr::from_ptr(self.strtab.offset(offset as isize) as *const _).to_bytes()
+    }
+}
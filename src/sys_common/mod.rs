@@ -1,3 +1,9 @@
+// Rust 1.16.0
+pub mod at_exit_imp;
+// Rust 1.16.0 (addresses and line numbers as integers)
+pub mod backtrace;
+// Rust 1.17.0
+pub mod condvar;
 // Rust 1.16.0 (no tests)
 pub mod io;
 // Rust 1.17.0
@@ -6,13 +12,21 @@ pub mod mutex;
 pub mod net;
 // Rust 1.17.0
 pub mod poison;
+// Rust 1.16.0
+pub mod remutex;
 // Rust 1.17.0
 pub mod rwlock;
 // Rust 1.15.0
 pub mod thread;
+// Rust 1.16.0
+pub mod thread_info;
 
 pub mod util;
 
+use io;
+use sync::{Once, ONCE_INIT};
+use sys;
+
 /// A trait for viewing representations from std types
 #[doc(hidden)]
 pub trait AsInner<Inner: ?Sized> {
@@ -37,5 +51,27 @@ pub trait FromInner<Inner> {
     fn from_inner(inner: Inner) -> Self;
 }
 
+/// Enqueues a procedure to run when the main thread exits.
+///
+/// Currently these closures are only run once the main *Rust* thread exits.
+/// Once the `at_exit` handlers begin running, more may be enqueued, but not
+/// infinitely so. Eventually a handler registration will be forced to fail.
+///
+/// Returns `Ok` if the handler was successfully registered, meaning that the
+/// closure will be run once the main thread exits. Returns `Err` to indicate
+/// that the closure could not be registered, meaning that it is not scheduled
+/// to be run.
+pub fn at_exit<F: FnOnce() + Send + 'static>(f: F) -> Result<(), ()> {
+    if at_exit_imp::push(Box::new(f)) {Ok(())} else {Err(())}
+}
+
 /// One-time runtime cleanup.
-pub fn cleanup() { }
+pub fn cleanup() {
+    static CLEANUP: Once = ONCE_INIT;
+    CLEANUP.call_once(|| unsafe {
+        io::cleanup();
+        sys::args::cleanup();
+        sys::stack_overflow::cleanup();
+        at_exit_imp::cleanup();
+    });
+}
@@ -48,6 +48,11 @@ impl Flag {
         }
     }
 
+    #[inline]
+    pub fn set(&self) {
+        self.failed.store(true, Ordering::Relaxed);
+    }
+
     #[inline]
     pub fn get(&self) -> bool {
         self.failed.load(Ordering::Relaxed)
@@ -10,6 +10,7 @@
 
 use alloc::boxed::FnBox;
 use libc;
+use sys;
 use sys::stack_overflow;
 
 pub unsafe fn start_thread(main: *mut libc::c_void) {
@@ -18,5 +19,8 @@ pub unsafe fn start_thread(main: *mut libc::c_void) {
     let _handler = stack_overflow::Handler::new();
 
     // Finally, let's run some code.
-    Box::from_raw(main as *mut Box<FnBox()>)()
+    Box::from_raw(main as *mut Box<FnBox()>)();
+
+    // steed: There's no `pthread_exit` to do this for us
+    sys::fast_thread_local::run_dtors();
 }
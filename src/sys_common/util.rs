@@ -1,3 +1,13 @@
+use io::Write;
+use sys::stdio::Stderr;
+use sys_common::thread_info;
+
 pub fn min_stack() -> usize {
     2 * 1024 * 1024 + 1
 }
+
+pub fn report_overflow() {
+    let thread = thread_info::current_thread();
+    let name = thread.as_ref().and_then(|t| t.name()).unwrap_or("<unknown>");
+    let _ = writeln!(Stderr::new(), "\nthread '{}' has overflowed its stack", name);
+}
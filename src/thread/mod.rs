@@ -4,15 +4,44 @@ use any::Any;
 use cell::UnsafeCell;
 use ffi::{CStr, CString};
 use fmt;
-use io;
+use io::{self, Write};
+use panic;
+use panicking;
 use str;
 use sync::Arc;
 use sync::atomic::{AtomicUsize, Ordering};
+use sys;
+use sys::parker::Parker;
+use sys::stdio::Stderr;
 use sys::thread as imp;
+use sys_common::thread_info::{self, NewThread};
 use sys_common::util;
 use sys_common::{AsInner, IntoInner};
 use time::Duration;
 
+////////////////////////////////////////////////////////////////////////////////
+// Thread-local storage
+////////////////////////////////////////////////////////////////////////////////
+
+#[macro_use] mod local;
+
+#[stable(feature = "rust1", since = "1.0.0")]
+pub use self::local::{LocalKey, LocalKeyState};
+
+#[unstable(feature = "libstd_thread_internals", issue = "0")]
+#[doc(hidden)] pub use self::local::fast::Key as __FastLocalKeyInner;
+
+////////////////////////////////////////////////////////////////////////////////
+// Scoped threads
+////////////////////////////////////////////////////////////////////////////////
+
+mod scoped;
+
+#[unstable(feature = "thread_scope", issue = "0")]
+pub use self::scoped::{scope, Scope, ScopedJoinHandle};
+
+use self::scoped::ScopeData;
+
 #[stable(feature = "rust1", since = "1.0.0")]
 #[derive(Debug)]
 pub struct Builder {
@@ -43,6 +72,16 @@ impl Builder {
     #[stable(feature = "rust1", since = "1.0.0")]
     pub fn spawn<F, T>(self, f: F) -> io::Result<JoinHandle<T>> where
         F: FnOnce() -> T, F: Send + 'static, T: Send + 'static
+    {
+        unsafe { self.spawn_unchecked(f, None).map(JoinHandle) }
+    }
+
+    // Like `spawn`, but `f` and its result may borrow from the caller, who has
+    // to make sure the thread is joined before those borrows end. A `scope`,
+    // if any, is told about the thread finishing.
+    unsafe fn spawn_unchecked<'a, F, T>(self, f: F, scope: Option<Arc<ScopeData>>)
+                                        -> io::Result<JoinInner<T>> where
+        F: FnOnce() -> T, F: Send + 'a, T: Send + 'a
     {
         let Builder { name, stack_size } = self;
 
@@ -51,41 +90,46 @@ impl Builder {
         let my_thread = Thread::new(name);
         let their_thread = my_thread.clone();
 
-        let my_packet : Arc<UnsafeCell<Option<Result<T>>>>
-            = Arc::new(UnsafeCell::new(None));
-        let their_packet = my_packet.clone();
+        if let Some(ref scope) = scope {
+            scope.increment_num_running_threads();
+        }
+
+        let my_packet = Packet(Arc::new(PacketInner {
+            scope: scope,
+            result: UnsafeCell::new(None),
+        }));
+        let their_packet = Packet(my_packet.0.clone());
 
         let main = move || {
             if let Some(name) = their_thread.cname() {
                 imp::Thread::set_name(name);
             }
-            unsafe {
-                // TODO(steed, #128): Add guard page to new threads.
-                /*
-                thread_info::set(imp::guard::current(), their_thread);
-                let try_result = panic::catch_unwind(panic::AssertUnwindSafe(f));
-                *their_packet.get() = Some(try_result);
-                */
-                let result = f();
-                *their_packet.get() = Some(Ok(result));
-            }
+            thread_info::set(imp::guard::current(), their_thread);
+            let try_result = panic::catch_unwind(panic::AssertUnwindSafe(f));
+            *their_packet.0.result.get() = Some(try_result);
+            // Dropping `their_packet` notifies the scope if this was the last
+            // reference to the packet
         };
 
-        Ok(JoinHandle(JoinInner {
-            native: unsafe {
-                Some(imp::Thread::new(stack_size, Box::new(main))?)
-            },
+        Ok(JoinInner {
+            native: Some(imp::Thread::new(stack_size, Box::new(main))?),
             thread: my_thread,
-            packet: Packet(my_packet),
-        }))
+            packet: my_packet,
+        })
     }
 }
 
+#[stable(feature = "rust1", since = "1.0.0")]
+pub fn current() -> Thread {
+    thread_info::current_thread().expect("use of std::thread::current() is not \
+                                         possible after the thread's local \
+                                         data has been destroyed")
+}
+
 #[inline]
 #[stable(feature = "rust1", since = "1.0.0")]
 pub fn panicking() -> bool {
-    // TODO(steed, #36): implement panicking::panicking()
-    false
+    panicking::panicking()
 }
 
 #[stable(feature = "rust1", since = "1.0.0")]
@@ -111,6 +155,28 @@ pub fn sleep(dur: Duration) {
     imp::Thread::sleep(dur)
 }
 
+#[stable(feature = "rust1", since = "1.0.0")]
+pub fn park() {
+    let thread = current();
+    unsafe {
+        thread.inner.parker.park();
+    }
+}
+
+#[stable(feature = "rust1", since = "1.0.0")]
+#[rustc_deprecated(since = "1.6.0", reason = "replaced by `std::thread::park_timeout`")]
+pub fn park_timeout_ms(ms: u32) {
+    park_timeout(Duration::from_millis(ms as u64))
+}
+
+#[stable(feature = "park_timeout", since = "1.4.0")]
+pub fn park_timeout(dur: Duration) {
+    let thread = current();
+    unsafe {
+        thread.inner.parker.park_timeout(dur);
+    }
+}
+
 #[unstable(feature = "thread_id", issue = "21507")]
 #[derive(Eq, PartialEq, Copy, Clone)]
 pub struct ThreadId(u64);
@@ -156,6 +222,7 @@ impl fmt::Debug for ThreadId {
 struct Inner {
     name: Option<CString>,      // Guaranteed to be UTF-8
     id: ThreadId,
+    parker: Parker,
 }
 
 #[derive(Clone)]
@@ -174,9 +241,14 @@ impl Thread {
             inner: Arc::new(Inner {
                 name: cname,
                 id: ThreadId::new(),
+                parker: Parker::new(),
             })
         }
     }
+    #[stable(feature = "rust1", since = "1.0.0")]
+    pub fn unpark(&self) {
+        self.inner.parker.unpark();
+    }
     #[unstable(feature = "thread_id", issue = "21507")]
     pub fn id(&self) -> ThreadId {
         self.inner.id
@@ -190,6 +262,10 @@ impl Thread {
     }
 }
 
+impl NewThread for Thread {
+    fn new(name: Option<String>) -> Thread { Thread::new(name) }
+}
+
 #[stable(feature = "rust1", since = "1.0.0")]
 impl fmt::Debug for Thread {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -206,17 +282,46 @@ pub type Result<T> = ::result::Result<T, Box<Any + Send + 'static>>;
 // parent thread never reads this packet until the child has exited).
 //
 // This packet itself is then stored into a `JoinInner` which in turns is placed
-// in `JoinHandle` and `JoinGuard`. Due to the usage of `UnsafeCell` we need to
-// manually worry about impls like Send and Sync. The type `T` should
+// in `JoinHandle` and `ScopedJoinHandle`. Due to the usage of `UnsafeCell` we
+// need to manually worry about impls like Send and Sync. The type `T` should
 // already always be Send (otherwise the thread could not have been created) and
 // this type is inherently Sync because no methods take &self. Regardless,
 // however, we add inheriting impls for Send/Sync to this type to ensure it's
 // Send/Sync and that future modifications will still appropriately classify it.
-struct Packet<T>(Arc<UnsafeCell<Option<Result<T>>>>);
+struct Packet<T>(Arc<PacketInner<T>>);
 
 unsafe impl<T: Send> Send for Packet<T> {}
 unsafe impl<T: Sync> Sync for Packet<T> {}
 
+struct PacketInner<T> {
+    // The scope the thread was spawned in, if it's a scoped thread
+    scope: Option<Arc<ScopeData>>,
+    result: UnsafeCell<Option<Result<T>>>,
+}
+
+impl<T> Drop for PacketInner<T> {
+    fn drop(&mut self) {
+        let result = unsafe { &mut *self.result.get() };
+        // A panic that no one joined the thread for is reported by the scope
+        let unhandled_panic = match *result {
+            Some(Err(_)) => true,
+            _ => false,
+        };
+        // The result may borrow from the scope's environment, so it must be
+        // gone before the scope learns that the thread is done. Dropping it
+        // happens outside of the thread's `catch_unwind`, and unwinding out of
+        // here would leave the scope waiting forever, so a panic in a `Drop`
+        // of the result or of the panic payload is fatal.
+        if panic::catch_unwind(panic::AssertUnwindSafe(|| *result = None)).is_err() {
+            let _ = writeln!(Stderr::new(), "fatal runtime error: thread result panicked on drop");
+            unsafe { sys::abort_internal() }
+        }
+        if let Some(ref scope) = self.scope {
+            scope.decrement_num_running_threads(unhandled_panic);
+        }
+    }
+}
+
 struct JoinInner<T> {
     native: Option<imp::Thread>,
     thread: Thread,
@@ -227,7 +332,7 @@ impl<T> JoinInner<T> {
     fn join(&mut self) -> Result<T> {
         self.native.take().unwrap().join();
         unsafe {
-            (*self.packet.0.get()).take().unwrap()
+            (*self.packet.0.result.get()).take().unwrap()
         }
     }
 }
@@ -264,5 +369,6 @@ impl<T> fmt::Debug for JoinHandle<T> {
 fn _assert_sync_and_send() {
     fn _assert_both<T: Send + Sync>() {}
     _assert_both::<JoinHandle<()>>();
+    _assert_both::<ScopedJoinHandle<'static, ()>>();
     _assert_both::<Thread>();
 }
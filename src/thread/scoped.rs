@@ -0,0 +1,211 @@
+use fmt;
+use io;
+use marker::PhantomData;
+use panic;
+use sync::Arc;
+use sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use sys_common::{AsInner, IntoInner};
+use sys::thread as imp;
+use super::{current, park, Builder, JoinInner, Result, Thread};
+
+/// A scope to spawn threads in that may borrow from outside the scope.
+///
+/// See `scope` for details.
+#[unstable(feature = "thread_scope", issue = "0")]
+pub struct Scope<'env> {
+    data: Arc<ScopeData>,
+    // Invariant over 'env, so the borrows of the threads can't be shortened
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+/// An owned permission to join on a scoped thread (block on its termination).
+///
+/// See `Scope::spawn` for details.
+#[unstable(feature = "thread_scope", issue = "0")]
+pub struct ScopedJoinHandle<'scope, T>(JoinInner<T>, PhantomData<&'scope ()>);
+
+pub struct ScopeData {
+    num_running_threads: AtomicUsize,
+    a_thread_panicked: AtomicBool,
+    main_thread: Thread,
+}
+
+impl ScopeData {
+    pub fn increment_num_running_threads(&self) {
+        // We check for 'overflow' with usize::MAX / 2, to make sure there's no
+        // chance it overflows to 0, which would result in unsoundness.
+        if self.num_running_threads.fetch_add(1, Ordering::Relaxed) > ::usize::MAX / 2 {
+            // This can only reasonably happen by mem::forget()'ing many many
+            // ScopedJoinHandles.
+            self.decrement_num_running_threads(false);
+            panic!("too many running threads in thread scope");
+        }
+    }
+
+    pub fn decrement_num_running_threads(&self, panic: bool) {
+        if panic {
+            self.a_thread_panicked.store(true, Ordering::Relaxed);
+        }
+        if self.num_running_threads.fetch_sub(1, Ordering::Release) == 1 {
+            self.main_thread.unpark();
+        }
+    }
+}
+
+/// Creates a scope for spawning scoped threads.
+///
+/// The function passed to `scope` will be provided a `Scope` object, through
+/// which scoped threads can be spawned. Unlike non-scoped threads, scoped
+/// threads can borrow non-`'static` data, as the scope guarantees all threads
+/// will be joined at the end of the scope.
+///
+/// All threads spawned within the scope that haven't been manually joined
+/// will be automatically joined before this function returns.
+///
+/// # Panics
+///
+/// If any of the automatically joined threads panicked, this function will
+/// panic. If you want to handle panics from spawned threads, `join` them
+/// before the end of the scope.
+///
+/// # Example
+///
+/// ```
+/// #![feature(thread_scope)]
+///
+/// use std::thread;
+///
+/// let mut a = vec![1, 2, 3];
+/// let mut x = 0;
+///
+/// thread::scope(|s| {
+///     s.spawn(|| {
+///         println!("hello from the first scoped thread");
+///         // We can borrow `a` here.
+///         println!("{:?}", a);
+///     });
+///     s.spawn(|| {
+///         println!("hello from the second scoped thread");
+///         // We can even mutably borrow `x` here,
+///         // because no other threads are using it.
+///         x += a[0] + a[2];
+///     });
+///     println!("hello from the main thread");
+/// });
+///
+/// // After the scope, we can modify and access our variables again:
+/// a.push(4);
+/// assert_eq!(x, a.len());
+/// ```
+#[unstable(feature = "thread_scope", issue = "0")]
+pub fn scope<'env, F, T>(f: F) -> T
+    where F: FnOnce(&Scope<'env>) -> T
+{
+    let scope = Scope {
+        data: Arc::new(ScopeData {
+            num_running_threads: AtomicUsize::new(0),
+            a_thread_panicked: AtomicBool::new(false),
+            main_thread: current(),
+        }),
+        env: PhantomData,
+    };
+
+    // Run `f`, but catch panics so we can make sure to wait for all the
+    // threads to join.
+    let result = panic::catch_unwind(panic::AssertUnwindSafe(|| f(&scope)));
+
+    // Wait until all the threads are finished.
+    while scope.data.num_running_threads.load(Ordering::Acquire) != 0 {
+        park();
+    }
+
+    // Throw any panic from `f`, or the return value of `f` if no thread
+    // panicked.
+    match result {
+        Err(e) => panic::resume_unwind(e),
+        Ok(_) if scope.data.a_thread_panicked.load(Ordering::Relaxed) => {
+            panic!("a scoped thread panicked")
+        }
+        Ok(result) => result,
+    }
+}
+
+impl<'env> Scope<'env> {
+    /// Spawns a new thread within a scope, returning a `ScopedJoinHandle` for
+    /// it.
+    ///
+    /// Unlike non-scoped threads, threads spawned with this function may
+    /// borrow non-`'static` data from the outside the scope. See `scope` for
+    /// details.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the OS fails to create a thread; use `Builder::spawn_scoped`
+    /// to recover from such errors.
+    #[unstable(feature = "thread_scope", issue = "0")]
+    pub fn spawn<'scope, F, T>(&'scope self, f: F) -> ScopedJoinHandle<'scope, T>
+        where F: FnOnce() -> T + Send + 'env, T: Send + 'env
+    {
+        Builder::new().spawn_scoped(self, f).expect("failed to spawn thread")
+    }
+}
+
+impl Builder {
+    /// Spawns a new scoped thread using the settings set through this
+    /// `Builder`.
+    ///
+    /// Unlike `Scope::spawn`, this method yields an `io::Result` to capture
+    /// any failure to create the thread at the OS level.
+    #[unstable(feature = "thread_scope", issue = "0")]
+    pub fn spawn_scoped<'scope, 'env, F, T>(self, scope: &'scope Scope<'env>, f: F)
+                                            -> io::Result<ScopedJoinHandle<'scope, T>>
+        where F: FnOnce() -> T + Send + 'env, T: Send + 'env
+    {
+        let inner = unsafe { self.spawn_unchecked(f, Some(scope.data.clone()))? };
+        Ok(ScopedJoinHandle(inner, PhantomData))
+    }
+}
+
+impl<'scope, T> ScopedJoinHandle<'scope, T> {
+    /// Extracts a handle to the underlying thread.
+    #[unstable(feature = "thread_scope", issue = "0")]
+    pub fn thread(&self) -> &Thread {
+        &self.0.thread
+    }
+
+    /// Waits for the associated thread to finish.
+    ///
+    /// If the child thread panics, `Err` is returned with the parameter given
+    /// to `panic`.
+    #[unstable(feature = "thread_scope", issue = "0")]
+    pub fn join(mut self) -> Result<T> {
+        self.0.join()
+    }
+}
+
+impl<'scope, T> AsInner<imp::Thread> for ScopedJoinHandle<'scope, T> {
+    fn as_inner(&self) -> &imp::Thread { self.0.native.as_ref().unwrap() }
+}
+
+impl<'scope, T> IntoInner<imp::Thread> for ScopedJoinHandle<'scope, T> {
+    fn into_inner(self) -> imp::Thread { self.0.native.unwrap() }
+}
+
+#[unstable(feature = "thread_scope", issue = "0")]
+impl<'env> fmt::Debug for Scope<'env> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Scope")
+            .field("num_running_threads",
+                   &self.data.num_running_threads.load(Ordering::Relaxed))
+            .field("a_thread_panicked", &self.data.a_thread_panicked.load(Ordering::Relaxed))
+            .field("main_thread", &self.data.main_thread)
+            .finish()
+    }
+}
+
+#[unstable(feature = "thread_scope", issue = "0")]
+impl<'scope, T> fmt::Debug for ScopedJoinHandle<'scope, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad("ScopedJoinHandle { .. }")
+    }
+}